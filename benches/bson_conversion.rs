@@ -0,0 +1,134 @@
+/*
+    Compares the columnar dataframe_to_documents() against the row by row loop it replaced.
+    The loop is frozen below as it was before the columnar conversion, so later changes to the
+    library do not move the baseline. Runs without MongoDB.
+
+    cargo bench --bench bson_conversion
+*/
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use mongodb::bson::{self, Bson, Document};
+use polars::prelude::*;
+use std::time::Instant;
+
+use molly_db::database::database_service::conversion::dataframe_to_documents;
+use molly_db::database::models::eod_models::OhlcvMetaData;
+
+const ROWS: usize = 250_000;
+const RUNS: u32 = 5;
+
+// the date parsing the row by row loop used
+fn string_to_datetime(date: &str) -> bson::DateTime {
+    match date {
+        _ if date.len() <= 10 => {
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .expect("Could not parse date string in %Y-%m-%d to NativeDate object");
+            let datetime = date.and_hms_opt(0, 0, 0)
+                .expect("Could not convert NativeDate to NativeDateTime object for date string in format %Y-%m-%d");
+            let datetime_utc: DateTime<Utc> = Utc.from_utc_datetime(&datetime);
+            bson::DateTime::from_chrono(datetime_utc)
+        }
+        _ => {
+            let datetime = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").expect(
+                "Could not parse date string in %Y-%m-%d %H:%M:%S to NativeDateTime object",
+            );
+            let datetime_utc: DateTime<Utc> = Utc.from_utc_datetime(&datetime);
+            bson::DateTime::from_chrono(datetime_utc)
+        }
+    }
+}
+
+// the row by row conversion insert_series() used before dataframe_to_documents()
+fn dataframe_to_documents_rowwise(df: &DataFrame) -> Result<Vec<Document>> {
+    let col_names = df.get_column_names();
+    let mut doc_vec = Vec::new();
+    // iterate through each row of df
+    for i in 0..df.height() {
+        let row = df.get_row(i)?;
+        let mut doc_row = Document::new();
+        // iterate through each element within row of df
+        for (i, name) in col_names.iter().enumerate().take(df.width()) {
+            match (*name, row.0.get(i)) {
+                (_, Some(AnyValue::Null)) => {
+                    doc_row.insert(name.to_string(), Bson::Null);
+                }
+                ("open", Some(AnyValue::Float64(number)))
+                | ("high", Some(AnyValue::Float64(number)))
+                | ("low", Some(AnyValue::Float64(number)))
+                | ("close", Some(AnyValue::Float64(number)))
+                | ("adjusted_close", Some(AnyValue::Float64(number))) => {
+                    doc_row.insert(name.to_string(), Bson::Double(*number));
+                }
+                ("volume", Some(AnyValue::Int64(number))) => {
+                    doc_row.insert(name.to_string(), *number);
+                }
+                ("datetime", Some(AnyValue::String(string))) => {
+                    let datetime = string_to_datetime(string);
+                    doc_row.insert(name.to_string(), Bson::DateTime(datetime));
+                }
+                ("date", Some(AnyValue::String(string))) => {
+                    let date = string_to_datetime(string);
+                    doc_row.insert("datetime".to_string(), Bson::DateTime(date));
+                }
+                ("metadata", Some(AnyValue::String(string))) => {
+                    let metadata: OhlcvMetaData = serde_json::from_str(string)?;
+                    let metadata_bson = bson::to_bson(&metadata)?;
+                    doc_row.insert(name.to_string(), metadata_bson);
+                }
+                _ => (),
+            }
+        }
+        doc_vec.push(doc_row);
+    }
+    Ok(doc_vec)
+}
+
+fn synthetic_df() -> DataFrame {
+    let start = NaiveDate::from_ymd_opt(1990, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+    let dates = (0..ROWS)
+        .map(|i| (start + Duration::minutes(i as i64)).format("%Y-%m-%d %H:%M:%S").to_string())
+        .collect::<Vec<String>>();
+    let close = (0..ROWS).map(|i| 100.0 + (i as f64 / 50.0).sin()).collect::<Vec<f64>>();
+    let metadata = serde_json::to_string(&OhlcvMetaData {
+        metadata_collection_name: "equity_spot_1m".to_string(),
+        ticker: "AAPL".to_string(),
+        source: "eod".to_string(),
+        exchange: "US".to_string(),
+        currency: None,
+    })
+    .unwrap();
+    df!(
+        "datetime" => dates,
+        "open" => &close,
+        "high" => &close,
+        "low" => &close,
+        "close" => &close,
+        "adjusted_close" => &close,
+        "volume" => vec![1_000i64; ROWS],
+        "metadata" => vec![metadata; ROWS],
+    )
+    .unwrap()
+}
+
+fn main() {
+    let df = synthetic_df();
+    println!("{} rows x {} columns, best of {} runs", df.height(), df.width(), RUNS);
+
+    let best = |convert: &dyn Fn(&DataFrame) -> usize| {
+        (0..RUNS)
+            .map(|_| {
+                let start = Instant::now();
+                assert_eq!(convert(&df), ROWS);
+                start.elapsed()
+            })
+            .min()
+            .unwrap()
+    };
+    let rowwise = best(&|df| dataframe_to_documents_rowwise(df).unwrap().len());
+    let columnar = best(&|df| dataframe_to_documents(df, Tz::UTC).unwrap().len());
+
+    println!("row by row {:>10.2?}", rowwise);
+    println!("columnar   {:>10.2?}", columnar);
+    println!("speedup    {:>10.1}x", rowwise.as_secs_f64() / columnar.as_secs_f64());
+}
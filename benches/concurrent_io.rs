@@ -0,0 +1,133 @@
+/*
+    Compares sequential and bounded-parallel reads and writes on a 500 ticker universe, and the
+    batched $or metadata lookup against one find_metadata() per ticker.
+    Needs a running MongoDB and MONGODB_NAME_MOCK / MONGODB_METADATA_NAME_MOCK in .env, the
+    mock databases are dropped when the benchmark finishes.
+
+    cargo bench --bench concurrent_io
+*/
+use chrono::{Duration, NaiveDate};
+use futures::{StreamExt, TryStreamExt};
+use polars::prelude::*;
+use std::env;
+use std::time::Instant;
+
+use molly_db::database::config::Config;
+use molly_db::database::database_service::MongoDbClient;
+use molly_db::database::models::eod_models::{MongoTickerParams, OhlcvMetaData};
+use molly_db::database::utility_functions::parse_datetime;
+
+const TICKERS: usize = 500;
+const ROWS_PER_TICKER: usize = 2_520;
+const CONCURRENCY: usize = 16;
+
+fn synthetic_df(ticker: &str, collection_name: &str) -> DataFrame {
+    let start = NaiveDate::from_ymd_opt(2010, 1, 1).unwrap();
+    let dates = (0..ROWS_PER_TICKER)
+        .map(|i| (start + Duration::days(i as i64)).format("%Y-%m-%d").to_string())
+        .collect::<Vec<String>>();
+    let close = (0..ROWS_PER_TICKER)
+        .map(|i| 100.0 + (i as f64 / 10.0).sin() * 5.0)
+        .collect::<Vec<f64>>();
+    let metadata = OhlcvMetaData {
+        metadata_collection_name: collection_name.to_string(),
+        ticker: ticker.to_string(),
+        source: "eod".to_string(),
+        exchange: "US".to_string(),
+        currency: None,
+    };
+    df!(
+        "date" => dates,
+        "open" => &close,
+        "high" => close.iter().map(|c| c + 1.0).collect::<Vec<f64>>(),
+        "low" => close.iter().map(|c| c - 1.0).collect::<Vec<f64>>(),
+        "close" => &close,
+        "adjusted_close" => &close,
+        "volume" => vec![1_000_000i64; ROWS_PER_TICKER],
+        "metadata" => vec![serde_json::to_string(&metadata).unwrap(); ROWS_PER_TICKER],
+    )
+    .unwrap()
+}
+
+fn universe(collection_name: &str) -> Vec<MongoTickerParams> {
+    (0..TICKERS)
+        .map(|i| MongoTickerParams {
+            ticker: format!("SYN{:04}", i),
+            exchange: "US".to_string(),
+            series_collection_name: collection_name.to_string(),
+            source: "eod".to_string(),
+            from: parse_datetime("1970-01-01").unwrap(),
+            to: parse_datetime("2030-01-01").unwrap(),
+        })
+        .collect()
+}
+
+async fn client(config: &Config, concurrency: usize) -> MongoDbClient {
+    MongoDbClient::builder()
+        .config(config)
+        .series_db(env::var("MONGODB_NAME_MOCK").unwrap())
+        .metadata_db(env::var("MONGODB_METADATA_NAME_MOCK").unwrap())
+        .concurrency(concurrency)
+        .build()
+        .await
+        .unwrap()
+}
+
+async fn bench(concurrency: usize, collection_name: &str, config: &Config) {
+    let client = client(config, concurrency).await;
+    let tickers = universe(collection_name);
+    client.ensure_series_collection_exists(&tickers[..1]).await.unwrap();
+    for ticker in tickers.iter() {
+        client.insert_metadata(ticker).await.unwrap();
+    }
+    let dfs = tickers
+        .iter()
+        .map(|ticker| synthetic_df(&ticker.ticker, collection_name))
+        .collect::<Vec<DataFrame>>();
+
+    let start = Instant::now();
+    client.insert_series(dfs).await.unwrap();
+    let insert = start.elapsed();
+
+    let start = Instant::now();
+    client.update_metadata_dates(&tickers).await.unwrap();
+    let metadata_update = start.elapsed();
+
+    let start = Instant::now();
+    let metadata = client.find_metadata_batch(&tickers).await.unwrap();
+    let metadata_lookup = start.elapsed();
+    assert_eq!(metadata.len(), TICKERS);
+
+    // the per-ticker lookups the batch replaced, as many at a time as the client allows
+    let start = Instant::now();
+    let metadata = futures::stream::iter(tickers.iter())
+        .map(|ticker| client.find_metadata(ticker))
+        .buffer_unordered(concurrency)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    let metadata_lookup_per_ticker = start.elapsed();
+    assert_eq!(metadata.into_iter().flatten().count(), TICKERS);
+
+    let start = Instant::now();
+    let dfs = client.read_series(tickers).await.unwrap();
+    let read = start.elapsed();
+    assert_eq!(dfs.len(), TICKERS);
+
+    println!(
+        "concurrency {:>2}: insert {:>8.2?}  update_metadata_dates {:>8.2?}  find_metadata_batch {:>8.2?}  find_metadata per ticker {:>8.2?}  read_series {:>8.2?}",
+        concurrency, insert, metadata_update, metadata_lookup, metadata_lookup_per_ticker, read
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    let config = Config::load().unwrap();
+    println!("{} tickers x {} rows", TICKERS, ROWS_PER_TICKER);
+    bench(1, "bench_sequential_1d", &config).await;
+    bench(CONCURRENCY, "bench_concurrent_1d", &config).await;
+
+    let mongo_client = mongodb::Client::with_uri_str(config.mongodb.uri.unwrap()).await.unwrap();
+    mongo_client.database(&env::var("MONGODB_NAME_MOCK").unwrap()).drop(None).await.unwrap();
+    mongo_client.database(&env::var("MONGODB_METADATA_NAME_MOCK").unwrap()).drop(None).await.unwrap();
+}
@@ -1,6 +1,8 @@
 use crate::database::database_service::MongoDbClient;
-use anyhow::Result;
-use chrono::{Days, NaiveDateTime};
+use crate::database::utility_functions::parse_timezone;
+use anyhow::{anyhow, Result};
+use chrono::{Days, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
 use polars::{lazy::dsl::col, prelude::*};
 use std::fs::File;
 use tokio;
@@ -9,7 +11,7 @@ pub fn get_data() -> Result<Vec<(String, DataFrame)>> {
     #[tokio::main]
     async fn get_data() -> Result<Vec<(String, DataFrame)>> {
         let mongo_client = MongoDbClient::new().await;
-        let dfs = mongo_client
+        let (dfs, report) = mongo_client
             .run(vec![
                 (
                     "AAPL",
@@ -46,6 +48,9 @@ pub fn get_data() -> Result<Vec<(String, DataFrame)>> {
             ])
             .await
             .unwrap();
+        if report.has_failures() {
+            log::error!("get_data() {} tickers failed to ingest", report.failed().len());
+        }
         Ok(dfs)
     }
     get_data()
@@ -78,11 +83,37 @@ impl Alpha {
         Ok(date_range)
     }
 
+    /*
+        The days of the date range with the datetime type of a series, so they join on its datetime column.
+        Each day is midnight in the timezone of the series, i.e., midnight UTC, the date key of daily bars,
+        or midnight in exchange time for localized intraday series.
+    */
+    fn date_range_series(date_range: &[NaiveDateTime], dtype: &DataType) -> Result<Series> {
+        let timezone = match dtype {
+            DataType::Datetime(_, Some(timezone)) => Some(timezone.clone()),
+            _ => None,
+        };
+        let tz = timezone.as_deref().map(parse_timezone).transpose()?.unwrap_or(Tz::UTC);
+        let millis = date_range
+            .iter()
+            .map(|date| {
+                tz.from_local_datetime(date)
+                    .earliest()
+                    .map(|datetime| datetime.timestamp_millis())
+                    .ok_or_else(|| anyhow!("date_range_series() {} does not exist in timezone {}", date, tz))
+            })
+            .collect::<Result<Vec<i64>>>()?;
+        let series = Int64Chunked::from_vec("datetime", millis)
+            .into_datetime(TimeUnit::Milliseconds, timezone)
+            .into_series();
+        Ok(series.cast(dtype)?)
+    }
+
     fn compute_meta_info(self) -> Result<()> {
         let date_range = self.generate_date_range().unwrap();
         let mut dfs_updated = Vec::new();
         for (ticker, df) in self.dfs.into_iter() {
-            let daterange_df = df!("datetime" => &date_range).unwrap();
+            let daterange_df = DataFrame::new(vec![Self::date_range_series(&date_range, df.column("datetime")?.dtype())?])?;
             let df_joined = daterange_df
                 .left_join(&df, ["datetime"], ["datetime"])?
                 .fill_null(FillNullStrategy::Forward(None))?
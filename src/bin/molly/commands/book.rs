@@ -0,0 +1,123 @@
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+
+use molly_db::database::database_service::option_chain::option_chain_df;
+use molly_db::database::database_service::order_book::book_levels;
+use molly_db::database::database_service::MongoDbClient;
+use molly_db::database::models::option_models::{ChainFilter, OptionRight};
+
+use super::{format_date, format_quote_field, SeriesArgs};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ChainRight {
+    Call,
+    Put,
+}
+
+impl From<ChainRight> for OptionRight {
+    fn from(right: ChainRight) -> Self {
+        match right {
+            ChainRight::Call => OptionRight::Call,
+            ChainRight::Put => OptionRight::Put,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct BookArgs {
+    #[command(flatten)]
+    series: SeriesArgs,
+    /// Date, datetime, unix timestamp or relative date such as -5y or ytd
+    #[arg(long, default_value = "now")]
+    at: String,
+    /// Number of levels to print per side
+    #[arg(long, default_value_t = 10)]
+    depth: usize,
+}
+
+#[derive(Args)]
+pub struct ChainArgs {
+    #[command(flatten)]
+    series: SeriesArgs,
+    /// Date, datetime, unix timestamp or relative date such as -5y or ytd
+    #[arg(long, default_value = "today")]
+    date: String,
+    /// Only contracts expiring within this many days of the date
+    #[arg(long)]
+    max_days: Option<i64>,
+    #[arg(long)]
+    min_strike: Option<f64>,
+    #[arg(long)]
+    max_strike: Option<f64>,
+    #[arg(long, value_enum)]
+    right: Option<ChainRight>,
+    /// Number of rows to print
+    #[arg(long, default_value_t = 50)]
+    rows: usize,
+}
+
+pub async fn book(client: &MongoDbClient, args: BookArgs) -> Result<bool> {
+    let params = args.series.to_params("1970-01-01", &args.at)?;
+    let snapshot = match client.read_book_at(&params, params.to).await? {
+        Some(snapshot) => snapshot,
+        None => {
+            eprintln!("no order book stored for {} at or before {}", args.series.ticker, args.at);
+            return Ok(false);
+        }
+    };
+    println!(
+        "{} sequence {} mid {} spread {}",
+        snapshot.datetime,
+        snapshot.sequence,
+        format_quote_field(snapshot.mid()),
+        format_quote_field(snapshot.spread())
+    );
+    let mut shown = snapshot;
+    shown.bid_prices.truncate(args.depth);
+    shown.bid_sizes.truncate(args.depth);
+    shown.ask_prices.truncate(args.depth);
+    shown.ask_sizes.truncate(args.depth);
+    println!("{}", book_levels(&shown)?);
+    Ok(true)
+}
+
+pub async fn chain(client: &MongoDbClient, args: ChainArgs) -> Result<bool> {
+    let params = args.series.to_params("1970-01-01", &args.date)?;
+    let filter = ChainFilter {
+        max_days_to_expiry: args.max_days,
+        min_strike: args.min_strike,
+        max_strike: args.max_strike,
+        right: args.right.map(OptionRight::from),
+        ..Default::default()
+    };
+    let quotes = client.read_option_chain(&params, params.to, &filter).await?;
+    if quotes.is_empty() {
+        eprintln!("no option chain stored for {} on {}", args.series.ticker, args.date);
+        return Ok(false);
+    }
+    println!("{}", option_chain_df(&quotes)?.head(Some(args.rows)));
+    Ok(true)
+}
+
+pub async fn quote(client: &MongoDbClient, series: &SeriesArgs, json: bool) -> Result<bool> {
+    let params = series.to_params("1970-01-01", "today")?;
+    match client.latest_quotes(&[params]).await?.pop() {
+        Some(quote) if json => println!("{}", serde_json::to_string(&quote)?),
+        Some(quote) => println!(
+            "{:<12} {:<8} {} last {} bid {} ask {} change {} ({}%)",
+            quote.metadata.ticker,
+            quote.metadata.exchange,
+            format_date(quote.datetime),
+            quote.last,
+            format_quote_field(quote.bid),
+            format_quote_field(quote.ask),
+            format_quote_field(quote.change),
+            format_quote_field(quote.change_percent),
+        ),
+        None => {
+            eprintln!("no quotes stored for ticker {} in {}", series.ticker, series.collection);
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,133 @@
+use anyhow::{anyhow, Result};
+
+use molly_db::database::database_service::MongoDbClient;
+use molly_db::database::models::schema_models::{CollectionSchema, FieldSchema, FieldType};
+
+use super::{collection_names, metadata_params, print_coverage, SeriesArgs};
+
+fn parse_field(field: &str) -> Result<FieldSchema> {
+    let (name, field_type) = field
+        .split_once(':')
+        .ok_or_else(|| anyhow!("field {} should be name:type", field))?;
+    let field_type = match field_type {
+        "float" => FieldType::Float,
+        "integer" => FieldType::Integer,
+        "boolean" => FieldType::Boolean,
+        _ => return Err(anyhow!("field type {} should be float, integer or boolean", field_type)),
+    };
+    Ok(FieldSchema::new(name, field_type))
+}
+
+pub async fn ls(client: &MongoDbClient, collection: Option<String>) -> Result<bool> {
+    for collection_name in collection_names(client, collection).await? {
+        let registered_series = client.list_registered_series(&collection_name).await?;
+        println!("{} ({} series)", collection_name, registered_series.len());
+        for metadata in registered_series.iter() {
+            print!("    ");
+            print_coverage(metadata);
+        }
+    }
+    Ok(true)
+}
+
+pub async fn show(
+    client: &MongoDbClient,
+    ticker: &str,
+    exchange: Option<String>,
+    collection: Option<String>,
+    rows: usize,
+) -> Result<bool> {
+    let mut found = false;
+    for collection_name in collection_names(client, collection).await? {
+        let registered_series = client
+            .list_registered_series(&collection_name)
+            .await?
+            .into_iter()
+            .filter(|metadata| metadata.ticker == ticker)
+            .filter(|metadata| match &exchange {
+                Some(exchange) => *exchange == metadata.exchange,
+                None => true,
+            });
+        for metadata in registered_series {
+            found = true;
+            print_coverage(&metadata);
+            for (_, df) in client.read_series(vec![metadata_params(&metadata)]).await? {
+                println!("{} rows\n{}", df.height(), df.tail(Some(rows)));
+            }
+        }
+    }
+    if !found {
+        eprintln!("no series registered for ticker {}", ticker);
+    }
+    Ok(found)
+}
+
+pub async fn delete(client: &MongoDbClient, series: &SeriesArgs, yes: bool) -> Result<bool> {
+    if !yes {
+        return Err(anyhow!(
+            "refusing to delete {} from {} without --yes",
+            series.ticker,
+            series.collection
+        ));
+    }
+    let params = series.to_params("1970-01-01", "today")?;
+    let (series_rows, metadata_documents) = client.delete_series(&params).await?;
+    println!(
+        "deleted {} rows and {} metadata documents for {}",
+        series_rows, metadata_documents, series.ticker
+    );
+    Ok(true)
+}
+
+pub async fn verify(client: &MongoDbClient, collection: Option<String>, json: bool) -> Result<bool> {
+    let mut success = true;
+    for collection_name in collection_names(client, collection).await? {
+        for metadata in client.list_registered_series(&collection_name).await? {
+            let verification = client.verify_series(&metadata_params(&metadata)).await?;
+            let consistent = verification.is_consistent();
+            success &= consistent;
+            if json {
+                println!("{}", serde_json::to_string(&verification)?);
+            } else {
+                println!(
+                    "{:<12} {:<8} {:<24} rows {:>8} duplicates {:>4} {}",
+                    verification.ticker,
+                    verification.exchange,
+                    verification.series_collection_name,
+                    verification.rows,
+                    verification.duplicate_datetimes,
+                    if consistent { "ok" } else { "INCONSISTENT" },
+                );
+            }
+        }
+    }
+    Ok(success)
+}
+
+pub async fn schema(client: &MongoDbClient, collection: &str, fields: &[String]) -> Result<bool> {
+    if !fields.is_empty() {
+        let fields = fields
+            .iter()
+            .map(|field| parse_field(field))
+            .collect::<Result<Vec<FieldSchema>>>()?;
+        client
+            .register_schema(&CollectionSchema {
+                collection_name: collection.to_string(),
+                fields,
+            })
+            .await?;
+    }
+    let schema = client.get_schema(collection).await?;
+    for field in schema.fields.iter() {
+        println!("{:<24} {:?}", field.name, field.field_type);
+    }
+    Ok(true)
+}
+
+pub async fn migrate_volume(client: &MongoDbClient, collection: Option<String>) -> Result<bool> {
+    for collection_name in collection_names(client, collection).await? {
+        let rows = client.migrate_volume(&collection_name).await?;
+        println!("{:<24} {:>8} rows rewritten", collection_name, rows);
+    }
+    Ok(true)
+}
@@ -0,0 +1,91 @@
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use std::fs;
+use std::path::Path;
+
+use molly_db::database::database_service::MongoDbClient;
+use molly_db::database::models::eod_models::MongoTickerParams;
+use molly_db::database::utility_functions::{parse_datetime, parse_ticker_list};
+
+use super::SeriesArgs;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum EventKind {
+    Earnings,
+    Splits,
+}
+
+#[derive(Args)]
+pub struct EventsArgs {
+    ticker: String,
+    #[arg(long)]
+    exchange: String,
+    #[arg(long, default_value = "eod")]
+    source: String,
+    #[arg(long, value_enum, default_value_t = EventKind::Earnings)]
+    kind: EventKind,
+    #[arg(long, default_value = "1970-01-01")]
+    from: String,
+    #[arg(long, default_value = "+1y")]
+    to: String,
+    /// Number of rows to print
+    #[arg(long, default_value_t = 20)]
+    rows: usize,
+}
+
+#[derive(Args)]
+pub struct NewsArgs {
+    #[command(flatten)]
+    series: SeriesArgs,
+    #[arg(long, default_value = "-1w")]
+    from: String,
+    #[arg(long, default_value = "now")]
+    to: String,
+    /// Number of rows to print
+    #[arg(long, default_value_t = 20)]
+    rows: usize,
+}
+
+pub async fn ingest_events(client: &MongoDbClient, file: &Path) -> Result<bool> {
+    let tickers = parse_ticker_list(&fs::read_to_string(file)?)?;
+    let events_stored = client.ingest_events(&tickers).await?;
+    println!("stored {} earnings and split events", events_stored);
+    Ok(true)
+}
+
+pub async fn ingest_ipos(client: &MongoDbClient, from: &str, to: &str, source: &str) -> Result<bool> {
+    let ipos_stored = client.ingest_ipos(source, parse_datetime(from)?, parse_datetime(to)?).await?;
+    println!("stored {} ipos", ipos_stored);
+    Ok(true)
+}
+
+pub async fn events(client: &MongoDbClient, args: EventsArgs) -> Result<bool> {
+    // events belong to the instrument, not to one of its series collections
+    let params = MongoTickerParams {
+        ticker: args.ticker,
+        exchange: args.exchange,
+        series_collection_name: String::new(),
+        source: args.source,
+        from: parse_datetime(&args.from)?,
+        to: parse_datetime(&args.to)?,
+    };
+    let (_, df) = match args.kind {
+        EventKind::Earnings => client.read_earnings(params).await?,
+        EventKind::Splits => client.read_splits(params).await?,
+    };
+    println!("{}", df.tail(Some(args.rows)));
+    Ok(true)
+}
+
+pub async fn ingest_news(client: &MongoDbClient, file: &Path) -> Result<bool> {
+    let tickers = parse_ticker_list(&fs::read_to_string(file)?)?;
+    let items_stored = client.ingest_news(&tickers).await?;
+    println!("stored {} news items for {} tickers", items_stored, tickers.len());
+    Ok(true)
+}
+
+pub async fn news(client: &MongoDbClient, args: NewsArgs) -> Result<bool> {
+    let (_, df) = client.read_news(args.series.to_params(&args.from, &args.to)?).await?;
+    println!("{}", df.tail(Some(args.rows)));
+    Ok(true)
+}
@@ -0,0 +1,178 @@
+use anyhow::{anyhow, Result};
+use clap::{Args, ValueEnum};
+use std::path::PathBuf;
+
+use molly_db::database::config::Config;
+use molly_db::database::data_apis::SyntheticApi;
+use molly_db::database::database_service::fx_conversion::DEFAULT_FX_COLLECTION;
+use molly_db::database::database_service::{BarSpec, ContinuousContractBuilder, FxConversion, MongoDbClient};
+use molly_db::database::models::futures_models::{AdjustmentMethod, RollRule};
+
+use super::{write_df, ExportFormat, SeriesArgs};
+
+#[derive(Args)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    series: SeriesArgs,
+    /// Date, datetime, unix timestamp or relative date such as -5y or ytd
+    #[arg(long, default_value = "1970-01-01")]
+    from: String,
+    #[arg(long, default_value = "today")]
+    to: String,
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+    format: ExportFormat,
+    #[arg(long)]
+    output: PathBuf,
+    /// Write datetimes in the exchange timezone instead of UTC
+    #[arg(long)]
+    localize: bool,
+    /// Convert prices into this currency with the forex pairs in --fx-collection
+    #[arg(long)]
+    currency: Option<String>,
+    #[arg(long, default_value = DEFAULT_FX_COLLECTION)]
+    fx_collection: String,
+    /// Add the daily sentiment stored for the ticker in this collection, e.g., equity_sentiment_1d
+    #[arg(long)]
+    sentiment: Option<String>,
+}
+
+#[derive(Args)]
+pub struct SyntheticArgs {
+    #[command(flatten)]
+    series: SeriesArgs,
+    #[arg(long, default_value = "-1y")]
+    from: String,
+    #[arg(long, default_value = "now")]
+    to: String,
+    /// Seed to use instead of the one in the config
+    #[arg(long)]
+    seed: Option<u64>,
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+    format: ExportFormat,
+    #[arg(long)]
+    output: PathBuf,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum BarKind {
+    Time,
+    Volume,
+    Dollar,
+}
+
+#[derive(Args)]
+pub struct BarsArgs {
+    #[command(flatten)]
+    series: SeriesArgs,
+    #[arg(long, default_value = "1970-01-01")]
+    from: String,
+    #[arg(long, default_value = "today")]
+    to: String,
+    #[arg(long, value_enum, default_value_t = BarKind::Time)]
+    kind: BarKind,
+    /// Seconds per time bar, or traded size or price * size per volume or dollar bar
+    #[arg(long, default_value_t = 60.0)]
+    size: f64,
+    /// Number of rows to print
+    #[arg(long, default_value_t = 10)]
+    rows: usize,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum RollArg {
+    Days,
+    Volume,
+    OpenInterest,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum AdjustmentArg {
+    Difference,
+    Ratio,
+    None,
+}
+
+impl From<AdjustmentArg> for AdjustmentMethod {
+    fn from(adjustment: AdjustmentArg) -> Self {
+        match adjustment {
+            AdjustmentArg::Difference => AdjustmentMethod::Difference,
+            AdjustmentArg::Ratio => AdjustmentMethod::Ratio,
+            AdjustmentArg::None => AdjustmentMethod::None,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ContinuousArgs {
+    #[command(flatten)]
+    series: SeriesArgs,
+    #[arg(long, default_value = "1970-01-01")]
+    from: String,
+    #[arg(long, default_value = "today")]
+    to: String,
+    #[arg(long, value_enum, default_value_t = RollArg::Days)]
+    roll: RollArg,
+    /// Calendar days before first notice or last trade to roll on with --roll days
+    #[arg(long, default_value_t = 5)]
+    days: i64,
+    #[arg(long, value_enum, default_value_t = AdjustmentArg::Ratio)]
+    adjustment: AdjustmentArg,
+    #[arg(long)]
+    output: PathBuf,
+}
+
+pub async fn export(client: &MongoDbClient, args: ExportArgs) -> Result<bool> {
+    let params = args.series.to_params(&args.from, &args.to)?;
+    let mut dfs = match &args.currency {
+        Some(currency) => {
+            let conversion = FxConversion::new(currency).fx_collection(&args.fx_collection);
+            client.read_series_in_currency(vec![params.clone()], &conversion).await?
+        }
+        None => client.read_series(vec![params.clone()]).await?,
+    };
+    if let Some(sentiment_collection) = &args.sentiment {
+        dfs = client.attach_sentiment(std::slice::from_ref(&params), dfs, sentiment_collection).await?;
+    }
+    if args.localize {
+        dfs = client.localize_series(&[params], dfs).await?;
+    }
+    let (_, mut df) = dfs
+        .pop()
+        .ok_or_else(|| anyhow!("export read no series for ticker {}", args.series.ticker))?;
+    write_df(&mut df, args.format, &args.output)?;
+    Ok(true)
+}
+
+pub fn synthetic(config: &Config, args: SyntheticArgs) -> Result<bool> {
+    let mut synthetic_config = config.synthetic.clone();
+    if let Some(seed) = args.seed {
+        synthetic_config.seed = seed;
+    }
+    let params = args.series.to_params(&args.from, &args.to)?;
+    let mut df = SyntheticApi::new(synthetic_config).generate(&params, params.to)?;
+    write_df(&mut df, args.format, &args.output)?;
+    Ok(true)
+}
+
+pub async fn bars(client: &MongoDbClient, args: BarsArgs) -> Result<bool> {
+    let spec = match args.kind {
+        BarKind::Time => BarSpec::Time((args.size * 1000.0) as i64),
+        BarKind::Volume => BarSpec::Volume(args.size),
+        BarKind::Dollar => BarSpec::Dollar(args.size),
+    };
+    let (_, df) = client.read_tick_bars(args.series.to_params(&args.from, &args.to)?, spec).await?;
+    println!("{}", df.head(Some(args.rows)));
+    Ok(true)
+}
+
+pub async fn continuous(client: &MongoDbClient, args: ContinuousArgs) -> Result<bool> {
+    let roll_rule = match args.roll {
+        RollArg::Days => RollRule::DaysBeforeExpiry(args.days),
+        RollArg::Volume => RollRule::VolumeSwitch,
+        RollArg::OpenInterest => RollRule::OpenInterestSwitch,
+    };
+    let builder = ContinuousContractBuilder::new(roll_rule, args.adjustment.into());
+    let (_, mut df) = client.read_continuous_series(args.series.to_params(&args.from, &args.to)?, &builder).await?;
+    write_df(&mut df, ExportFormat::Csv, &args.output)?;
+    Ok(true)
+}
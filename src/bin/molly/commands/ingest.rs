@@ -0,0 +1,79 @@
+use anyhow::{anyhow, Result};
+use chrono::{NaiveTime, Utc};
+use clap::ValueEnum;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use molly_db::database::config::Config;
+use molly_db::database::data_apis::eod_stream::{EodFeed, EodStream};
+use molly_db::database::database_service::{MongoDbClient, QuotePoller, RefreshDaemon, StreamIngestor};
+use molly_db::database::utility_functions::parse_ticker_list;
+
+use super::{collection_names, print_report};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum StreamFeed {
+    UsTrades,
+    UsQuotes,
+    Forex,
+    Crypto,
+}
+
+impl From<StreamFeed> for EodFeed {
+    fn from(feed: StreamFeed) -> Self {
+        match feed {
+            StreamFeed::UsTrades => EodFeed::UsTrades,
+            StreamFeed::UsQuotes => EodFeed::UsQuotes,
+            StreamFeed::Forex => EodFeed::Forex,
+            StreamFeed::Crypto => EodFeed::Crypto,
+        }
+    }
+}
+
+pub async fn ingest(client: &MongoDbClient, file: &Path, json: bool) -> Result<bool> {
+    let tickers = parse_ticker_list(&fs::read_to_string(file)?)?;
+    let report = client.ingest(&tickers).await?;
+    print_report(&report, json)?;
+    Ok(!report.has_failures())
+}
+
+pub async fn update(client: &MongoDbClient, all: bool, collection: Option<String>, json: bool) -> Result<bool> {
+    if !all && collection.is_none() {
+        return Err(anyhow!("update needs either --all or --collection"));
+    }
+    let mut success = true;
+    let refresher = RefreshDaemon::new(client.clone(), NaiveTime::MIN);
+    for collection_name in collection_names(client, collection).await? {
+        let report = refresher.refresh_collection(&collection_name).await?;
+        print_report(&report, json)?;
+        success &= !report.has_failures();
+    }
+    Ok(success)
+}
+
+pub async fn poll(client: MongoDbClient, file: &Path, interval: u64) -> Result<bool> {
+    let tickers = parse_ticker_list(&fs::read_to_string(file)?)?;
+    log::info!("molly poll starting at {}", Utc::now());
+    QuotePoller::new(client, tickers, Duration::from_secs(interval.max(1))).run().await?;
+    Ok(true)
+}
+
+pub async fn stream(client: MongoDbClient, config: &Config, file: &Path, feed: StreamFeed) -> Result<bool> {
+    let tickers = parse_ticker_list(&fs::read_to_string(file)?)?;
+    let api_token = config
+        .eod
+        .api_token
+        .as_deref()
+        .ok_or_else(|| anyhow!("stream needs an EOD api token"))?;
+    let stream = EodStream::new(api_token, feed.into(), tickers);
+    StreamIngestor::new(client, stream).run().await?;
+    Ok(true)
+}
+
+pub async fn daemon(client: MongoDbClient, daily_refresh_time: &str) -> Result<bool> {
+    let daily_refresh_time = NaiveTime::parse_from_str(daily_refresh_time, "%H:%M")?;
+    log::info!("molly daemon starting at {}", Utc::now());
+    RefreshDaemon::new(client, daily_refresh_time).run().await?;
+    Ok(true)
+}
@@ -0,0 +1,121 @@
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use polars::prelude::*;
+use std::fs::File;
+use std::path::Path;
+
+use molly_db::database::database_service::MongoDbClient;
+use molly_db::database::models::eod_models::{MongoTickerParams, TimeseriesMetaDataStruct};
+use molly_db::database::models::ingest_models::IngestReport;
+use molly_db::database::utility_functions::parse_datetime;
+
+pub mod book;
+pub mod catalog;
+pub mod events;
+pub mod export;
+pub mod ingest;
+pub mod rates;
+
+#[derive(Args)]
+pub struct SeriesArgs {
+    pub ticker: String,
+    #[arg(long)]
+    pub exchange: String,
+    #[arg(long)]
+    pub collection: String,
+    #[arg(long, default_value = "eod")]
+    pub source: String,
+}
+
+impl SeriesArgs {
+    pub fn to_params(&self, from: &str, to: &str) -> Result<MongoTickerParams> {
+        Ok(MongoTickerParams {
+            ticker: self.ticker.clone(),
+            exchange: self.exchange.clone(),
+            series_collection_name: self.collection.clone(),
+            source: self.source.clone(),
+            from: parse_datetime(from)?,
+            to: parse_datetime(to)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+}
+
+pub fn write_df(df: &mut DataFrame, format: ExportFormat, output: &Path) -> Result<()> {
+    let mut file = File::create(output)?;
+    match format {
+        ExportFormat::Csv => CsvWriter::new(&mut file).finish(df)?,
+        ExportFormat::Parquet => {
+            ParquetWriter::new(&mut file).finish(df)?;
+        }
+    }
+    println!("wrote {} rows to {}", df.height(), output.display());
+    Ok(())
+}
+
+pub fn format_date(date: bson::DateTime) -> String {
+    date.to_chrono().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+pub fn format_quote_field(value: Option<f64>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_else(|| "-".to_string())
+}
+
+pub fn print_coverage(metadata: &TimeseriesMetaDataStruct) {
+    println!(
+        "{:<12} {:<8} {:<24} {:<6} {} -> {} (updated {})",
+        metadata.ticker,
+        metadata.exchange,
+        metadata.series_collection_name,
+        metadata.source,
+        format_date(metadata.from),
+        format_date(metadata.to),
+        format_date(metadata.last_updated),
+    );
+}
+
+pub fn print_report(report: &IngestReport, json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(report)?);
+        return Ok(());
+    }
+    for ticker in report.tickers.iter() {
+        println!(
+            "{:<12} {:<8} {:<24} {:<10} fetched {:>8} written {:>8} {}",
+            ticker.ticker,
+            ticker.exchange,
+            ticker.series_collection_name,
+            format!("{:?}", ticker.action),
+            ticker.rows_fetched,
+            ticker.rows_written,
+            ticker.errors.join("; "),
+        );
+    }
+    println!("finished in {:.1?}", report.timings.total);
+    Ok(())
+}
+
+// the collection asked for, or every collection in the metadata database
+pub async fn collection_names(client: &MongoDbClient, collection: Option<String>) -> Result<Vec<String>> {
+    match collection {
+        Some(collection) => Ok(vec![collection]),
+        None => client.list_metadata_collections().await,
+    }
+}
+
+// the series registered in the metadata, over all their dates
+pub fn metadata_params(metadata: &TimeseriesMetaDataStruct) -> MongoTickerParams {
+    MongoTickerParams {
+        ticker: metadata.ticker.clone(),
+        exchange: metadata.exchange.clone(),
+        series_collection_name: metadata.series_collection_name.clone(),
+        source: metadata.source.clone(),
+        from: metadata.from,
+        to: metadata.to,
+    }
+}
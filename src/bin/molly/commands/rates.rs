@@ -0,0 +1,90 @@
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use std::fs;
+use std::path::Path;
+
+use molly_db::database::database_service::yield_curve::{interpolate_curve, CurveInterpolation};
+use molly_db::database::database_service::MongoDbClient;
+use molly_db::database::utility_functions::{parse_datetime, parse_ticker_list};
+
+use super::SeriesArgs;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum InterpolationArg {
+    Linear,
+    Cubic,
+    NelsonSiegel,
+}
+
+#[derive(Args)]
+pub struct CurveArgs {
+    #[command(flatten)]
+    series: SeriesArgs,
+    #[arg(long, default_value = "1970-01-01")]
+    from: String,
+    #[arg(long, default_value = "today")]
+    to: String,
+    /// Tenor such as 3M or 7Y
+    #[arg(long = "tenor")]
+    tenors: Vec<String>,
+    #[arg(long, value_enum, default_value_t = InterpolationArg::Linear)]
+    method: InterpolationArg,
+    /// Number of rows to print
+    #[arg(long, default_value_t = 10)]
+    rows: usize,
+}
+
+#[derive(Args)]
+pub struct MacroArgs {
+    #[command(flatten)]
+    series: SeriesArgs,
+    /// Reference periods from and to
+    #[arg(long, default_value = "1970-01-01")]
+    from: String,
+    #[arg(long, default_value = "today")]
+    to: String,
+    /// Only releases up to this date, the latest vintages if left out
+    #[arg(long)]
+    as_of: Option<String>,
+    #[arg(long)]
+    vintages: bool,
+    /// Number of rows to print
+    #[arg(long, default_value_t = 20)]
+    rows: usize,
+}
+
+pub async fn curve(client: &MongoDbClient, args: CurveArgs) -> Result<bool> {
+    let (_, matrix) = client.read_curve(args.series.to_params(&args.from, &args.to)?).await?;
+    let df = match args.tenors.is_empty() {
+        true => matrix,
+        false => {
+            let method = match args.method {
+                InterpolationArg::Linear => CurveInterpolation::Linear,
+                InterpolationArg::Cubic => CurveInterpolation::CubicSpline,
+                InterpolationArg::NelsonSiegel => CurveInterpolation::NelsonSiegel,
+            };
+            let tenors = args.tenors.iter().map(String::as_str).collect::<Vec<&str>>();
+            interpolate_curve(&matrix, &tenors, method)?
+        }
+    };
+    println!("{}", df.tail(Some(args.rows)));
+    Ok(true)
+}
+
+pub async fn ingest_macro(client: &MongoDbClient, file: &Path) -> Result<bool> {
+    let tickers = parse_ticker_list(&fs::read_to_string(file)?)?;
+    let vintages_stored = client.ingest_macro(&tickers).await?;
+    println!("stored {} new vintages for {} indicators", vintages_stored, tickers.len());
+    Ok(true)
+}
+
+pub async fn macro_series(client: &MongoDbClient, args: MacroArgs) -> Result<bool> {
+    let params = args.series.to_params(&args.from, &args.to)?;
+    let (_, df) = match (args.vintages, &args.as_of) {
+        (true, _) => client.read_macro_vintages(params).await?,
+        (false, Some(as_of)) => client.read_macro_series(params, Some(parse_datetime(as_of)?)).await?,
+        (false, None) => client.read_macro_series(params, None).await?,
+    };
+    println!("{}", df.tail(Some(args.rows)));
+    Ok(true)
+}
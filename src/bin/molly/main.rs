@@ -0,0 +1,187 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use molly_db::database::config::Config;
+use molly_db::database::database_service::MongoDbClient;
+
+mod commands;
+use commands::book::{self, BookArgs, ChainArgs};
+use commands::events::{self, EventsArgs, NewsArgs};
+use commands::export::{self, BarsArgs, ContinuousArgs, ExportArgs, SyntheticArgs};
+use commands::ingest::{self, StreamFeed};
+use commands::rates::{self, CurveArgs, MacroArgs};
+use commands::{catalog, SeriesArgs};
+
+/// Operate the molly quant database from the command line
+#[derive(Parser)]
+#[command(name = "molly", version)]
+struct Cli {
+    /// Print reports as json instead of a summary
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Config file to use instead of MOLLY_CONFIG or ./molly.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create or refresh the series listed in a file of ticker,exchange,collection,source[,from[,to]] lines
+    Ingest { file: PathBuf },
+    /// Refresh series that are already registered in the metadata database
+    Update {
+        /// Refresh every collection
+        #[arg(long, conflicts_with = "collection")]
+        all: bool,
+        /// Refresh a single collection
+        #[arg(long)]
+        collection: Option<String>,
+    },
+    /// List collections and the coverage of every series registered in them
+    Ls { collection: Option<String> },
+    /// Print the metadata and last rows of a ticker
+    Show {
+        ticker: String,
+        #[arg(long)]
+        exchange: Option<String>,
+        #[arg(long)]
+        collection: Option<String>,
+        /// Number of rows to print
+        #[arg(long, default_value_t = 10)]
+        rows: usize,
+    },
+    /// Write a series to a csv or parquet file
+    Export(ExportArgs),
+    /// Generate a series with the synthetic datasource and the [synthetic] settings of the config, without storing it
+    Synthetic(SyntheticArgs),
+    /// Delete a series and its metadata document
+    Delete {
+        #[command(flatten)]
+        series: SeriesArgs,
+        /// Required to actually delete anything
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Check that the metadata of every series matches the rows stored for it
+    Verify { collection: Option<String> },
+    /// Print the field schema of a collection, or register one by passing --field name:type
+    Schema {
+        collection: String,
+        /// Field as name:float, name:integer or name:boolean, in the order reads should return them
+        #[arg(long = "field")]
+        fields: Vec<String>,
+    },
+    /// Rewrite integer volumes stored before volume became a double
+    MigrateVolume { collection: Option<String> },
+    /// Append quotes for the tickers in a file to their live collections until ctrl-c is received
+    Poll {
+        file: PathBuf,
+        /// Seconds between polls
+        #[arg(long, default_value_t = 60)]
+        interval: u64,
+    },
+    /// Aggregate an EOD websocket feed into 1m bars for the tickers in a file until ctrl-c is received
+    Stream {
+        file: PathBuf,
+        #[arg(long, value_enum)]
+        feed: StreamFeed,
+    },
+    /// Print the latest stored quote of a ticker in a live collection
+    Quote {
+        #[command(flatten)]
+        series: SeriesArgs,
+    },
+    /// Print the bars of a ticker in a trade tick collection, aggregated on the fly
+    Bars(BarsArgs),
+    /// Print the order book of a ticker in a book snapshot collection as it stood at a time
+    Book(BookArgs),
+    /// Print the option chain of an underlying on a date
+    Chain(ChainArgs),
+    /// Write the continuous series of a futures root, whose contracts are registered in the metadata database
+    Continuous(ContinuousArgs),
+    /// Print a yield curve as a date x tenor matrix, or interpolated to the tenors passed with --tenor
+    Curve(CurveArgs),
+    /// Fetch the macro indicators in a file of indicator,country,collection,source lines and store new releases and revisions
+    IngestMacro { file: PathBuf },
+    /// Print a macro indicator as it was known at a date, or every vintage of it with --vintages
+    Macro(MacroArgs),
+    /// Fetch the news about the tickers in a file of ticker,exchange,collection,source lines and store new items
+    IngestNews { file: PathBuf },
+    /// Print the news about a ticker
+    News(NewsArgs),
+    /// Fetch the earnings announcements and splits of the tickers in a file of ticker,exchange,collection,source lines
+    IngestEvents { file: PathBuf },
+    /// Fetch the ipos of every exchange filed, expected or priced between two dates
+    IngestIpos {
+        #[arg(long, default_value = "-1m")]
+        from: String,
+        #[arg(long, default_value = "+3m")]
+        to: String,
+        #[arg(long, default_value = "eod")]
+        source: String,
+    },
+    /// Print the earnings announcements or splits of an instrument
+    Events(EventsArgs),
+    /// Refresh every collection on its schedule until ctrl-c is received
+    Daemon {
+        /// Local time of each exchange its daily series are refreshed at
+        #[arg(long, default_value = "18:00")]
+        daily_refresh_time: String,
+    },
+}
+
+// the handlers of each area live in commands, this only picks one
+async fn run(cli: Cli) -> Result<bool> {
+    let config = match &cli.config {
+        Some(path) => Config::from_file(path)?.with_env_overrides(),
+        None => Config::load()?,
+    };
+    let client = MongoDbClient::builder().config(&config).build().await?;
+    match cli.command {
+        Command::Ingest { file } => ingest::ingest(&client, &file, cli.json).await,
+        Command::Update { all, collection } => ingest::update(&client, all, collection, cli.json).await,
+        Command::Poll { file, interval } => ingest::poll(client, &file, interval).await,
+        Command::Stream { file, feed } => ingest::stream(client, &config, &file, feed).await,
+        Command::Daemon { daily_refresh_time } => ingest::daemon(client, &daily_refresh_time).await,
+        Command::Ls { collection } => catalog::ls(&client, collection).await,
+        Command::Show { ticker, exchange, collection, rows } => catalog::show(&client, &ticker, exchange, collection, rows).await,
+        Command::Delete { series, yes } => catalog::delete(&client, &series, yes).await,
+        Command::Verify { collection } => catalog::verify(&client, collection, cli.json).await,
+        Command::Schema { collection, fields } => catalog::schema(&client, &collection, &fields).await,
+        Command::MigrateVolume { collection } => catalog::migrate_volume(&client, collection).await,
+        Command::Export(args) => export::export(&client, args).await,
+        Command::Synthetic(args) => export::synthetic(&config, args),
+        Command::Bars(args) => export::bars(&client, args).await,
+        Command::Continuous(args) => export::continuous(&client, args).await,
+        Command::Quote { series } => book::quote(&client, &series, cli.json).await,
+        Command::Book(args) => book::book(&client, args).await,
+        Command::Chain(args) => book::chain(&client, args).await,
+        Command::Curve(args) => rates::curve(&client, args).await,
+        Command::IngestMacro { file } => rates::ingest_macro(&client, &file).await,
+        Command::Macro(args) => rates::macro_series(&client, args).await,
+        Command::IngestNews { file } => events::ingest_news(&client, &file).await,
+        Command::News(args) => events::news(&client, args).await,
+        Command::IngestEvents { file } => events::ingest_events(&client, &file).await,
+        Command::IngestIpos { from, to, source } => events::ingest_ipos(&client, &from, &to, &source).await,
+        Command::Events(args) => events::events(&client, args).await,
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    env_logger::init();
+    match run(Cli::parse()).await {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("molly: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
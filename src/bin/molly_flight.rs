@@ -0,0 +1,340 @@
+use arrow_array::RecordBatch;
+use arrow_flight::{
+    encode::FlightDataEncoderBuilder,
+    error::FlightError,
+    flight_service_server::{FlightService, FlightServiceServer},
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightInfo, HandshakeRequest,
+    HandshakeResponse, PollInfo, PutResult, SchemaResult, Ticket,
+};
+use arrow_ipc::reader::StreamReader;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use polars::prelude::*;
+use serde::Deserialize;
+use std::io::Cursor;
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+
+use molly_db::database::config::Config;
+use molly_db::database::database_service::MongoDbClient;
+use molly_db::database::models::eod_models::MongoTickerParams;
+use molly_db::database::models::schema_models::CollectionSchema;
+use molly_db::database::utility_functions::parse_datetime;
+
+// address the flight service listens on when MOLLY_FLIGHT_ADDR is not set
+const DEFAULT_FLIGHT_ADDR: &str = "127.0.0.1:50051";
+// rows read from mongodb per record batch
+const ROWS_PER_CHUNK: usize = 65_536;
+
+/*
+    Tickets are json documents describing the panel to stream, e.g.,
+    {"collection": "equity_spot_1d", "tickers": [["AAPL", "US"], ["MSFT", "US"]],
+     "from": "2000-01-01", "to": "2024-01-01", "fields": ["adjusted_close", "volume"]}
+*/
+#[derive(Debug, Deserialize)]
+struct FlightQuery {
+    collection: String,
+    tickers: Vec<(String, String)>,
+    source: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    fields: Option<Vec<String>>,
+}
+
+impl FlightQuery {
+    fn to_params(&self) -> anyhow::Result<Vec<MongoTickerParams>> {
+        let source = self.source.clone().unwrap_or_else(|| "eod".to_string());
+        let from = parse_datetime(self.from.as_deref().unwrap_or("1970-01-01"))?;
+        let to = parse_datetime(self.to.as_deref().unwrap_or("today"))?;
+        let tickers = self
+            .tickers
+            .iter()
+            .map(|(ticker, exchange)| MongoTickerParams {
+                ticker: ticker.clone(),
+                exchange: exchange.clone(),
+                series_collection_name: self.collection.clone(),
+                source: source.clone(),
+                from,
+                to,
+            })
+            .collect();
+        Ok(tickers)
+    }
+
+    // every batch in a flight stream must share a schema so the column order is fixed here
+    fn columns(&self, schema: &CollectionSchema) -> Vec<String> {
+        let fields = self.fields.clone().unwrap_or_else(|| {
+            schema
+                .field_names()
+                .into_iter()
+                .map(|field| field.to_string())
+                .collect()
+        });
+        ["datetime".to_string(), "ticker".to_string()]
+            .into_iter()
+            .chain(fields)
+            .collect()
+    }
+}
+
+// polars and arrow-rs do not share array types, so frames cross over as an ipc stream
+fn dataframe_to_record_batches(df: &mut DataFrame) -> anyhow::Result<Vec<RecordBatch>> {
+    let mut buffer = Vec::new();
+    IpcStreamWriter::new(&mut buffer).finish(df)?;
+    let reader = StreamReader::try_new(Cursor::new(buffer), None)?;
+    Ok(reader.collect::<Result<Vec<RecordBatch>, _>>()?)
+}
+
+// a ticket that is not a json FlightQuery, or whose dates do not parse, is the caller's mistake
+fn parse_ticket(ticket: &Ticket) -> Result<(FlightQuery, Vec<MongoTickerParams>), Status> {
+    let query: FlightQuery = serde_json::from_slice(&ticket.ticket)
+        .map_err(|e| Status::invalid_argument(format!("could not parse ticket: {}", e)))?;
+    let tickers = query
+        .to_params()
+        .map_err(|e| Status::invalid_argument(format!("could not parse ticket: {:#}", e)))?;
+    Ok((query, tickers))
+}
+
+// record batches as the flight data do_get() answers with, the schema message first
+fn encode_batches(
+    batches: impl futures::Stream<Item = Result<RecordBatch, FlightError>> + Send + 'static,
+) -> BoxStream<'static, Result<FlightData, Status>> {
+    FlightDataEncoderBuilder::new()
+        .build(batches)
+        .map_err(Status::from)
+        .boxed()
+}
+
+fn to_flight_error(e: anyhow::Error) -> FlightError {
+    FlightError::ExternalError(e.into())
+}
+
+fn chunk_to_record_batches(df: DataFrame, ticker_name: &str, columns: &[String]) -> anyhow::Result<Vec<RecordBatch>> {
+    let ticker_column = Series::new("ticker", vec![ticker_name; df.height()]);
+    let mut df = df.hstack(&[ticker_column])?.select(columns)?;
+    dataframe_to_record_batches(&mut df)
+}
+
+// streams the record batches of one ticker, a chunk of rows at a time
+async fn ticker_batches(
+    client: MongoDbClient,
+    ticker: MongoTickerParams,
+    columns: Vec<String>,
+) -> Result<BoxStream<'static, Result<RecordBatch, FlightError>>, FlightError> {
+    let ticker_name = ticker.ticker.clone();
+    let chunks = client
+        .read_series_stream(ticker, ROWS_PER_CHUNK)
+        .await
+        .map_err(to_flight_error)?;
+    let batches = chunks
+        .map(move |chunk| chunk.and_then(|df| chunk_to_record_batches(df, &ticker_name, &columns)))
+        .map_err(to_flight_error)
+        .map_ok(|batches| stream::iter(batches.into_iter().map(Ok)))
+        .try_flatten();
+    Ok(batches.boxed())
+}
+
+struct MollyFlightService {
+    client: MongoDbClient,
+}
+
+#[tonic::async_trait]
+impl FlightService for MollyFlightService {
+    type HandshakeStream = BoxStream<'static, Result<HandshakeResponse, Status>>;
+    type ListFlightsStream = BoxStream<'static, Result<FlightInfo, Status>>;
+    type DoGetStream = BoxStream<'static, Result<FlightData, Status>>;
+    type DoPutStream = BoxStream<'static, Result<PutResult, Status>>;
+    type DoActionStream = BoxStream<'static, Result<arrow_flight::Result, Status>>;
+    type ListActionsStream = BoxStream<'static, Result<ActionType, Status>>;
+    type DoExchangeStream = BoxStream<'static, Result<FlightData, Status>>;
+
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("molly flight does not use handshakes"))
+    }
+
+    // one flight per collection in the metadata database
+    async fn list_flights(
+        &self,
+        _request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        let collection_names = self
+            .client
+            .list_metadata_collections()
+            .await
+            .map_err(|e| Status::internal(format!("{:#}", e)))?;
+        let flights = collection_names.into_iter().map(|collection_name| {
+            Ok(FlightInfo::new().with_descriptor(FlightDescriptor::new_path(vec![collection_name])))
+        });
+        Ok(Response::new(stream::iter(flights).boxed()))
+    }
+
+    async fn get_flight_info(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        Err(Status::unimplemented("send a json ticket to do_get instead"))
+    }
+
+    async fn poll_flight_info(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<PollInfo>, Status> {
+        Err(Status::unimplemented("send a json ticket to do_get instead"))
+    }
+
+    async fn get_schema(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        Err(Status::unimplemented("send a json ticket to do_get instead"))
+    }
+
+    async fn do_get(&self, request: Request<Ticket>) -> Result<Response<Self::DoGetStream>, Status> {
+        let (query, tickers) = parse_ticket(&request.into_inner())?;
+        log::info!("molly_flight do_get() streaming {} tickers from {}", query.tickers.len(), query.collection);
+
+        // tickers are read one after another in chunks so only a single chunk is held in memory
+        let schema = self
+            .client
+            .get_schema(&query.collection)
+            .await
+            .map_err(|e| Status::internal(format!("{:#}", e)))?;
+        let client = self.client.clone();
+        let columns = query.columns(&schema);
+        let batches = stream::iter(tickers)
+            .then(move |ticker| ticker_batches(client.clone(), ticker, columns.clone()))
+            .try_flatten();
+        Ok(Response::new(encode_batches(batches)))
+    }
+
+    async fn do_put(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("molly flight is read only"))
+    }
+
+    async fn do_action(&self, _request: Request<Action>) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("molly flight has no actions"))
+    }
+
+    async fn list_actions(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Ok(Response::new(stream::empty().boxed()))
+    }
+
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("molly flight is read only"))
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::init();
+    let config = Config::load()?;
+    let client = MongoDbClient::builder().config(&config).build().await?;
+
+    let addr = std::env::var("MOLLY_FLIGHT_ADDR")
+        .unwrap_or_else(|_| DEFAULT_FLIGHT_ADDR.to_string())
+        .parse()?;
+    log::info!("molly_flight listening on {}", addr);
+    Server::builder()
+        .add_service(FlightServiceServer::new(MollyFlightService { client }))
+        .serve(addr)
+        .await?;
+    Ok(())
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::Float64Type;
+    use arrow_flight::{decode::FlightRecordBatchStream, FlightClient};
+    use molly_db::database::utility_functions::utc_datetime_series;
+    use tonic::transport::Channel;
+
+    fn ticket(json: &str) -> Ticket {
+        Ticket::new(json.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_parse_ticket() {
+        let (query, tickers) = parse_ticket(&ticket(
+            r#"{"collection": "equity_spot_1d", "tickers": [["AAPL", "US"], ["7203", "TSE"]], "from": "2020-01-01", "fields": ["close"]}"#,
+        ))
+        .unwrap();
+        assert_eq!(tickers.len(), 2);
+        assert_eq!((tickers[1].ticker.as_str(), tickers[1].exchange.as_str(), tickers[1].source.as_str()), ("7203", "TSE", "eod"));
+        assert_eq!(tickers[0].from, parse_datetime("2020-01-01").unwrap());
+        assert_eq!(query.columns(&CollectionSchema::ohlcv("equity_spot_1d")), vec!["datetime", "ticker", "close"]);
+
+        // without fields every field of the schema is streamed, in schema order
+        let (query, _) = parse_ticket(&ticket(r#"{"collection": "equity_spot_1d", "tickers": []}"#)).unwrap();
+        let columns = query.columns(&CollectionSchema::ohlcv("equity_spot_1d"));
+        assert_eq!(columns[..3], ["datetime", "ticker", "open"]);
+
+        for bad in [r#"{"tickers": []}"#, "AAPL.US", r#"{"collection": "equity_spot_1d", "tickers": [], "from": "01/02/2020"}"#] {
+            assert_eq!(parse_ticket(&ticket(bad)).unwrap_err().code(), tonic::Code::InvalidArgument, "{}", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flight_round_trip() {
+        // a chunk read from mongodb survives the trip through polars ipc, arrow and flight data
+        let chunk = DataFrame::new(vec![
+            utc_datetime_series("datetime", Int64Chunked::from_vec("datetime", vec![1_704_153_600_000, 1_704_240_000_000])),
+            Series::new("close", vec![185.64, 184.25]),
+            Series::new("volume", vec![82_488_674.0, 58_414_460.0]),
+        ])
+        .unwrap();
+        let columns = vec!["datetime".to_string(), "ticker".to_string(), "close".to_string()];
+        let batches = chunk_to_record_batches(chunk, "AAPL", &columns).unwrap();
+        let flight_data = encode_batches(stream::iter(batches.into_iter().map(Ok)));
+        let decoded = FlightRecordBatchStream::new_from_flight_data(flight_data.map_err(FlightError::Tonic))
+            .try_collect::<Vec<RecordBatch>>()
+            .await
+            .unwrap();
+        let batch = &decoded[0];
+        let names = batch.schema().fields().iter().map(|field| field.name().clone()).collect::<Vec<String>>();
+        assert_eq!(names, columns);
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.column_by_name("close").unwrap().as_primitive::<Float64Type>().value(1), 184.25);
+
+        // a client talking to the service over grpc gets bad tickets back as invalid arguments
+        let client = MongoDbClient::builder()
+            .uri("mongodb://127.0.0.1:1/?serverSelectionTimeoutMS=100")
+            .series_db("molly_db_test")
+            .metadata_db("molly_db_metadata_test")
+            .build()
+            .await
+            .unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = stream::unfold(listener, |listener| async move {
+            let accepted = listener.accept().await.map(|(socket, _)| socket);
+            Some((accepted, listener))
+        })
+        .boxed();
+        tokio::spawn(
+            Server::builder()
+                .add_service(FlightServiceServer::new(MollyFlightService { client }))
+                .serve_with_incoming(incoming),
+        );
+        let channel = Channel::from_shared(format!("http://{}", addr)).unwrap().connect().await.unwrap();
+        let mut flight_client = FlightClient::new(channel);
+        match flight_client.do_get(ticket("AAPL.US")).await {
+            Err(FlightError::Tonic(status)) => assert_eq!(status.code(), tonic::Code::InvalidArgument),
+            other => panic!("expected an invalid argument, got {:?}", other.map(|_| ())),
+        }
+        let actions = flight_client.list_actions().await.unwrap().try_collect::<Vec<ActionType>>().await.unwrap();
+        assert!(actions.is_empty());
+    }
+}
@@ -0,0 +1,296 @@
+use anyhow::anyhow;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use polars::prelude::*;
+use serde::Deserialize;
+
+use molly_db::database::config::Config;
+use molly_db::database::database_service::{FxConversion, MongoDbClient};
+use molly_db::database::models::eod_models::MongoTickerParams;
+use molly_db::database::utility_functions::parse_datetime;
+
+// address the server listens on when MOLLY_SERVER_ADDR is not set
+const DEFAULT_SERVER_ADDR: &str = "127.0.0.1:8080";
+
+#[derive(Clone)]
+struct AppState {
+    client: MongoDbClient,
+    // bearer token POST /ingest requires, from MOLLY_SERVER_TOKEN. Ingestion is disabled without one
+    ingest_token: Option<String>,
+}
+
+struct AppError(StatusCode, anyhow::Error);
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        log::error!("molly_server request failed: {:#}", self.1);
+        (self.0, format!("{:#}", self.1)).into_response()
+    }
+}
+
+impl<E: Into<anyhow::Error>> From<E> for AppError {
+    fn from(e: E) -> Self {
+        AppError(StatusCode::INTERNAL_SERVER_ERROR, e.into())
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+enum SeriesFormat {
+    #[default]
+    Json,
+    Csv,
+    Arrow,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeriesQuery {
+    from: Option<String>,
+    to: Option<String>,
+    source: Option<String>,
+    #[serde(default)]
+    format: SeriesFormat,
+    // datetimes in the exchange timezone instead of UTC
+    #[serde(default)]
+    localize: bool,
+    // prices converted into this currency with the forex pairs in the default fx collection
+    currency: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IngestRequest {
+    ticker: String,
+    exchange: String,
+    collection: String,
+    source: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+fn to_params(
+    ticker: String,
+    exchange: String,
+    collection: String,
+    source: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<MongoTickerParams, AppError> {
+    // a date the parser does not understand is the caller's mistake, not the server's
+    let parse = |date: Option<String>, default: &str| {
+        parse_datetime(date.as_deref().unwrap_or(default)).map_err(|e| AppError(StatusCode::BAD_REQUEST, e))
+    };
+    Ok(MongoTickerParams {
+        ticker,
+        exchange,
+        series_collection_name: collection,
+        source: source.unwrap_or_else(|| "eod".to_string()),
+        from: parse(from, "1970-01-01")?,
+        to: parse(to, "today")?,
+    })
+}
+
+fn encode_series(df: &mut DataFrame, format: SeriesFormat) -> Result<Response, AppError> {
+    let mut body = Vec::new();
+    let content_type = match format {
+        SeriesFormat::Json => {
+            JsonWriter::new(&mut body)
+                .with_json_format(JsonFormat::Json)
+                .finish(df)?;
+            "application/json"
+        }
+        SeriesFormat::Csv => {
+            CsvWriter::new(&mut body).finish(df)?;
+            "text/csv"
+        }
+        SeriesFormat::Arrow => {
+            IpcWriter::new(&mut body).finish(df)?;
+            "application/vnd.apache.arrow.file"
+        }
+    };
+    Ok(([(header::CONTENT_TYPE, content_type)], body).into_response())
+}
+
+async fn list_collections(State(state): State<AppState>) -> Result<Response, AppError> {
+    let collection_names = state.client.list_metadata_collections().await?;
+    Ok(Json(collection_names).into_response())
+}
+
+async fn list_series(
+    State(state): State<AppState>,
+    Path(collection): Path<String>,
+) -> Result<Response, AppError> {
+    let registered_series = state.client.list_registered_series(&collection).await?;
+    Ok(Json(registered_series).into_response())
+}
+
+async fn read_series(
+    State(state): State<AppState>,
+    Path((collection, exchange, ticker)): Path<(String, String, String)>,
+    Query(query): Query<SeriesQuery>,
+) -> Result<Response, AppError> {
+    let client = &state.client;
+    let params = to_params(ticker, exchange, collection, query.source, query.from, query.to)?;
+    if client.find_metadata(&params).await?.is_empty() {
+        return Err(AppError(
+            StatusCode::NOT_FOUND,
+            anyhow!("no series registered for ticker {} in collection {}", params.ticker, params.series_collection_name),
+        ));
+    }
+    let mut dfs = match &query.currency {
+        Some(currency) => client.read_series_in_currency(vec![params.clone()], &FxConversion::new(currency)).await?,
+        None => client.read_series(vec![params.clone()]).await?,
+    };
+    if query.localize {
+        dfs = client.localize_series(&[params], dfs).await?;
+    }
+    let (_, mut df) = dfs
+        .pop()
+        .ok_or_else(|| anyhow!("read_series() returned no dataframe"))?;
+    encode_series(&mut df, query.format)
+}
+
+// ingestion writes to the database and spends datasource quota, so it needs the token of the server
+fn authorize_ingest(state: &AppState, headers: &HeaderMap) -> Result<(), AppError> {
+    let token = state.ingest_token.as_deref().ok_or_else(|| {
+        AppError(StatusCode::FORBIDDEN, anyhow!("ingestion is disabled, start the server with MOLLY_SERVER_TOKEN set"))
+    })?;
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match bearer {
+        Some(bearer) if bearer == token => Ok(()),
+        _ => Err(AppError(StatusCode::UNAUTHORIZED, anyhow!("ingestion needs an Authorization: Bearer header with the server token"))),
+    }
+}
+
+async fn ingest(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(requests): Json<Vec<IngestRequest>>,
+) -> Result<Response, AppError> {
+    authorize_ingest(&state, &headers)?;
+    let tickers = requests
+        .into_iter()
+        .map(|request| {
+            to_params(
+                request.ticker,
+                request.exchange,
+                request.collection,
+                request.source,
+                request.from,
+                request.to,
+            )
+        })
+        .collect::<Result<Vec<MongoTickerParams>, AppError>>()?;
+    let report = state.client.ingest(&tickers).await?;
+    let status = match report.has_failures() {
+        true => StatusCode::MULTI_STATUS,
+        false => StatusCode::OK,
+    };
+    Ok((status, Json(report)).into_response())
+}
+
+fn app(state: AppState) -> Router {
+    Router::new()
+        .route("/collections", get(list_collections))
+        .route("/collections/:collection", get(list_series))
+        .route("/series/:collection/:exchange/:ticker", get(read_series))
+        .route("/ingest", post(ingest))
+        .with_state(state)
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::init();
+    let config = Config::load()?;
+    let client = MongoDbClient::builder().config(&config).build().await?;
+    let ingest_token = std::env::var("MOLLY_SERVER_TOKEN").ok().filter(|token| !token.is_empty());
+    if ingest_token.is_none() {
+        log::warn!("molly_server MOLLY_SERVER_TOKEN is not set, POST /ingest is disabled");
+    }
+    let app = app(AppState { client, ingest_token });
+
+    let addr = std::env::var("MOLLY_SERVER_ADDR").unwrap_or_else(|_| DEFAULT_SERVER_ADDR.to_string());
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    log::info!("molly_server listening on {}", addr);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    // nothing listens on port 1, so the routes that reach the database fail fast instead of hanging
+    async fn test_app(ingest_token: Option<&str>) -> Router {
+        let client = MongoDbClient::builder()
+            .uri("mongodb://127.0.0.1:1/?serverSelectionTimeoutMS=100")
+            .series_db("molly_db_test")
+            .metadata_db("molly_db_metadata_test")
+            .build()
+            .await
+            .unwrap();
+        app(AppState {
+            client,
+            ingest_token: ingest_token.map(|token| token.to_string()),
+        })
+    }
+
+    async fn send(app: Router, request: Request<Body>) -> (StatusCode, String) {
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    fn ingest_request(token: Option<&str>, body: &str) -> Request<Body> {
+        let mut request = Request::post("/ingest").header(header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        request.body(Body::from(body.to_string())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_routes() {
+        let get = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+
+        // database errors come back as a 500 with the error rather than a dropped connection
+        let (status, body) = send(test_app(None).await, get("/collections")).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!body.is_empty());
+        let (status, _) = send(test_app(None).await, get("/collections/equity_spot_1d")).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+
+        // bad queries are rejected before the database is asked
+        let (status, body) = send(test_app(None).await, get("/series/equity_spot_1d/US/AAPL?from=01/02/2024")).await;
+        assert_eq!((status, body.contains("01/02/2024")), (StatusCode::BAD_REQUEST, true));
+        let (status, _) = send(test_app(None).await, get("/series/equity_spot_1d/US/AAPL?format=xml")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = send(test_app(None).await, get("/series/equity_spot_1d/US")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // ingestion needs the token of the server and is off when the server has none
+        let body = r#"[{"ticker":"AAPL","exchange":"US","collection":"equity_spot_1d","from":"yesterday-ish"}]"#;
+        let (status, _) = send(test_app(None).await, ingest_request(Some("secret"), body)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(test_app(Some("secret")).await, ingest_request(None, body)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(test_app(Some("secret")).await, ingest_request(Some("guess"), body)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, body) = send(test_app(Some("secret")).await, ingest_request(Some("secret"), body)).await;
+        assert_eq!((status, body.contains("yesterday-ish")), (StatusCode::BAD_REQUEST, true));
+        let (status, _) = send(test_app(Some("secret")).await, get("/ingest")).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
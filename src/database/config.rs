@@ -0,0 +1,140 @@
+use anyhow::{anyhow, Result};
+use dotenv::dotenv;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use crate::database::models::synthetic_models::SyntheticConfig;
+
+// path of the config file used by Config::load() when MOLLY_CONFIG is not set
+const DEFAULT_CONFIG_PATH: &str = "molly.toml";
+
+/*
+    Config file layout:
+
+    [mongodb]
+    uri = "mongodb://localhost:27017"
+    series_db = "molly_db"
+    metadata_db = "molly_db_metadata"
+    events_db = "molly_db_events"
+    concurrency = 16
+
+    [eod]
+    api_token = "..."
+
+    [synthetic]
+    seed = 42
+    model = { kind = "jump_diffusion", drift = 0.05, volatility = 0.25, jump_intensity = 2.0, jump_mean = -0.05, jump_volatility = 0.1 }
+    defects = { gap_probability = 0.01, split_probability = 0.001, bad_tick_probability = 0.001 }
+
+    [synthetic.models]
+    "TSLA.US" = { kind = "gbm", drift = 0.1, volatility = 0.6 }
+*/
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    pub mongodb: MongoDbConfig,
+    pub eod: EodConfig,
+    // settings of the synthetic datasource, see SyntheticConfig
+    pub synthetic: SyntheticConfig,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct MongoDbConfig {
+    pub uri: Option<String>,
+    pub series_db: Option<String>,
+    pub metadata_db: Option<String>,
+    // corporate events, the series database name with an _events suffix unless set
+    pub events_db: Option<String>,
+    pub concurrency: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct EodConfig {
+    pub api_token: Option<String>,
+}
+
+impl Config {
+    pub fn from_toml_str(contents: &str) -> Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let contents = fs::read_to_string(path.as_ref())
+            .map_err(|e| anyhow!("Could not read config file {}: {}", path.as_ref().display(), e))?;
+        Self::from_toml_str(&contents)
+    }
+
+    // the .env file and environment take precedence over whatever the config file says
+    pub fn with_env_overrides(mut self) -> Self {
+        dotenv().ok();
+        if let Ok(uri) = env::var("MONGODB_URI") {
+            self.mongodb.uri = Some(uri);
+        }
+        if let Ok(series_db) = env::var("MONGODB_NAME") {
+            self.mongodb.series_db = Some(series_db);
+        }
+        if let Ok(metadata_db) = env::var("MONGODB_METADATA_NAME") {
+            self.mongodb.metadata_db = Some(metadata_db);
+        }
+        if let Ok(events_db) = env::var("MONGODB_EVENTS_NAME") {
+            self.mongodb.events_db = Some(events_db);
+        }
+        if let Ok(api_token) = env::var("API_TOKEN") {
+            self.eod.api_token = Some(api_token);
+        }
+        self
+    }
+
+    // reads the file at MOLLY_CONFIG (or ./molly.toml if it exists) and applies env overrides
+    pub fn load() -> Result<Self> {
+        dotenv().ok();
+        let config = match env::var("MOLLY_CONFIG") {
+            Ok(path) => Self::from_file(path)?,
+            Err(_) if Path::new(DEFAULT_CONFIG_PATH).exists() => Self::from_file(DEFAULT_CONFIG_PATH)?,
+            Err(_) => Self::default(),
+        };
+        Ok(config.with_env_overrides())
+    }
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::synthetic_models::PriceModel;
+
+    #[test]
+    fn test_config_from_toml_str() {
+        let config = Config::from_toml_str(
+            r#"
+            [mongodb]
+            uri = "mongodb://localhost:27017"
+            series_db = "molly_db"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.mongodb.uri.as_deref(), Some("mongodb://localhost:27017"));
+        assert_eq!(config.mongodb.series_db.as_deref(), Some("molly_db"));
+        assert_eq!(config.mongodb.metadata_db, None);
+        assert_eq!(config.mongodb.events_db, None);
+        assert_eq!(config.eod.api_token, None);
+
+        let config = Config::from_toml_str(
+            r#"
+            [synthetic]
+            seed = 7
+
+            [synthetic.models]
+            "TSLA.US" = { kind = "gbm", drift = 0.1, volatility = 0.6 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.synthetic.seed, 7);
+        assert_eq!(config.synthetic.model_for("TSLA", "US"), &PriceModel::Gbm { drift: 0.1, volatility: 0.6 });
+        assert_eq!(config.synthetic.model_for("AAPL", "US"), &PriceModel::default());
+    }
+}
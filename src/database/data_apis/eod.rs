@@ -1,16 +1,26 @@
-use anyhow::Result;
-use dotenv::dotenv;
+use anyhow::{anyhow, Result};
+use chrono_tz::Tz;
+use futures::{StreamExt, TryStreamExt};
+use mongodb::bson;
 use polars::frame::DataFrame;
 use polars::prelude::*;
 use reqwest::Client;
-use serde_json::to_string;
-use std::{collections::HashMap, env};
+use serde_json::{to_string, Value};
+use std::collections::HashMap;
 
+use crate::database::config::Config;
 use crate::database::models::eod_models::{MongoTickerParams, OhlcvMetaData};
+use crate::database::models::event_models::{AnnouncementTime, EarningsEvent, IpoEvent, SplitEvent};
+use crate::database::models::macro_models::MacroValue;
+use crate::database::models::news_models::NewsItem;
+use crate::database::models::quote_models::Quote;
+use crate::database::models::schema_models::{CollectionKind, CollectionType, Granularity};
 use crate::database::utility_functions::{
-    async_http_request, get_current_date_string, get_current_datetime_bson, get_timestamps_tuple,
+    async_http_request, exchange_timezone, get_current_date_string, get_current_datetime_bson, get_timestamps_tuple,
+    parse_source_datetime, utc_datetime_series,
 };
 
+#[derive(Clone)]
 pub struct EodApi {
     client: Client,
     api_token: String,
@@ -18,13 +28,36 @@ pub struct EodApi {
 
 impl EodApi {
     pub async fn new() -> Self {
-        dotenv().ok();
-        let eod_api_token = env::var("API_TOKEN").expect("Failed tp parse API_TOKEN from .env");
+        let config = Config::load().expect("Failed to load config");
+        let eod_api_token = config.eod.api_token.expect("Failed tp parse API_TOKEN from .env");
+        Self::with_token(eod_api_token)
+    }
+
+    pub fn with_token(api_token: impl Into<String>) -> Self {
         log::info!("Established Client for EOD API");
         Self {
             client: Client::new(),
-            api_token: eod_api_token,
+            api_token: api_token.into(),
+        }
+    }
+
+    // daily bars are dated in exchange time, intraday and real-time bars carry unix timestamps
+    pub fn source_timezone(ticker: &MongoTickerParams) -> Tz {
+        match CollectionType::parse(&ticker.series_collection_name).is_daily() {
+            true => exchange_timezone(&ticker.exchange),
+            false => Tz::UTC,
+        }
+    }
+
+    // intraday "datetime" strings are in UTC but unlabelled, so the unix "timestamp" is used instead
+    fn timestamp_to_datetime(df: DataFrame) -> Result<DataFrame> {
+        if !df.get_column_names().contains(&"timestamp") {
+            return Ok(df);
         }
+        let millis = df.column("timestamp")?.cast(&DataType::Int64)? * 1000;
+        let mut df = df.drop_many(&["timestamp", "gmtoffset", "datetime"]);
+        df.with_column(utc_datetime_series("datetime", millis.i64()?.clone()))?;
+        Ok(df)
     }
 
     fn get_metadata(&self, ticker: MongoTickerParams) -> Result<OhlcvMetaData> {
@@ -139,21 +172,54 @@ impl EodApi {
         }
     }
 
+    // the dfs of every ticker, one per request made for it, with the error of each ticker that could not be fetched
     pub async fn batch_get_series_all(
         &self,
         tickers: Vec<MongoTickerParams>,
-    ) -> Result<Vec<DataFrame>> {
+    ) -> Result<Vec<(MongoTickerParams, Result<DataFrame>)>> {
         let mut urls = Vec::new();
+        let mut sentiment_tickers = Vec::new();
+        let mut dfs_with_metadata = Vec::new();
         for ticker in tickers.clone().into_iter() {
             let end_date_string = get_current_date_string();
-            let granularity = ticker.series_collection_name.chars().last();
+            let collection = CollectionType::parse(&ticker.series_collection_name);
             log::info!(
                 "batch_get_series_all() making url for: {} belonging to collection {}",
                 ticker.ticker,
                 ticker.series_collection_name
             );
-            match granularity {
-                Some('d') => {
+            match (collection.kind, collection.granularity) {
+                (CollectionKind::OptionQuotes, _) => {
+                    let e = anyhow!(
+                        "batch_get_series_all() {} holds option chains, store them with MongoDbClient::insert_option_quotes()",
+                        ticker.series_collection_name
+                    );
+                    dfs_with_metadata.push((ticker, Err(e)));
+                }
+                (CollectionKind::MacroIndicators, _) => {
+                    let e = anyhow!(
+                        "batch_get_series_all() {} holds macro indicators, fetch them with MongoDbClient::ingest_macro()",
+                        ticker.series_collection_name
+                    );
+                    dfs_with_metadata.push((ticker, Err(e)));
+                }
+                (CollectionKind::CurvePoints, _) => {
+                    let e = anyhow!(
+                        "batch_get_series_all() {} holds yield curves, store them with MongoDbClient::insert_curve_points()",
+                        ticker.series_collection_name
+                    );
+                    dfs_with_metadata.push((ticker, Err(e)));
+                }
+                (CollectionKind::NewsItems, _) => {
+                    let e = anyhow!(
+                        "batch_get_series_all() {} holds news items, fetch them with MongoDbClient::ingest_news()",
+                        ticker.series_collection_name
+                    );
+                    dfs_with_metadata.push((ticker, Err(e)));
+                }
+                // the sentiment api answers an object keyed by ticker rather than rows, so it is fetched on its own
+                (CollectionKind::DailySentiment, _) => sentiment_tickers.push(ticker),
+                (CollectionKind::Bars | CollectionKind::Futures, Some(Granularity::Days(_))) => {
                     let from_date = &ticker.from.to_string()[..10];
                     let url = format!(
                         "https://eodhistoricaldata.com/api/eod/{}.{}?api_token={}&fmt=json&from={}&to={}",
@@ -161,15 +227,8 @@ impl EodApi {
                     );
                     urls.push((ticker, url));
                 }
-                Some('h') | Some('m') => {
+                (CollectionKind::Bars | CollectionKind::Futures, Some(interval @ (Granularity::Hours(_) | Granularity::Minutes(_)))) => {
                     let end_date_datetime = get_current_datetime_bson();
-                    let collection_name_split = &ticker
-                        .series_collection_name
-                        .split('_')
-                        .collect::<Vec<&str>>();
-                    let interval = collection_name_split.last().expect(
-                        "batch_get_series_all() failed to get interval from collection_name",
-                    );
                     let timestamps_tuple =
                         get_timestamps_tuple(ticker.from, end_date_datetime, interval)?;
                     for (from, to) in timestamps_tuple.iter() {
@@ -180,17 +239,21 @@ impl EodApi {
                         urls.push((ticker.clone(), url));
                     }
                 }
-                Some('e') => {
-                    let url = format!(
-                        "https://eodhistoricaldata.com/api/real-time/{}.{}?api_token={}&fmt=json",
-                        ticker.ticker, ticker.exchange, self.api_token,
+                (CollectionKind::LiveQuotes, _) => {
+                    let e = anyhow!(
+                        "batch_get_series_all() {} is a live collection, poll it with MongoDbClient::poll_quotes()",
+                        ticker.series_collection_name
                     );
-                    urls.push((ticker, url));
+                    dfs_with_metadata.push((ticker, Err(e)));
+                }
+                _ => {
+                    let e = anyhow!(
+                        "batch_get_series_all() Could not parse granularity of {} for: {}",
+                        ticker.series_collection_name,
+                        ticker.ticker
+                    );
+                    dfs_with_metadata.push((ticker, Err(e)));
                 }
-                _ => log::error!(
-                    "batch_get_series_all() Could not parse granularity for: {}",
-                    ticker.ticker
-                ),
             }
         }
 
@@ -199,17 +262,648 @@ impl EodApi {
             urls_unique.entry(url).or_insert(params);
         }
 
-        let dfs = async_http_request(self.client.clone(), urls_unique).await?;
+        let dfs = async_http_request(self.client.clone(), urls_unique).await;
 
-        let mut dfs_with_metadata = Vec::new();
-        for (param, mut df) in dfs.into_iter() {
-            let metadata = self.get_metadata(param)?;
-            let series = Series::new("metadata", vec![to_string(&metadata)?; df.height()]);
-            df.with_column(series)?;
-            dfs_with_metadata.push(df);
+        for (param, df) in dfs.into_iter() {
+            let df = df.and_then(|df| {
+                let mut df = Self::timestamp_to_datetime(df)?;
+                let metadata = self.get_metadata(param.clone())?;
+                let series = Series::new("metadata", vec![to_string(&metadata)?; df.height()]);
+                df.with_column(series)?;
+                Ok(df)
+            });
+            dfs_with_metadata.push((param, df));
+        }
+        for ticker in sentiment_tickers.into_iter() {
+            let df = self.get_sentiment(&ticker).await;
+            dfs_with_metadata.push((ticker, df));
         }
 
         log::info!("batch_get_series_all() successfully retrieved all dataframes from EOD");
         Ok(dfs_with_metadata)
     }
+
+    // the currency code of the fundamentals of an instrument, which EOD leaves empty or "NA" where it has none
+    fn parse_currency(body: &Value) -> Option<String> {
+        body.as_str()
+            .map(|currency| currency.trim())
+            .filter(|currency| currency.len() == 3 && currency.chars().all(|c| c.is_ascii_alphabetic()))
+            .map(|currency| currency.to_string())
+    }
+
+    // the currency an instrument is quoted in, for exchanges that list instruments in several currencies
+    pub async fn get_currency(&self, ticker: &MongoTickerParams) -> Result<Option<String>> {
+        log::info!("get_currency() fetching the currency of {}.{}", ticker.ticker, ticker.exchange);
+        let url = format!(
+            "https://eodhistoricaldata.com/api/fundamentals/{}.{}?filter=General::CurrencyCode&api_token={}&fmt=json",
+            ticker.ticker, ticker.exchange, self.api_token,
+        );
+        let body = self.client.get(url).send().await?.error_for_status()?.json::<Value>().await?;
+        Ok(Self::parse_currency(&body))
+    }
+
+    // the real-time api answers up to this many tickers per request
+    const QUOTES_PER_REQUEST: usize = 15;
+
+    // EOD sends "NA" for fields it has no value for
+    fn quote_field(quote: &Value, field: &str) -> Option<f64> {
+        quote.get(field).and_then(|value| value.as_f64())
+    }
+
+    fn parse_quote(&self, ticker: &MongoTickerParams, quote: &Value) -> Result<Quote> {
+        let last = Self::quote_field(quote, "close")
+            .ok_or_else(|| anyhow!("get_quotes() EOD returned no last price for {}.{}", ticker.ticker, ticker.exchange))?;
+        let datetime = match quote.get("timestamp").and_then(|timestamp| timestamp.as_i64()) {
+            Some(timestamp) => bson::DateTime::from_millis(timestamp * 1000),
+            None => get_current_datetime_bson(),
+        };
+        Ok(Quote {
+            datetime,
+            last,
+            bid: Self::quote_field(quote, "bid"),
+            ask: Self::quote_field(quote, "ask"),
+            change: Self::quote_field(quote, "change"),
+            change_percent: Self::quote_field(quote, "change_p"),
+            volume: Self::quote_field(quote, "volume"),
+            metadata: self.get_metadata(ticker.clone())?,
+        })
+    }
+
+    // values EOD has no number for are left out, EOD does not publish release dates
+    fn parse_macro_values(ticker: &MongoTickerParams, body: &Value) -> Result<Vec<MacroValue>> {
+        let rows = body
+            .as_array()
+            .ok_or_else(|| anyhow!("get_macro_indicator() EOD returned no rows for {}.{}: {}", ticker.ticker, ticker.exchange, body))?;
+        let mut values = Vec::new();
+        for row in rows.iter() {
+            let (date, value) = match (row.get("Date").and_then(|date| date.as_str()), Self::quote_field(row, "Value")) {
+                (Some(date), Some(value)) => (date, value),
+                _ => continue,
+            };
+            values.push(MacroValue {
+                reference_period: parse_source_datetime(date, Self::source_timezone(ticker))?,
+                period: row.get("Period").and_then(|period| period.as_str()).unwrap_or_default().to_string(),
+                value,
+                release_date: None,
+            });
+        }
+        Ok(values)
+    }
+
+    // history of a macro indicator, the ticker is the indicator and the exchange the alpha-3 country code
+    pub async fn get_macro_indicator(&self, ticker: &MongoTickerParams) -> Result<Vec<MacroValue>> {
+        log::info!("get_macro_indicator() fetching {} for {}", ticker.ticker, ticker.exchange);
+        let url = format!(
+            "https://eodhistoricaldata.com/api/macro-indicator/{}?indicator={}&api_token={}&fmt=json",
+            ticker.exchange, ticker.ticker, self.api_token,
+        );
+        let body = self.client.get(url).send().await?.error_for_status()?.json::<Value>().await?;
+        Self::parse_macro_values(ticker, &body)
+    }
+
+    // the symbols the earnings calendar answers per request
+    const EARNINGS_SYMBOLS_PER_REQUEST: usize = 50;
+
+    // calendar dates as EOD takes them in requests
+    fn request_date(datetime: bson::DateTime) -> String {
+        let datetime: chrono::DateTime<chrono::Utc> = datetime.into();
+        datetime.format("%Y-%m-%d").to_string()
+    }
+
+    // calendar fields are numbers, numbers as strings, null or "NA"
+    fn event_field(event: &Value, field: &str) -> Option<f64> {
+        match event.get(field) {
+            Some(Value::String(value)) => value.parse::<f64>().ok(),
+            Some(value) => value.as_f64(),
+            None => None,
+        }
+    }
+
+    // calendar dates are exchange dates, stored at midnight UTC like daily bars, times are exchange time
+    fn event_date(event: &Value, field: &str, exchange: &str) -> Option<bson::DateTime> {
+        event
+            .get(field)
+            .and_then(|date| date.as_str())
+            .and_then(|date| parse_source_datetime(date, exchange_timezone(exchange)).ok())
+    }
+
+    fn parse_earnings(tickers: &[MongoTickerParams], body: &Value, fetched_at: bson::DateTime) -> Result<Vec<EarningsEvent>> {
+        let rows = body
+            .get("earnings")
+            .and_then(|rows| rows.as_array())
+            .ok_or_else(|| anyhow!("get_earnings() EOD returned no earnings: {}", body))?;
+        let mut events = Vec::new();
+        for row in rows.iter() {
+            let code = row.get("code").and_then(|code| code.as_str()).unwrap_or_default();
+            let ticker = match tickers.iter().find(|ticker| format!("{}.{}", ticker.ticker, ticker.exchange) == code) {
+                Some(ticker) => ticker,
+                None => continue,
+            };
+            let (announcement_date, fiscal_period_end) = match (
+                Self::event_date(row, "report_date", &ticker.exchange),
+                Self::event_date(row, "date", &ticker.exchange),
+            ) {
+                (Some(announcement_date), Some(fiscal_period_end)) => (announcement_date, fiscal_period_end),
+                _ => continue,
+            };
+            let time_of_day = match row.get("before_after_market").and_then(|time| time.as_str()) {
+                Some("BeforeMarket") => AnnouncementTime::BeforeMarket,
+                Some("AfterMarket") => AnnouncementTime::AfterMarket,
+                Some("DuringMarket") => AnnouncementTime::DuringMarket,
+                _ => AnnouncementTime::Unknown,
+            };
+            let actual = Self::event_field(row, "actual");
+            events.push(EarningsEvent {
+                ticker: ticker.ticker.clone(),
+                exchange: ticker.exchange.clone(),
+                source: ticker.source.clone(),
+                fiscal_period_end,
+                announcement_date,
+                time_of_day,
+                currency: row.get("currency").and_then(|currency| currency.as_str()).map(|currency| currency.to_string()),
+                estimate: Self::event_field(row, "estimate"),
+                actual,
+                surprise: Self::event_field(row, "difference"),
+                surprise_percent: Self::event_field(row, "percent"),
+                // EOD fills in actual once the earnings are reported, on the announcement date
+                confirmed: actual.is_some() && announcement_date <= fetched_at,
+                date_changes: 0,
+                first_seen: fetched_at,
+                last_updated: fetched_at,
+            });
+        }
+        Ok(events)
+    }
+
+    // earnings announced, or expected, between from and to for every ticker, in requests of up to 50 tickers
+    pub async fn get_earnings(&self, tickers: &[MongoTickerParams], from: bson::DateTime, to: bson::DateTime) -> Result<Vec<EarningsEvent>> {
+        let fetched_at = get_current_datetime_bson();
+        let mut events = Vec::new();
+        for chunk in tickers.chunks(Self::EARNINGS_SYMBOLS_PER_REQUEST) {
+            let codes = chunk
+                .iter()
+                .map(|ticker| format!("{}.{}", ticker.ticker, ticker.exchange))
+                .collect::<Vec<String>>();
+            log::info!("get_earnings() fetching earnings for {} tickers", codes.len());
+            let url = format!(
+                "https://eodhistoricaldata.com/api/calendar/earnings?symbols={}&from={}&to={}&api_token={}&fmt=json",
+                codes.join(","),
+                Self::request_date(from),
+                Self::request_date(to),
+                self.api_token,
+            );
+            let body = self.client.get(url).send().await?.error_for_status()?.json::<Value>().await?;
+            events.extend(Self::parse_earnings(chunk, &body, fetched_at)?);
+        }
+        Ok(events)
+    }
+
+    // EOD reports splits as "new/old" shares
+    fn parse_splits(ticker: &MongoTickerParams, body: &Value, fetched_at: bson::DateTime) -> Result<Vec<SplitEvent>> {
+        let rows = body
+            .as_array()
+            .ok_or_else(|| anyhow!("get_splits() EOD returned no splits for {}.{}: {}", ticker.ticker, ticker.exchange, body))?;
+        let mut events = Vec::new();
+        for row in rows.iter() {
+            let split = row.get("split").and_then(|split| split.as_str()).and_then(|split| split.split_once('/'));
+            let shares = split.and_then(|(new_shares, old_shares)| new_shares.parse::<f64>().ok().zip(old_shares.parse::<f64>().ok()));
+            match (Self::event_date(row, "date", &ticker.exchange), shares) {
+                (Some(split_date), Some((new_shares, old_shares))) if new_shares > 0.0 && old_shares > 0.0 => events.push(SplitEvent {
+                    ticker: ticker.ticker.clone(),
+                    exchange: ticker.exchange.clone(),
+                    source: ticker.source.clone(),
+                    split_date,
+                    old_shares,
+                    new_shares,
+                    last_updated: fetched_at,
+                }),
+                _ => log::error!("get_splits() skipping unparseable split of {}.{}: {}", ticker.ticker, ticker.exchange, row),
+            }
+        }
+        Ok(events)
+    }
+
+    // splits of a ticker between from and to
+    pub async fn get_splits(&self, ticker: &MongoTickerParams, from: bson::DateTime, to: bson::DateTime) -> Result<Vec<SplitEvent>> {
+        log::info!("get_splits() fetching splits of {}.{}", ticker.ticker, ticker.exchange);
+        let url = format!(
+            "https://eodhistoricaldata.com/api/splits/{}.{}?from={}&to={}&api_token={}&fmt=json",
+            ticker.ticker,
+            ticker.exchange,
+            Self::request_date(from),
+            Self::request_date(to),
+            self.api_token,
+        );
+        let body = self.client.get(url).send().await?.error_for_status()?.json::<Value>().await?;
+        Self::parse_splits(ticker, &body, get_current_datetime_bson())
+    }
+
+    // ipos are keyed by the code they list as, rows without an exchange in their code are skipped
+    fn parse_ipos(body: &Value, fetched_at: bson::DateTime) -> Result<Vec<IpoEvent>> {
+        let rows = body
+            .get("ipos")
+            .and_then(|rows| rows.as_array())
+            .ok_or_else(|| anyhow!("get_ipos() EOD returned no ipos: {}", body))?;
+        let text = |row: &Value, field: &str| row.get(field).and_then(|value| value.as_str()).unwrap_or_default().to_string();
+        let mut events = Vec::new();
+        for row in rows.iter() {
+            let code = text(row, "code");
+            let (ticker, exchange) = match code.rsplit_once('.') {
+                Some((ticker, exchange)) => (ticker.to_string(), exchange.to_string()),
+                None => continue,
+            };
+            events.push(IpoEvent {
+                name: text(row, "name"),
+                venue: text(row, "exchange"),
+                listing_date: Self::event_date(row, "start_date", &exchange),
+                filing_date: Self::event_date(row, "filing_date", &exchange),
+                amended_date: Self::event_date(row, "amended_date", &exchange),
+                price_from: Self::event_field(row, "price_from"),
+                price_to: Self::event_field(row, "price_to"),
+                offer_price: Self::event_field(row, "offer_price"),
+                shares: Self::event_field(row, "shares"),
+                deal_type: text(row, "deal_type"),
+                currency: row.get("currency").and_then(|currency| currency.as_str()).map(|currency| currency.to_string()),
+                ticker,
+                exchange,
+                source: "eod".to_string(),
+                last_updated: fetched_at,
+            });
+        }
+        Ok(events)
+    }
+
+    // ipos filed, expected or priced between from and to on every exchange
+    pub async fn get_ipos(&self, from: bson::DateTime, to: bson::DateTime) -> Result<Vec<IpoEvent>> {
+        log::info!("get_ipos() fetching ipos between {} and {}", from, to);
+        let url = format!(
+            "https://eodhistoricaldata.com/api/calendar/ipos?from={}&to={}&api_token={}&fmt=json",
+            Self::request_date(from),
+            Self::request_date(to),
+            self.api_token,
+        );
+        let body = self.client.get(url).send().await?.error_for_status()?.json::<Value>().await?;
+        Self::parse_ipos(&body, get_current_datetime_bson())
+    }
+
+    // the news api answers up to this many items per request, later ones are paged through with an offset
+    const NEWS_PER_REQUEST: usize = 1000;
+
+    // the publisher of an item, the host of its link
+    fn news_source(link: &str) -> String {
+        let host = link.split_once("://").map_or(link, |(_, rest)| rest);
+        host.split('/').next().unwrap_or_default().trim_start_matches("www.").to_string()
+    }
+
+    fn parse_news(ticker: &MongoTickerParams, metadata: &OhlcvMetaData, body: &Value) -> Result<Vec<NewsItem>> {
+        let rows = body
+            .as_array()
+            .ok_or_else(|| anyhow!("get_news() EOD returned no news for {}.{}: {}", ticker.ticker, ticker.exchange, body))?;
+        let mut items = Vec::new();
+        for row in rows.iter() {
+            let (date, headline) = match (row.get("date").and_then(|date| date.as_str()), row.get("title").and_then(|title| title.as_str())) {
+                (Some(date), Some(headline)) => (date, headline),
+                _ => continue,
+            };
+            // one malformed date should not cost the rest of the fetch
+            let datetime = match parse_source_datetime(date, Self::source_timezone(ticker)) {
+                Ok(datetime) => datetime,
+                Err(e) => {
+                    log::warn!("get_news() skipping news item {:?} about {}.{}: {:#}", headline, ticker.ticker, ticker.exchange, e);
+                    continue;
+                }
+            };
+            let url = row.get("link").and_then(|link| link.as_str()).map(|link| link.to_string());
+            let tickers = row
+                .get("symbols")
+                .and_then(|symbols| symbols.as_array())
+                .map(|symbols| symbols.iter().filter_map(|symbol| symbol.as_str().map(|symbol| symbol.to_string())).collect())
+                .unwrap_or_default();
+            items.push(NewsItem {
+                datetime,
+                headline: headline.to_string(),
+                source: url.as_deref().map(Self::news_source).unwrap_or_default(),
+                url,
+                tickers,
+                sentiment: row.get("sentiment").and_then(|sentiment| Self::event_field(sentiment, "polarity")),
+                metadata: metadata.clone(),
+            });
+        }
+        Ok(items)
+    }
+
+    // news items about a ticker published between its from and to, with the polarity EOD scores them with
+    pub async fn get_news(&self, ticker: &MongoTickerParams) -> Result<Vec<NewsItem>> {
+        log::info!("get_news() fetching news about {}.{}", ticker.ticker, ticker.exchange);
+        let metadata = self.get_metadata(ticker.clone())?;
+        let mut items = Vec::new();
+        for page in 0.. {
+            let url = format!(
+                "https://eodhistoricaldata.com/api/news?s={}.{}&from={}&to={}&limit={}&offset={}&api_token={}&fmt=json",
+                ticker.ticker,
+                ticker.exchange,
+                Self::request_date(ticker.from),
+                Self::request_date(ticker.to),
+                Self::NEWS_PER_REQUEST,
+                page * Self::NEWS_PER_REQUEST,
+                self.api_token,
+            );
+            let body = self.client.get(url).send().await?.error_for_status()?.json::<Value>().await?;
+            let rows = body.as_array().map_or(0, |rows| rows.len());
+            items.extend(Self::parse_news(ticker, &metadata, &body)?);
+            if rows < Self::NEWS_PER_REQUEST {
+                break;
+            }
+        }
+        // the api works in dates, items from before from are already stored or were not asked for
+        items.retain(|item| item.datetime >= ticker.from);
+        Ok(items)
+    }
+
+    // days before today, whose sentiment is final, today's still changes as news comes in
+    fn parse_sentiment(ticker: &MongoTickerParams, body: &Value, today: &str) -> Result<(Vec<String>, Vec<f64>, Vec<i64>)> {
+        let code = format!("{}.{}", ticker.ticker, ticker.exchange);
+        let rows = match body.get(&code) {
+            Some(rows) => rows
+                .as_array()
+                .ok_or_else(|| anyhow!("get_sentiment() EOD returned no sentiment for {}: {}", code, body))?,
+            None => return Ok((Vec::new(), Vec::new(), Vec::new())),
+        };
+        let mut rows = rows
+            .iter()
+            .filter_map(|row| {
+                let date = row.get("date").and_then(|date| date.as_str()).filter(|date| *date < today)?;
+                let sentiment = Self::event_field(row, "normalized")?;
+                let count = row.get("count").and_then(|count| count.as_i64()).unwrap_or(0);
+                Some((date.to_string(), sentiment, count))
+            })
+            .collect::<Vec<(String, f64, i64)>>();
+        rows.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut columns = (Vec::new(), Vec::new(), Vec::new());
+        for (date, sentiment, count) in rows.into_iter() {
+            columns.0.push(date);
+            columns.1.push(sentiment);
+            columns.2.push(count);
+        }
+        Ok(columns)
+    }
+
+    // daily sentiment of a ticker from its from to yesterday, as a df of date, sentiment and count ready for insert_series()
+    pub async fn get_sentiment(&self, ticker: &MongoTickerParams) -> Result<DataFrame> {
+        log::info!("get_sentiment() fetching daily sentiment of {}.{}", ticker.ticker, ticker.exchange);
+        let today = get_current_date_string();
+        let url = format!(
+            "https://eodhistoricaldata.com/api/sentiments?s={}.{}&from={}&to={}&api_token={}&fmt=json",
+            ticker.ticker,
+            ticker.exchange,
+            Self::request_date(ticker.from),
+            today,
+            self.api_token,
+        );
+        let body = self.client.get(url).send().await?.error_for_status()?.json::<Value>().await?;
+        let (dates, sentiments, counts) = Self::parse_sentiment(ticker, &body, &today)?;
+        let metadata = self.get_metadata(ticker.clone())?;
+        let df = DataFrame::new(vec![
+            Series::new("metadata", vec![to_string(&metadata)?; dates.len()]),
+            Series::new("date", dates),
+            Series::new("sentiment", sentiments),
+            Series::new("count", counts),
+        ])?;
+        Ok(df)
+    }
+
+    // latest quote of each ticker, tickers EOD has no usable quote for are left out
+    pub async fn get_quotes(&self, tickers: &[MongoTickerParams]) -> Result<Vec<Quote>> {
+        let requests = tickers.chunks(Self::QUOTES_PER_REQUEST).map(|chunk| {
+            let codes = chunk
+                .iter()
+                .map(|ticker| format!("{}.{}", ticker.ticker, ticker.exchange))
+                .collect::<Vec<String>>();
+            let mut url = format!(
+                "https://eodhistoricaldata.com/api/real-time/{}?api_token={}&fmt=json",
+                codes[0], self.api_token,
+            );
+            if codes.len() > 1 {
+                url.push_str(&format!("&s={}", codes[1..].join(",")));
+            }
+            let client = self.client.clone();
+            async move {
+                log::info!("get_quotes() fetching quotes for {} tickers", codes.len());
+                let body = client.get(url).send().await?.error_for_status()?.json::<Value>().await?;
+                Ok::<_, anyhow::Error>(body)
+            }
+        });
+        let bodies = futures::stream::iter(requests)
+            .buffer_unordered(4)
+            .try_collect::<Vec<Value>>()
+            .await?;
+
+        // a single ticker comes back as an object, several as an array
+        let mut quotes = Vec::new();
+        for body in bodies.iter() {
+            let body_quotes = match body {
+                Value::Array(body_quotes) => body_quotes.iter().collect::<Vec<&Value>>(),
+                body_quote => vec![body_quote],
+            };
+            for quote in body_quotes {
+                let code = quote.get("code").and_then(|code| code.as_str()).unwrap_or_default();
+                let ticker = tickers
+                    .iter()
+                    .find(|ticker| format!("{}.{}", ticker.ticker, ticker.exchange) == code);
+                match ticker.map(|ticker| self.parse_quote(ticker, quote)) {
+                    Some(Ok(quote)) => quotes.push(quote),
+                    Some(Err(e)) => log::warn!("get_quotes() skipping quote: {:#}", e),
+                    None => log::warn!("get_quotes() EOD returned a quote for unrequested code {}", code),
+                }
+            }
+        }
+        Ok(quotes)
+    }
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_quote() {
+        let eod_api = EodApi::with_token("demo");
+        let ticker = MongoTickerParams {
+            ticker: "AAPL".to_string(),
+            exchange: "US".to_string(),
+            series_collection_name: "equity_spot_live".to_string(),
+            source: "eod".to_string(),
+            from: bson::DateTime::from_millis(0),
+            to: get_current_datetime_bson(),
+        };
+        let response: Value = serde_json::from_str(
+            r#"{"code":"AAPL.US","timestamp":1704205800,"gmtoffset":0,"open":187.15,"high":188.44,
+                "low":183.89,"close":185.64,"volume":82488674,"previousClose":192.53,"change":-6.89,
+                "change_p":"NA"}"#,
+        )
+        .unwrap();
+
+        let quote = eod_api.parse_quote(&ticker, &response).unwrap();
+        assert_eq!(quote.datetime, bson::DateTime::from_millis(1_704_205_800_000));
+        assert_eq!(quote.last, 185.64);
+        assert_eq!(quote.change, Some(-6.89));
+        assert_eq!(quote.change_percent, None);
+        assert_eq!(quote.bid, None);
+        assert_eq!(quote.volume, Some(82_488_674.0));
+        assert_eq!(quote.metadata.metadata_collection_name, "equity_spot_live");
+
+        let delisted: Value = serde_json::from_str(r#"{"code":"AAPL.US","timestamp":"NA","close":"NA"}"#).unwrap();
+        assert!(eod_api.parse_quote(&ticker, &delisted).is_err());
+    }
+
+    #[test]
+    fn test_parse_macro_values() {
+        let ticker = MongoTickerParams {
+            ticker: "gdp_growth_annual".to_string(),
+            exchange: "USA".to_string(),
+            series_collection_name: "macro_indicator_1d".to_string(),
+            source: "eod".to_string(),
+            from: bson::DateTime::from_millis(0),
+            to: get_current_datetime_bson(),
+        };
+        // rows without a value or a date are skipped rather than stored as zero
+        let response: Value = serde_json::from_str(
+            r#"[{"CountryCode":"USA","CountryName":"United States","Indicator":"GDP growth (annual %)",
+                 "Date":"2022-12-31","Period":"Annual","Value":1.9355},
+                {"CountryCode":"USA","Date":"2021-12-31","Period":"Annual","Value":5.9455},
+                {"CountryCode":"USA","Date":"2023-12-31","Period":"Annual","Value":"NA"},
+                {"CountryCode":"USA","Date":"2020-12-31","Period":"Annual","Value":null},
+                {"CountryCode":"USA","Period":"Annual","Value":2.29}]"#,
+        )
+        .unwrap();
+
+        let values = EodApi::parse_macro_values(&ticker, &response).unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].reference_period, crate::database::utility_functions::parse_datetime("2022-12-31").unwrap());
+        assert_eq!((values[0].period.as_str(), values[0].value, values[0].release_date), ("Annual", 1.9355, None));
+        assert_eq!(values[1].value, 5.9455);
+
+        let error: Value = serde_json::from_str(r#"{"error":"Unknown indicator"}"#).unwrap();
+        assert!(EodApi::parse_macro_values(&ticker, &error).is_err());
+    }
+
+    fn event_ticker(ticker: &str, exchange: &str) -> MongoTickerParams {
+        MongoTickerParams {
+            ticker: ticker.to_string(),
+            exchange: exchange.to_string(),
+            series_collection_name: "equity_spot_1d".to_string(),
+            source: "eod".to_string(),
+            from: bson::DateTime::from_millis(0),
+            to: get_current_datetime_bson(),
+        }
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let date = |date: &str| crate::database::utility_functions::parse_datetime(date).unwrap();
+        let fetched_at = date("2024-02-05");
+
+        // reported earnings are confirmed, expected ones and codes not asked for are not
+        let earnings: Value = serde_json::from_str(
+            r#"{"type":"Earnings","earnings":[
+                {"code":"AAPL.US","report_date":"2024-02-01","date":"2023-12-31","before_after_market":"AfterMarket",
+                 "currency":"USD","actual":2.18,"estimate":"2.1","difference":0.08,"percent":3.8095},
+                {"code":"AAPL.US","report_date":"2024-05-02","date":"2024-03-31","before_after_market":null,
+                 "currency":"USD","actual":null,"estimate":1.5,"difference":"NA","percent":null},
+                {"code":"MSFT.US","report_date":"2024-01-30","date":"2023-12-31","actual":2.93},
+                {"code":"AAPL.US","report_date":"NA","date":"2024-06-30"}]}"#,
+        )
+        .unwrap();
+        let events = EodApi::parse_earnings(&[event_ticker("AAPL", "US")], &earnings, fetched_at).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[0].announcement_date, events[0].fiscal_period_end), (date("2024-02-01"), date("2023-12-31")));
+        assert_eq!(events[0].time_of_day, AnnouncementTime::AfterMarket);
+        assert_eq!((events[0].estimate, events[0].actual, events[0].surprise), (Some(2.1), Some(2.18), Some(0.08)));
+        assert!(events[0].confirmed);
+        assert_eq!((events[1].time_of_day, events[1].surprise, events[1].confirmed), (AnnouncementTime::Unknown, None, false));
+        assert!(EodApi::parse_earnings(&[event_ticker("AAPL", "US")], &Value::Array(vec![]), fetched_at).is_err());
+
+        // splits are new/old shares, malformed and zero ratios are skipped
+        let splits: Value = serde_json::from_str(
+            r#"[{"date":"2014-06-09","split":"7.000000/1.000000"},{"date":"2020-08-31","split":"4/1"},
+                {"date":"2021-01-04","split":"0/1"},{"date":"2022-01-03","split":"1:2"}]"#,
+        )
+        .unwrap();
+        let events = EodApi::parse_splits(&event_ticker("AAPL", "US"), &splits, fetched_at).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[1].split_date, events[1].new_shares, events[1].old_shares), (date("2020-08-31"), 4.0, 1.0));
+        assert_eq!(events[1].price_factor(), 0.25);
+
+        // ipos are keyed by the code they list as
+        let ipos: Value = serde_json::from_str(
+            r#"{"type":"IPOs","ipos":[
+                {"code":"ARM.US","name":"Arm Holdings plc","exchange":"NASDAQ","currency":"USD","start_date":"2023-09-14",
+                 "filing_date":"2023-08-21","amended_date":"2023-09-05","price_from":47,"price_to":51,"offer_price":"51",
+                 "shares":95500000,"deal_type":"Priced"},
+                {"code":"NOEXCHANGE","name":"Unlisted","exchange":"","start_date":"2023-09-14","deal_type":"Expected"}]}"#,
+        )
+        .unwrap();
+        let events = EodApi::parse_ipos(&ipos, fetched_at).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].ticker.as_str(), events[0].exchange.as_str(), events[0].venue.as_str()), ("ARM", "US", "NASDAQ"));
+        assert_eq!((events[0].listing_date, events[0].offer_price, events[0].shares), (Some(date("2023-09-14")), Some(51.0), Some(95_500_000.0)));
+        assert_eq!(events[0].deal_type, "Priced");
+    }
+
+    #[test]
+    fn test_parse_news() {
+        let mut ticker = event_ticker("AAPL", "US");
+        ticker.series_collection_name = "equity_news_items".to_string();
+        let metadata = EodApi::with_token("demo").get_metadata(ticker.clone()).unwrap();
+        let response: Value = serde_json::from_str(
+            r#"[{"date":"2024-01-02T14:30:00+00:00","title":"Apple cuts prices in China",
+                 "link":"https://www.reuters.com/technology/apple-china","symbols":["AAPL.US","AAPL.MX"],
+                 "sentiment":{"polarity":-0.42,"neg":0.1,"neu":0.8,"pos":0.1}},
+                {"date":"2024-01-0é","title":"Misdated"},
+                {"date":"2024-01-03T09:00:00+00:00","title":"Apple in brief","link":null,"symbols":[]},
+                {"date":null,"title":"Undated"}]"#,
+        )
+        .unwrap();
+
+        let items = EodApi::parse_news(&ticker, &metadata, &response).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].datetime, bson::DateTime::from_millis(1_704_205_800_000));
+        assert_eq!(items[0].source, "reuters.com");
+        assert_eq!(items[0].tickers, vec!["AAPL.US".to_string(), "AAPL.MX".to_string()]);
+        assert_eq!(items[0].sentiment, Some(-0.42));
+        assert_eq!((items[1].url.as_deref(), items[1].source.as_str(), items[1].sentiment), (None, "", None));
+        assert_eq!(items[1].metadata.metadata_collection_name, "equity_news_items");
+    }
+
+    #[test]
+    fn test_parse_sentiment() {
+        let ticker = event_ticker("AAPL", "US");
+        // rows come newest first and today's is still incomplete
+        let response: Value = serde_json::from_str(
+            r#"{"AAPL.US":[{"date":"2024-01-04","count":3,"normalized":0.2},
+                           {"date":"2024-01-03","count":12,"normalized":"0.5"},
+                           {"date":"2024-01-02","count":7,"normalized":-0.1},
+                           {"date":"2024-01-01","count":0,"normalized":null}]}"#,
+        )
+        .unwrap();
+
+        let (dates, sentiments, counts) = EodApi::parse_sentiment(&ticker, &response, "2024-01-04").unwrap();
+        assert_eq!(dates, vec!["2024-01-02".to_string(), "2024-01-03".to_string()]);
+        assert_eq!(sentiments, vec![-0.1, 0.5]);
+        assert_eq!(counts, vec![7, 12]);
+
+        let (dates, _, _) = EodApi::parse_sentiment(&ticker, &serde_json::json!({}), "2024-01-04").unwrap();
+        assert!(dates.is_empty());
+        assert!(EodApi::parse_sentiment(&ticker, &serde_json::json!({"AAPL.US": {}}), "2024-01-04").is_err());
+    }
+
+    #[test]
+    fn test_parse_currency() {
+        assert_eq!(EodApi::parse_currency(&Value::from("USD")), Some("USD".to_string()));
+        assert_eq!(EodApi::parse_currency(&Value::from("GBX")), Some("GBX".to_string()));
+        for body in [Value::from("NA"), Value::from(""), Value::Null, serde_json::json!({})] {
+            assert_eq!(EodApi::parse_currency(&body), None);
+        }
+    }
 }
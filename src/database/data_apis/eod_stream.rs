@@ -0,0 +1,476 @@
+use anyhow::{anyhow, Result};
+use futures::{SinkExt, StreamExt};
+use polars::prelude::*;
+use serde_json::{json, to_string, Value};
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+use crate::database::models::eod_models::{MongoTickerParams, OhlcvMetaData};
+use crate::database::utility_functions::utc_datetime_series;
+
+const MILLISECONDS_PER_MINUTE: i64 = 60_000;
+// a dropped connection is retried this many times, waiting twice as long after every failed attempt
+const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+const INITIAL_RECONNECT_BACKOFF: Duration = Duration::from_secs(1);
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// the websocket feeds EOD offers, one connection per feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EodFeed {
+    UsTrades,
+    UsQuotes,
+    Forex,
+    Crypto,
+}
+
+impl EodFeed {
+    pub fn url(&self, api_token: &str) -> String {
+        let path = match self {
+            EodFeed::UsTrades => "us",
+            EodFeed::UsQuotes => "us-quote",
+            EodFeed::Forex => "forex",
+            EodFeed::Crypto => "crypto",
+        };
+        format!("wss://ws.eodhistoricaldata.com/ws/{}?api_token={}", path, api_token)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tick {
+    pub symbol: String,
+    // milliseconds since the epoch in UTC
+    pub timestamp: i64,
+    pub price: f64,
+    pub size: f64,
+}
+
+// numbers arrive as json numbers on the us feeds and as strings on the crypto feed
+fn number(message: &Value, field: &str) -> Option<f64> {
+    match message.get(field)? {
+        Value::Number(number) => number.as_f64(),
+        Value::String(string) => string.parse().ok(),
+        _ => None,
+    }
+}
+
+/*
+    Parses a message of an EOD feed into a tick. Returns None for status messages such as
+    {"status_code":200,"message":"Authorized"}.
+    - trades carry the price in "p" and the size in "v" (us) or "q" (crypto)
+    - us and forex quotes carry the ask in "a"/"ap" and bid in "b"/"bp", their tick is the mid with no size
+*/
+pub fn parse_tick(feed: EodFeed, message: &str) -> Result<Option<Tick>> {
+    let message: Value = serde_json::from_str(message)?;
+    let symbol = match message.get("s").and_then(|symbol| symbol.as_str()) {
+        Some(symbol) => symbol.to_string(),
+        None => return Ok(None),
+    };
+    let timestamp = number(&message, "t")
+        .ok_or_else(|| anyhow!("parse_tick() message for {} has no timestamp", symbol))? as i64;
+    let (price, size) = match feed {
+        EodFeed::UsTrades => (number(&message, "p"), number(&message, "v")),
+        EodFeed::Crypto => (number(&message, "p"), number(&message, "q")),
+        EodFeed::UsQuotes => (
+            number(&message, "ap").zip(number(&message, "bp")).map(|(ask, bid)| (ask + bid) / 2.0),
+            Some(0.0),
+        ),
+        EodFeed::Forex => (
+            number(&message, "a").zip(number(&message, "b")).map(|(ask, bid)| (ask + bid) / 2.0),
+            Some(0.0),
+        ),
+    };
+    let price = price.ok_or_else(|| anyhow!("parse_tick() message for {} has no price", symbol))?;
+    Ok(Some(Tick {
+        symbol,
+        timestamp,
+        price,
+        size: size.unwrap_or(0.0),
+    }))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bar {
+    pub symbol: String,
+    // start of the minute the bar covers
+    pub timestamp: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+impl Bar {
+    fn new(tick: &Tick, timestamp: i64) -> Self {
+        Self {
+            symbol: tick.symbol.clone(),
+            timestamp,
+            open: tick.price,
+            high: tick.price,
+            low: tick.price,
+            close: tick.price,
+            volume: tick.size,
+        }
+    }
+
+    fn update(&mut self, tick: &Tick) {
+        self.high = self.high.max(tick.price);
+        self.low = self.low.min(tick.price);
+        self.close = tick.price;
+        self.volume += tick.size;
+    }
+}
+
+fn minute_of(timestamp: i64) -> i64 {
+    timestamp - timestamp.rem_euclid(MILLISECONDS_PER_MINUTE)
+}
+
+/*
+    Aggregates ticks into 1m bars per symbol. A bar is complete once the feed has moved past its
+    minute, judged by the tick timestamps rather than the local clock so replays aggregate the same
+    way as the live feed. Ticks from a minute the feed has already moved past are dropped, and so are
+    the bars still open when the feed ends, as they only cover part of their minute.
+*/
+#[derive(Debug, Default)]
+pub struct BarAggregator {
+    open_bars: HashMap<String, Bar>,
+    latest_timestamp: i64,
+}
+
+impl BarAggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // pushes a tick and returns the bars it completed
+    pub fn push(&mut self, tick: &Tick) -> Vec<Bar> {
+        self.latest_timestamp = self.latest_timestamp.max(tick.timestamp);
+        let completed = self.complete();
+        let minute = minute_of(tick.timestamp);
+        if minute < minute_of(self.latest_timestamp) {
+            log::debug!("BarAggregator dropping late tick for {} at {}", tick.symbol, tick.timestamp);
+            return completed;
+        }
+        match self.open_bars.get_mut(&tick.symbol) {
+            Some(bar) => bar.update(tick),
+            None => {
+                self.open_bars.insert(tick.symbol.clone(), Bar::new(tick, minute));
+            }
+        }
+        completed
+    }
+
+    // removes and returns the bars whose minute the feed has moved past
+    fn complete(&mut self) -> Vec<Bar> {
+        let current_minute = minute_of(self.latest_timestamp);
+        let completed_symbols = self
+            .open_bars
+            .iter()
+            .filter(|(_, bar)| bar.timestamp < current_minute)
+            .map(|(symbol, _)| symbol.clone())
+            .collect::<Vec<String>>();
+        let mut bars = completed_symbols
+            .iter()
+            .filter_map(|symbol| self.open_bars.remove(symbol))
+            .collect::<Vec<Bar>>();
+        bars.sort_by(|a, b| (a.timestamp, &a.symbol).cmp(&(b.timestamp, &b.symbol)));
+        bars
+    }
+
+    // the number of bars whose minute has not ended yet
+    pub fn open_bars(&self) -> usize {
+        self.open_bars.len()
+    }
+}
+
+/*
+    Subscribes to an EOD websocket feed and aggregates its ticks into 1m bars for the given tickers,
+    whose collections should be 1m intraday collections, e.g., equity_spot_1m.
+*/
+pub struct EodStream {
+    url: String,
+    feed: EodFeed,
+    tickers: Vec<MongoTickerParams>,
+    flush_interval: Duration,
+}
+
+impl EodStream {
+    pub fn new(api_token: &str, feed: EodFeed, tickers: Vec<MongoTickerParams>) -> Self {
+        Self::with_url(feed.url(api_token), feed, tickers)
+    }
+
+    // connects to any server speaking the EOD protocol, e.g., a local replay of recorded messages
+    pub fn with_url(url: impl Into<String>, feed: EodFeed, tickers: Vec<MongoTickerParams>) -> Self {
+        Self {
+            url: url.into(),
+            feed,
+            tickers,
+            flush_interval: Duration::from_secs(5),
+        }
+    }
+
+    pub fn flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    pub fn tickers(&self) -> &[MongoTickerParams] {
+        &self.tickers
+    }
+
+    // completed bars as one df per ticker, shaped like the dfs batch_get_series_all() returns
+    fn bars_to_dataframes(&self, bars: Vec<Bar>) -> Result<Vec<DataFrame>> {
+        let mut bars_by_symbol: HashMap<String, Vec<Bar>> = HashMap::new();
+        for bar in bars.into_iter() {
+            bars_by_symbol.entry(bar.symbol.clone()).or_default().push(bar);
+        }
+
+        let mut dfs = Vec::new();
+        for ticker in self.tickers.iter() {
+            let bars = match bars_by_symbol.remove(&ticker.ticker) {
+                Some(bars) => bars,
+                None => continue,
+            };
+            let metadata = to_string(&OhlcvMetaData {
+                metadata_collection_name: ticker.series_collection_name.clone(),
+                ticker: ticker.ticker.clone(),
+                source: ticker.source.clone(),
+                exchange: ticker.exchange.clone(),
+                currency: None,
+            })?;
+            let timestamps = Int64Chunked::from_vec("datetime", bars.iter().map(|bar| bar.timestamp).collect());
+            let df = DataFrame::new(vec![
+                utc_datetime_series("datetime", timestamps),
+                Series::new("open", bars.iter().map(|bar| bar.open).collect::<Vec<f64>>()),
+                Series::new("high", bars.iter().map(|bar| bar.high).collect::<Vec<f64>>()),
+                Series::new("low", bars.iter().map(|bar| bar.low).collect::<Vec<f64>>()),
+                Series::new("close", bars.iter().map(|bar| bar.close).collect::<Vec<f64>>()),
+                Series::new("volume", bars.iter().map(|bar| bar.volume).collect::<Vec<f64>>()),
+                Series::new("metadata", vec![metadata; bars.len()]),
+            ])?;
+            dfs.push(df);
+        }
+        Ok(dfs)
+    }
+
+    // connects to the feed and subscribes to the tickers
+    async fn connect(&self) -> Result<Socket> {
+        let (mut socket, _) = connect_async(self.url.as_str()).await?;
+        let symbols = self
+            .tickers
+            .iter()
+            .map(|ticker| ticker.ticker.as_str())
+            .collect::<Vec<&str>>()
+            .join(",");
+        log::info!("EodStream subscribing to {:?} feed for {}", self.feed, symbols);
+        let subscribe = json!({ "action": "subscribe", "symbols": symbols });
+        socket.send(Message::Text(subscribe.to_string())).await?;
+        Ok(socket)
+    }
+
+    // connects again after the connection failed, with exponential backoff between attempts
+    async fn reconnect(&self) -> Result<Socket> {
+        let mut backoff = INITIAL_RECONNECT_BACKOFF;
+        for attempt in 1..=MAX_RECONNECT_ATTEMPTS {
+            tokio::time::sleep(backoff).await;
+            match self.connect().await {
+                Ok(socket) => return Ok(socket),
+                Err(e) => log::warn!("EodStream reconnect attempt {} of {} failed: {:#}", attempt, MAX_RECONNECT_ATTEMPTS, e),
+            }
+            backoff *= 2;
+        }
+        Err(anyhow!("EodStream could not reconnect to the {:?} feed after {} attempts", self.feed, MAX_RECONNECT_ATTEMPTS))
+    }
+
+    /*
+        Reads the feed until the server closes it or ctrl-c is received, handing completed bars to
+        on_bars every flush interval. A failed connection is reconnected with backoff, and is an error
+        once it can not be. Bars still open when the feed ends only cover part of their minute, so they
+        are dropped rather than stored as if they were final.
+    */
+    pub async fn aggregate<F, Fut>(&self, mut on_bars: F) -> Result<()>
+    where
+        F: FnMut(Vec<DataFrame>) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let mut socket = self.connect().await?;
+        let mut aggregator = BarAggregator::new();
+        let mut completed = Vec::new();
+        let mut flush = tokio::time::interval(self.flush_interval);
+        let shutdown = tokio::signal::ctrl_c();
+        tokio::pin!(shutdown);
+        loop {
+            tokio::select! {
+                message = socket.next() => match message {
+                    Some(Ok(Message::Text(text))) => match parse_tick(self.feed, &text) {
+                        Ok(Some(tick)) => completed.extend(aggregator.push(&tick)),
+                        Ok(None) => log::info!("EodStream status message: {}", text),
+                        Err(e) => log::warn!("EodStream skipping message {}: {:#}", text, e),
+                    },
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => (),
+                    Some(Err(e)) => {
+                        log::error!("EodStream connection failed, reconnecting: {}", e);
+                        socket = self.reconnect().await?;
+                    }
+                },
+                _ = flush.tick() => {
+                    if !completed.is_empty() {
+                        on_bars(self.bars_to_dataframes(std::mem::take(&mut completed))?).await?;
+                    }
+                }
+                _ = &mut shutdown => {
+                    log::info!("EodStream received shutdown signal");
+                    break;
+                }
+            }
+        }
+
+        if aggregator.open_bars() > 0 {
+            log::info!("EodStream dropping {} bars of an unfinished minute", aggregator.open_bars());
+        }
+        if !completed.is_empty() {
+            on_bars(self.bars_to_dataframes(completed)?).await?;
+        }
+        Ok(())
+    }
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use mongodb::bson;
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    // messages recorded from the us trades feed
+    pub(crate) const RECORDED_US_TRADES: [&str; 8] = [
+        r#"{"status_code":200,"message":"Authorized"}"#,
+        r#"{"s":"AAPL","p":185.10,"c":[12,37],"v":100,"dp":false,"t":1704205800000}"#,
+        r#"{"s":"MSFT","p":370.00,"c":[14],"v":10,"dp":false,"t":1704205801000}"#,
+        r#"{"s":"AAPL","p":185.50,"c":[12],"v":200,"dp":false,"t":1704205830000}"#,
+        r#"{"s":"AAPL","p":184.90,"c":[12],"v":50,"dp":false,"t":1704205859999}"#,
+        r#"{"s":"AAPL","p":185.00,"c":[12],"v":25,"dp":false,"t":1704205860000}"#,
+        r#"{"s":"MSFT","p":371.00,"c":[14],"v":5,"dp":true,"t":1704205861000}"#,
+        r#"not json"#,
+    ];
+
+    /*
+        A stand-in for the EOD websocket that waits for the subscription and replays the messages of one
+        connection after another. Every connection but the last is dropped without a close frame, like
+        a connection that failed, the last one is closed.
+    */
+    pub(crate) async fn replay_server(connections: &'static [&'static [&'static str]]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for (i, messages) in connections.iter().enumerate() {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+                let subscription = socket.next().await.unwrap().unwrap();
+                assert!(subscription.to_text().unwrap().contains("subscribe"));
+                for message in messages.iter() {
+                    socket.send(Message::Text(message.to_string())).await.unwrap();
+                }
+                if i + 1 == connections.len() {
+                    socket.close(None).await.unwrap();
+                }
+            }
+        });
+        format!("ws://{}", addr)
+    }
+
+    pub(crate) fn ticker(ticker: &str) -> MongoTickerParams {
+        MongoTickerParams {
+            ticker: ticker.to_string(),
+            exchange: "US".to_string(),
+            series_collection_name: "equity_spot_1m".to_string(),
+            source: "eod".to_string(),
+            from: bson::DateTime::from_millis(0),
+            to: bson::DateTime::now(),
+        }
+    }
+
+    async fn aggregate_all(stream: &EodStream) -> Vec<DataFrame> {
+        let flushed = Arc::new(Mutex::new(Vec::new()));
+        stream
+            .aggregate(|dfs| {
+                let flushed = flushed.clone();
+                async move {
+                    flushed.lock().unwrap().extend(dfs);
+                    Ok(())
+                }
+            })
+            .await
+            .unwrap();
+        let flushed = flushed.lock().unwrap();
+        flushed.clone()
+    }
+
+    fn bars_of(flushed: &[DataFrame], symbol: &str) -> DataFrame {
+        flushed
+            .iter()
+            .filter(|df| df.column("metadata").unwrap().str().unwrap().get(0).unwrap().contains(symbol))
+            .cloned()
+            .reduce(|a, b| a.vstack(&b).unwrap())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_eod_stream_replay() {
+        let url = replay_server(&[&RECORDED_US_TRADES]).await;
+        let stream = EodStream::with_url(url, EodFeed::UsTrades, vec![ticker("AAPL"), ticker("MSFT")]);
+
+        let flushed = aggregate_all(&stream).await;
+
+        // the bars of the minute the feed ended in are unfinished and dropped
+        let aapl = bars_of(&flushed, "AAPL");
+        assert_eq!(aapl.height(), 1);
+        let column = |name: &str| aapl.column(name).unwrap().f64().unwrap().into_no_null_iter().collect::<Vec<f64>>();
+        assert_eq!(column("open"), vec![185.10]);
+        assert_eq!(column("high"), vec![185.50]);
+        assert_eq!(column("low"), vec![184.90]);
+        assert_eq!(column("close"), vec![184.90]);
+        assert_eq!(column("volume"), vec![350.0]);
+        assert_eq!(
+            aapl.column("datetime").unwrap().cast(&DataType::Int64).unwrap().i64().unwrap().get(0),
+            Some(1_704_205_800_000)
+        );
+        let rows: usize = flushed.iter().map(|df| df.height()).sum();
+        assert_eq!(rows, 2);
+    }
+
+    #[tokio::test]
+    async fn test_eod_stream_reconnect() {
+        // the first connection fails halfway through a minute, the bar carries on after reconnecting
+        const FIRST: [&str; 2] = [
+            r#"{"s":"AAPL","p":185.10,"v":100,"t":1704205800000}"#,
+            r#"{"s":"AAPL","p":185.50,"v":200,"t":1704205830000}"#,
+        ];
+        const SECOND: [&str; 2] = [
+            r#"{"s":"AAPL","p":184.90,"v":50,"t":1704205859999}"#,
+            r#"{"s":"AAPL","p":185.00,"v":25,"t":1704205860000}"#,
+        ];
+        let url = replay_server(&[&FIRST, &SECOND]).await;
+        let stream = EodStream::with_url(url, EodFeed::UsTrades, vec![ticker("AAPL")]);
+        let aapl = bars_of(&aggregate_all(&stream).await, "AAPL");
+        assert_eq!(aapl.height(), 1);
+        assert_eq!(aapl.column("volume").unwrap().f64().unwrap().get(0), Some(350.0));
+        assert_eq!(aapl.column("close").unwrap().f64().unwrap().get(0), Some(184.90));
+    }
+
+    #[test]
+    fn test_parse_tick() {
+        let crypto = parse_tick(EodFeed::Crypto, r#"{"s":"ETH-USD","p":"2330.42","q":"0.0306","dc":"-0.07","t":1726073278000}"#);
+        assert_eq!(crypto.unwrap().unwrap().size, 0.0306);
+        let forex = parse_tick(EodFeed::Forex, r#"{"s":"EURUSD","a":1.1002,"b":1.1,"dc":"0.1","t":1726073278000}"#);
+        assert!((forex.unwrap().unwrap().price - 1.1001).abs() < 1e-9);
+        assert_eq!(parse_tick(EodFeed::UsTrades, r#"{"status_code":200,"message":"Authorized"}"#).unwrap(), None);
+        assert!(parse_tick(EodFeed::UsTrades, r#"{"s":"AAPL","t":1726073278000}"#).is_err());
+    }
+}
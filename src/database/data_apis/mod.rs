@@ -1,2 +1,28 @@
+use anyhow::Result;
+use futures::future::BoxFuture;
+
+use crate::database::models::eod_models::MongoTickerParams;
+use crate::database::models::macro_models::MacroValue;
+
 pub mod eod;
-pub use eod::EodApi;
\ No newline at end of file
+pub mod eod_stream;
+pub mod synthetic;
+pub use eod::EodApi;
+pub use eod_stream::EodStream;
+pub use synthetic::SyntheticApi;
+
+/*
+    A datasource of macro indicators. MongoDbClient::ingest_macro() fetches every ticker from the source
+    registered under its source name: EOD is registered as "eod" when its token is configured, other
+    sources are plugged in with MongoDbClientBuilder::macro_source().
+*/
+pub trait MacroSource: Send + Sync {
+    // every value of an indicator the source has, the ticker is the indicator and the exchange the country
+    fn get_macro_indicator<'a>(&'a self, ticker: &'a MongoTickerParams) -> BoxFuture<'a, Result<Vec<MacroValue>>>;
+}
+
+impl MacroSource for EodApi {
+    fn get_macro_indicator<'a>(&'a self, ticker: &'a MongoTickerParams) -> BoxFuture<'a, Result<Vec<MacroValue>>> {
+        Box::pin(EodApi::get_macro_indicator(self, ticker))
+    }
+}
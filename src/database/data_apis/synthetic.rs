@@ -0,0 +1,401 @@
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use polars::prelude::*;
+use serde_json::to_string;
+
+use crate::database::models::eod_models::{MongoTickerParams, OhlcvMetaData};
+use crate::database::models::schema_models::{CollectionKind, CollectionType};
+use crate::database::models::synthetic_models::{PriceModel, SyntheticConfig};
+use crate::database::utility_functions::{collection_interval_millis, get_current_datetime_bson, parse_datetime, utc_datetime_series};
+
+const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+const MILLIS_PER_DAY: i64 = 86_400_000;
+// splits as (new shares, old shares)
+const SPLITS: [(f64, f64); 3] = [(2.0, 1.0), (3.0, 1.0), (1.0, 2.0)];
+
+// streams keep the random numbers of the path, the bars and each defect apart, so changing one changes nothing else
+const PATH_STREAM: u64 = 1;
+const BAR_STREAM: u64 = 2;
+const INTRADAY_STREAM: u64 = 3;
+const SPLIT_STREAM: u64 = 4;
+const GAP_STREAM: u64 = 5;
+const BAD_TICK_STREAM: u64 = 6;
+
+// splitmix64, small and fully determined by its seed on every platform
+struct Rng(u64);
+
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl Rng {
+    // the generator of a stream, for the index-th bar or day if it is one drawn per bar
+    fn new(seed: u64, stream: u64, index: u64) -> Self {
+        Rng(mix(seed ^ mix(stream.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ mix(index))))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        mix(self.0)
+    }
+
+    // uniform in (0, 1)
+    fn uniform(&mut self) -> f64 {
+        ((self.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    }
+
+    fn normal(&mut self) -> f64 {
+        let (u1, u2) = (self.uniform(), self.uniform());
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+}
+
+// fnv-1a, stable across platforms and versions unlike std's hasher
+fn stable_hash(value: &str) -> u64 {
+    value.bytes().fold(0xCBF2_9CE4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3))
+}
+
+// a trading day of the underlying path, prices per share before any split
+struct Day {
+    date: NaiveDate,
+    index: u64,
+    open: f64,
+    close: f64,
+    // daily volatility the day was drawn with
+    volatility: f64,
+    // factor quoted prices are the path times, from the splits up to and including the day
+    price_factor: f64,
+}
+
+struct Bar {
+    datetime: i64,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    adjusted_close: Option<f64>,
+    volume: f64,
+}
+
+/*
+    A datasource of synthetic bars for any ticker in a daily or intraday collection, for running ingest(),
+    run() and the alpha code without a vendor. Daily closes follow the PriceModel of the ticker on weekdays
+    from the origin of the config, and the intraday bars of a day bridge its open to its close over the
+    whole UTC day, so every interval of a ticker agrees on its daily closes. A bar is only returned once it
+    has ended and always comes out the same, so refreshes continue series like a vendor would. Like EOD,
+    close is as traded and adjusted_close, daily bars only, is adjusted for the splits up to the end of the
+    request, which moves when a split is generated after bars were stored.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct SyntheticApi {
+    config: SyntheticConfig,
+}
+
+impl SyntheticApi {
+    pub fn new(config: SyntheticConfig) -> Self {
+        log::info!("Established synthetic datasource with seed {}", config.seed);
+        Self { config }
+    }
+
+    fn ticker_seed(&self, ticker: &MongoTickerParams) -> u64 {
+        mix(self.config.seed ^ stable_hash(&format!("{}.{}", ticker.ticker, ticker.exchange)))
+    }
+
+    // the trading days from the origin to last_date
+    fn daily_path(&self, ticker: &MongoTickerParams, seed: u64, last_date: NaiveDate) -> Result<Vec<Day>> {
+        let model = self.config.model_for(&ticker.ticker, &ticker.exchange);
+        if let PriceModel::RegimeSwitching { regimes, .. } = model {
+            if regimes.is_empty() {
+                return Err(anyhow!("SyntheticApi regime switching model of {}.{} has no regimes", ticker.ticker, ticker.exchange));
+            }
+        }
+        let dt = 1.0 / TRADING_DAYS_PER_YEAR;
+        let mut path_rng = Rng::new(seed, PATH_STREAM, 0);
+        let mut date = parse_datetime(&self.config.origin)?.to_chrono().date_naive();
+        let (mut index, mut regime, mut close, mut price_factor) = (0, 0, self.config.initial_price, 1.0);
+        let mut days = Vec::new();
+        while date <= last_date {
+            if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+                date += Duration::days(1);
+                continue;
+            }
+            let (drift, volatility, jump) = match model {
+                PriceModel::Gbm { drift, volatility } => (*drift, *volatility, 0.0),
+                PriceModel::JumpDiffusion { drift, volatility, jump_intensity, jump_mean, jump_volatility } => {
+                    let arrives = path_rng.uniform() < jump_intensity * dt;
+                    let jump = jump_mean + jump_volatility * path_rng.normal();
+                    (*drift, *volatility, if arrives { jump } else { 0.0 })
+                }
+                PriceModel::RegimeSwitching { regimes, switch_probability } => {
+                    if path_rng.uniform() < *switch_probability && regimes.len() > 1 {
+                        regime = (regime + 1 + path_rng.below(regimes.len() - 1)) % regimes.len();
+                    }
+                    (regimes[regime].drift, regimes[regime].volatility, 0.0)
+                }
+            };
+            let open = close;
+            let log_return = (drift - 0.5 * volatility * volatility) * dt + volatility * dt.sqrt() * path_rng.normal() + jump;
+            close = open * log_return.exp();
+
+            let mut split_rng = Rng::new(seed, SPLIT_STREAM, index);
+            if split_rng.uniform() < self.config.defects.split_probability {
+                let (new_shares, old_shares) = SPLITS[split_rng.below(SPLITS.len())];
+                price_factor *= old_shares / new_shares;
+            }
+            days.push(Day {
+                date,
+                index,
+                open,
+                close,
+                volatility: volatility * dt.sqrt(),
+                price_factor,
+            });
+            index += 1;
+            date += Duration::days(1);
+        }
+        Ok(days)
+    }
+
+    // the bar of a key is in an outage if one started at most max_gap_bars bars before it and is still going
+    fn in_gap(&self, seed: u64, key: u64) -> bool {
+        let defects = &self.config.defects;
+        if defects.gap_probability <= 0.0 {
+            return false;
+        }
+        let max_gap_bars = defects.max_gap_bars.max(1);
+        (0..(max_gap_bars as u64).min(key + 1)).any(|back| {
+            let mut rng = Rng::new(seed, GAP_STREAM, key - back);
+            rng.uniform() < defects.gap_probability && (1 + rng.below(max_gap_bars)) as u64 > back
+        })
+    }
+
+    fn spoil(&self, seed: u64, key: u64, bar: &mut Bar) {
+        let mut rng = Rng::new(seed, BAD_TICK_STREAM, key);
+        if rng.uniform() >= self.config.defects.bad_tick_probability {
+            return;
+        }
+        match rng.below(4) {
+            0 => bar.close *= 10.0,
+            1 => bar.close /= 10.0,
+            2 => bar.close = 0.0,
+            _ => std::mem::swap(&mut bar.high, &mut bar.low),
+        }
+    }
+
+    // bars of a day quoted with its split factor, the bar of a daily collection or those of an intraday one
+    fn day_bars(&self, seed: u64, day: &Day, interval: i64, final_factor: f64) -> Vec<(u64, Bar)> {
+        let midnight = day.date.and_hms_opt(0, 0, 0).expect("midnight is a valid time").and_utc().timestamp_millis();
+        let factor = day.price_factor;
+        if interval == MILLIS_PER_DAY {
+            let mut rng = Rng::new(seed, BAR_STREAM, day.index);
+            let high = day.open.max(day.close) * (0.5 * day.volatility * rng.normal().abs()).exp();
+            let low = day.open.min(day.close) * (-0.5 * day.volatility * rng.normal().abs()).exp();
+            let volume = self.config.base_volume * (0.5 * rng.normal() - 0.125).exp() / factor;
+            let bar = Bar {
+                datetime: midnight,
+                open: day.open * factor,
+                high: high * factor,
+                low: low * factor,
+                close: day.close * factor,
+                adjusted_close: Some(day.close * final_factor),
+                volume: volume.round(),
+            };
+            return vec![(day.index, bar)];
+        }
+
+        // a brownian bridge in log price from the open to the close of the day
+        let bars_per_day = (MILLIS_PER_DAY / interval) as usize;
+        let step_volatility = day.volatility / (bars_per_day as f64).sqrt();
+        let mut rng = Rng::new(seed, INTRADAY_STREAM, day.index);
+        let mut walk = vec![0.0; bars_per_day + 1];
+        for step in 1..=bars_per_day {
+            walk[step] = walk[step - 1] + step_volatility * rng.normal();
+        }
+        let (log_open, log_close) = (day.open.ln(), day.close.ln());
+        let prices = walk
+            .iter()
+            .enumerate()
+            .map(|(step, position)| {
+                let fraction = step as f64 / bars_per_day as f64;
+                (log_open + fraction * (log_close - log_open) + position - fraction * walk[bars_per_day]).exp()
+            })
+            .collect::<Vec<f64>>();
+        prices
+            .windows(2)
+            .enumerate()
+            .map(|(step, window)| {
+                let high = window[0].max(window[1]) * (0.5 * step_volatility * rng.normal().abs()).exp();
+                let low = window[0].min(window[1]) * (-0.5 * step_volatility * rng.normal().abs()).exp();
+                let volume = self.config.base_volume / bars_per_day as f64 * (0.5 * rng.normal() - 0.125).exp() / factor;
+                let bar = Bar {
+                    datetime: midnight + step as i64 * interval,
+                    open: window[0] * factor,
+                    high: high * factor,
+                    low: low * factor,
+                    close: window[1] * factor,
+                    adjusted_close: None,
+                    volume: volume.round(),
+                };
+                (day.index * bars_per_day as u64 + step as u64, bar)
+            })
+            .collect()
+    }
+
+    /*
+        The bars of a ticker from its from that have ended by until, as a df of datetime, open, high, low, close,
+        adjusted_close for daily collections and volume, with the defects of the config injected. Collections
+        other than daily or intraday bars, e.g., options or ticks, are an error.
+    */
+    pub fn generate(&self, ticker: &MongoTickerParams, until: bson::DateTime) -> Result<DataFrame> {
+        let collection_name = &ticker.series_collection_name;
+        let interval = collection_interval_millis(collection_name);
+        if CollectionType::parse(collection_name).kind != CollectionKind::Bars
+            || interval <= 0
+            || interval > MILLIS_PER_DAY
+            || MILLIS_PER_DAY % interval != 0
+        {
+            return Err(anyhow!("SyntheticApi can not generate bars for collection {}", collection_name));
+        }
+
+        let until = until.timestamp_millis();
+        let mut from = ticker.from.timestamp_millis();
+        if interval < MILLIS_PER_DAY {
+            from = from.max(until - self.config.intraday_history_days * MILLIS_PER_DAY);
+        }
+        let last_date = match bson::DateTime::from_millis(until - interval).to_chrono().date_naive() {
+            last_date if until - interval >= from => last_date,
+            _ => return self.bars_df(Vec::new(), interval),
+        };
+
+        let seed = self.ticker_seed(ticker);
+        let days = self.daily_path(ticker, seed, last_date)?;
+        let final_factor = days.last().map_or(1.0, |day| day.price_factor);
+        let first_date = bson::DateTime::from_millis(from).to_chrono().date_naive();
+        let mut bars = Vec::new();
+        for day in days.iter().filter(|day| day.date >= first_date) {
+            for (key, mut bar) in self.day_bars(seed, day, interval, final_factor) {
+                if bar.datetime < from || bar.datetime + interval > until || self.in_gap(seed, key) {
+                    continue;
+                }
+                self.spoil(seed, key, &mut bar);
+                bars.push(bar);
+            }
+        }
+        self.bars_df(bars, interval)
+    }
+
+    fn bars_df(&self, bars: Vec<Bar>, interval: i64) -> Result<DataFrame> {
+        let column = |field: fn(&Bar) -> f64| bars.iter().map(field).collect::<Vec<f64>>();
+        let datetimes = Int64Chunked::from_vec("datetime", bars.iter().map(|bar| bar.datetime).collect());
+        let mut columns = vec![
+            utc_datetime_series("datetime", datetimes),
+            Series::new("open", column(|bar| bar.open)),
+            Series::new("high", column(|bar| bar.high)),
+            Series::new("low", column(|bar| bar.low)),
+            Series::new("close", column(|bar| bar.close)),
+        ];
+        if interval == MILLIS_PER_DAY {
+            columns.push(Series::new("adjusted_close", column(|bar| bar.adjusted_close.unwrap_or(bar.close))));
+        }
+        columns.push(Series::new("volume", column(|bar| bar.volume)));
+        Ok(DataFrame::new(columns)?)
+    }
+
+    // the counterpart of EodApi::batch_get_series_all(), with the error of each ticker it can not generate
+    pub async fn batch_get_series_all(&self, tickers: Vec<MongoTickerParams>) -> Result<Vec<(MongoTickerParams, Result<DataFrame>)>> {
+        let until = get_current_datetime_bson();
+        let mut dfs = Vec::new();
+        for ticker in tickers.into_iter() {
+            log::info!("batch_get_series_all() generating {} in collection {}", ticker.ticker, ticker.series_collection_name);
+            let df = self.generate(&ticker, until).and_then(|mut df| {
+                let metadata = OhlcvMetaData {
+                    metadata_collection_name: ticker.series_collection_name.clone(),
+                    ticker: ticker.ticker.clone(),
+                    source: "synthetic".to_string(),
+                    exchange: ticker.exchange.clone(),
+                    currency: None,
+                };
+                df.with_column(Series::new("metadata", vec![to_string(&metadata)?; df.height()]))?;
+                Ok(df)
+            });
+            dfs.push((ticker, df));
+        }
+        Ok(dfs)
+    }
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::synthetic_models::{Defects, Regime};
+
+    fn ticker(collection: &str, from: &str) -> MongoTickerParams {
+        MongoTickerParams {
+            ticker: "SYN".to_string(),
+            exchange: "US".to_string(),
+            series_collection_name: collection.to_string(),
+            source: "synthetic".to_string(),
+            from: parse_datetime(from).unwrap(),
+            to: parse_datetime("2024-01-01").unwrap(),
+        }
+    }
+
+    fn closes(df: &DataFrame, column: &str) -> Vec<f64> {
+        df.column(column).unwrap().f64().unwrap().into_no_null_iter().collect()
+    }
+
+    #[test]
+    fn test_synthetic_series() {
+        let config = SyntheticConfig::new(7).ticker_model(
+            "SYN",
+            "US",
+            PriceModel::RegimeSwitching {
+                regimes: vec![Regime { drift: 0.1, volatility: 0.1 }, Regime { drift: -0.2, volatility: 0.5 }],
+                switch_probability: 0.02,
+            },
+        );
+        let api = SyntheticApi::new(config.clone());
+        let until = parse_datetime("2023-07-01").unwrap();
+
+        // the same settings give the same bars, and a refresh continues the series it refreshes
+        let full = api.generate(&ticker("equity_spot_1d", "2023-01-01"), until).unwrap();
+        let again = SyntheticApi::new(config.clone()).generate(&ticker("equity_spot_1d", "2023-01-01"), until).unwrap();
+        assert_eq!(closes(&again, "close"), closes(&full, "close"));
+        let refreshed = api.generate(&ticker("equity_spot_1d", "2023-03-01"), until).unwrap();
+        assert_eq!(closes(&refreshed, "close"), closes(&full, "close")[full.height() - refreshed.height()..].to_vec());
+        assert_eq!(full.height(), 130);
+
+        // hourly bars bridge the daily bars, the last hour closes where the day does
+        let hourly = api.generate(&ticker("equity_spot_1h", "2023-06-30"), until).unwrap();
+        assert_eq!(hourly.height(), 24);
+        assert!((closes(&hourly, "close")[23] - closes(&full, "close")[full.height() - 1]).abs() < 1e-9);
+        assert!(api.generate(&ticker("equity_options_1d", "2023-01-01"), until).is_err());
+
+        // defects drop and spoil bars and split the shares, but leave the path and so adjusted closes alone
+        let defects = Defects {
+            gap_probability: 0.05,
+            max_gap_bars: 3,
+            split_probability: 0.001,
+            bad_tick_probability: 0.05,
+        };
+        let spoilt = SyntheticApi::new(config.defects(defects)).generate(&ticker("equity_spot_1d", "2023-01-01"), until).unwrap();
+        assert!(spoilt.height() < full.height());
+        let datetimes = |df: &DataFrame| df.column("datetime").unwrap().cast(&DataType::Int64).unwrap().i64().unwrap().into_no_null_iter().collect::<Vec<i64>>();
+        let full_datetimes = datetimes(&full);
+        let (full_adjusted, spoilt_adjusted) = (closes(&full, "adjusted_close"), closes(&spoilt, "adjusted_close"));
+        let ratios = datetimes(&spoilt)
+            .iter()
+            .zip(spoilt_adjusted)
+            .map(|(datetime, adjusted)| adjusted / full_adjusted[full_datetimes.iter().position(|full_datetime| full_datetime == datetime).unwrap()])
+            .collect::<Vec<f64>>();
+        assert!(ratios.iter().all(|ratio| (ratio / ratios[0] - 1.0).abs() < 1e-9));
+        assert!(closes(&spoilt, "close").iter().zip(closes(&spoilt, "adjusted_close")).any(|(close, adjusted)| (close / adjusted / ratios[0] - 1.0).abs() > 1e-9));
+    }
+}
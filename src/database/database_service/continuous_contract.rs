@@ -0,0 +1,259 @@
+use anyhow::{anyhow, Result};
+use polars::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::database::models::futures_models::{AdjustmentMethod, FuturesContractSpec, RollRule};
+use crate::database::utility_functions::utc_datetime_series;
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+// the bars of a contract as read from its series, keyed by datetime in milliseconds
+struct ContractBars {
+    spec: FuturesContractSpec,
+    rows: HashMap<i64, usize>,
+    open: Vec<Option<f64>>,
+    high: Vec<Option<f64>>,
+    low: Vec<Option<f64>>,
+    close: Vec<Option<f64>>,
+    volume: Vec<Option<f64>>,
+    open_interest: Vec<Option<f64>>,
+}
+
+impl ContractBars {
+    fn new(spec: FuturesContractSpec, df: &DataFrame) -> Result<Self> {
+        let float_column = |name: &str| -> Result<Vec<Option<f64>>> {
+            match df.column(name) {
+                Ok(series) => Ok(series.cast(&DataType::Float64)?.f64()?.into_iter().collect()),
+                // open interest is only in collections with a futures schema
+                Err(_) if name == "open_interest" => Ok(vec![None; df.height()]),
+                Err(error) => Err(anyhow!("ContinuousContractBuilder contract {} has no {} column: {}", spec.ticker, name, error)),
+            }
+        };
+        let rows = df
+            .column("datetime")?
+            .cast(&DataType::Int64)?
+            .i64()?
+            .into_iter()
+            .enumerate()
+            .filter_map(|(row, datetime)| datetime.map(|datetime| (datetime, row)))
+            .collect();
+        Ok(Self {
+            rows,
+            open: float_column("open")?,
+            high: float_column("high")?,
+            low: float_column("low")?,
+            close: float_column("close")?,
+            volume: float_column("volume")?,
+            open_interest: float_column("open_interest")?,
+            spec,
+        })
+    }
+
+    fn close_at(&self, datetime: i64) -> Option<f64> {
+        self.rows.get(&datetime).and_then(|row| self.close[*row])
+    }
+
+    // the value the switch roll rules compare, missing bars count as nothing traded
+    fn activity_at(&self, datetime: i64, rule: RollRule) -> f64 {
+        let values = match rule {
+            RollRule::OpenInterestSwitch => &self.open_interest,
+            RollRule::VolumeSwitch | RollRule::DaysBeforeExpiry(_) => &self.volume,
+        };
+        self.rows.get(&datetime).and_then(|row| values[*row]).unwrap_or(0.0)
+    }
+}
+
+/*
+    Stitches the series of a root's contracts into one continuous series.
+    - contracts are held in expiry order and the held contract never moves back
+    - every rule rolls by the held contract's roll deadline (first notice or last trade), DaysBeforeExpiry(n)
+      rolls n calendar days before it and the switch rules roll earlier if the next contract takes over.
+      A switch seen in a day's bars takes effect the next day, so no bar is picked with its own data
+    - at each roll the gap between the two contracts' closes on the last day of the old contract is
+      removed from every earlier bar by the AdjustmentMethod, so the latest prices are the traded ones
+    Returns datetime, open, high, low, close, volume, open_interest and the contract ticker held on each day.
+*/
+pub struct ContinuousContractBuilder {
+    roll_rule: RollRule,
+    adjustment: AdjustmentMethod,
+}
+
+impl ContinuousContractBuilder {
+    pub fn new(roll_rule: RollRule, adjustment: AdjustmentMethod) -> Self {
+        Self { roll_rule, adjustment }
+    }
+
+    pub fn build(&self, contracts: Vec<(FuturesContractSpec, DataFrame)>) -> Result<DataFrame> {
+        let mut contracts = contracts
+            .into_iter()
+            .map(|(spec, df)| ContractBars::new(spec, &df))
+            .collect::<Result<Vec<ContractBars>>>()?;
+        contracts.sort_by_key(|contract| contract.spec.expiry);
+        let datetimes = contracts
+            .iter()
+            .flat_map(|contract| contract.rows.keys().copied())
+            .collect::<BTreeSet<i64>>();
+
+        let days_before = match self.roll_rule {
+            RollRule::DaysBeforeExpiry(days) => days,
+            RollRule::VolumeSwitch | RollRule::OpenInterestSwitch => 0,
+        };
+        let mut held = 0;
+        let mut switch_pending = false;
+        // (datetime, contract index) of every bar in the continuous series
+        let mut picked: Vec<(i64, usize)> = Vec::new();
+        for datetime in datetimes.into_iter() {
+            if switch_pending && held + 1 < contracts.len() {
+                held += 1;
+            }
+            switch_pending = false;
+            while held + 1 < contracts.len()
+                && datetime >= contracts[held].spec.roll_deadline().timestamp_millis() - days_before * MILLIS_PER_DAY
+            {
+                held += 1;
+            }
+
+            if contracts[held].rows.contains_key(&datetime) {
+                picked.push((datetime, held));
+            }
+            if let (RollRule::VolumeSwitch | RollRule::OpenInterestSwitch, Some(next)) = (self.roll_rule, contracts.get(held + 1)) {
+                switch_pending = next.activity_at(datetime, self.roll_rule) > contracts[held].activity_at(datetime, self.roll_rule);
+            }
+        }
+
+        // walk back from the latest bar, carrying the adjustment of every roll after the current bar
+        let mut offset = 0.0;
+        let mut factor = 1.0;
+        let mut adjustments = vec![(0.0, 1.0); picked.len()];
+        for row in (0..picked.len()).rev() {
+            adjustments[row] = (offset, factor);
+            if row == 0 || picked[row - 1].1 == picked[row].1 {
+                continue;
+            }
+            let (last_datetime, old) = picked[row - 1];
+            let new = picked[row].1;
+            match (contracts[old].close_at(last_datetime), contracts[new].close_at(last_datetime)) {
+                (Some(old_close), Some(new_close)) if old_close != 0.0 => {
+                    offset += new_close - old_close;
+                    factor *= new_close / old_close;
+                }
+                _ => log::warn!(
+                    "ContinuousContractBuilder roll from {} to {} is not adjusted as both have no close on the same day",
+                    contracts[old].spec.ticker,
+                    contracts[new].spec.ticker
+                ),
+            }
+        }
+
+        let adjusted = |values: fn(&ContractBars) -> &Vec<Option<f64>>| {
+            picked
+                .iter()
+                .zip(adjustments.iter())
+                .map(|((datetime, contract), (offset, factor))| {
+                    let contract = &contracts[*contract];
+                    let value = values(contract)[contract.rows[datetime]]?;
+                    Some(match self.adjustment {
+                        AdjustmentMethod::Difference => value + offset,
+                        AdjustmentMethod::Ratio => value * factor,
+                        AdjustmentMethod::None => value,
+                    })
+                })
+                .collect::<Vec<Option<f64>>>()
+        };
+        let unadjusted = |values: fn(&ContractBars) -> &Vec<Option<f64>>| {
+            picked
+                .iter()
+                .map(|(datetime, contract)| values(&contracts[*contract])[contracts[*contract].rows[datetime]])
+                .collect::<Vec<Option<f64>>>()
+        };
+
+        let datetimes = Int64Chunked::from_vec("datetime", picked.iter().map(|(datetime, _)| *datetime).collect());
+        let df = DataFrame::new(vec![
+            utc_datetime_series("datetime", datetimes),
+            Series::new("open", adjusted(|contract| &contract.open)),
+            Series::new("high", adjusted(|contract| &contract.high)),
+            Series::new("low", adjusted(|contract| &contract.low)),
+            Series::new("close", adjusted(|contract| &contract.close)),
+            Series::new("volume", unadjusted(|contract| &contract.volume)),
+            Series::new("open_interest", unadjusted(|contract| &contract.open_interest)),
+            Series::new(
+                "contract",
+                picked.iter().map(|(_, contract)| contracts[*contract].spec.ticker.as_str()).collect::<Vec<&str>>(),
+            ),
+        ])?;
+        Ok(df)
+    }
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: i64) -> bson::DateTime {
+        bson::DateTime::from_millis(day * MILLIS_PER_DAY)
+    }
+
+    // a contract with a bar on every day in days, closing at close + day and trading volume[i]
+    fn contract(ticker: &str, last_trade: i64, days: &[i64], close: f64, volume: &[f64]) -> (FuturesContractSpec, DataFrame) {
+        let spec = FuturesContractSpec {
+            root: "ES".to_string(),
+            ticker: ticker.to_string(),
+            exchange: "CME".to_string(),
+            series_collection_name: "equity_futures_1d".to_string(),
+            source: "eod".to_string(),
+            expiry: day(last_trade),
+            last_trade: day(last_trade),
+            first_notice: None,
+            multiplier: 50.0,
+            tick_size: 0.25,
+            currency: Some("USD".to_string()),
+        };
+        let closes = days.iter().map(|day| close + *day as f64).collect::<Vec<f64>>();
+        let df = DataFrame::new(vec![
+            utc_datetime_series("datetime", Int64Chunked::from_vec("datetime", days.iter().map(|day| day * MILLIS_PER_DAY).collect())),
+            Series::new("open", closes.clone()),
+            Series::new("high", closes.clone()),
+            Series::new("low", closes.clone()),
+            Series::new("close", closes),
+            Series::new("volume", volume.to_vec()),
+        ])
+        .unwrap();
+        (spec, df)
+    }
+
+    fn contracts() -> Vec<(FuturesContractSpec, DataFrame)> {
+        vec![
+            // listed out of order, the builder sorts them by expiry
+            contract("ESM24", 10, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9], 200.0, &[1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0]),
+            contract("ESH24", 5, &[0, 1, 2, 3, 4, 5], 100.0, &[5.0, 5.0, 0.5, 0.5, 0.5, 0.5]),
+        ]
+    }
+
+    fn column(df: &DataFrame, name: &str) -> Vec<f64> {
+        df.column(name).unwrap().cast(&DataType::Float64).unwrap().f64().unwrap().into_no_null_iter().collect()
+    }
+
+    #[test]
+    fn test_continuous_contract() {
+        // rolls 2 days before the front contract's last trade on day 5, i.e., on day 3
+        let df = ContinuousContractBuilder::new(RollRule::DaysBeforeExpiry(2), AdjustmentMethod::None).build(contracts()).unwrap();
+        let held = df.column("contract").unwrap().str().unwrap().into_no_null_iter().collect::<Vec<&str>>();
+        assert_eq!(held, vec!["ESH24", "ESH24", "ESH24", "ESM24", "ESM24", "ESM24", "ESM24", "ESM24", "ESM24", "ESM24"]);
+        assert_eq!(column(&df, "close")[2..4], [102.0, 203.0]);
+
+        // ESM24 out trades ESH24 on day 2, so it is held from day 3
+        let df = ContinuousContractBuilder::new(RollRule::VolumeSwitch, AdjustmentMethod::Difference).build(contracts()).unwrap();
+        let held = df.column("contract").unwrap().str().unwrap().into_no_null_iter().collect::<Vec<&str>>();
+        assert_eq!(held[2..4], ["ESH24", "ESM24"]);
+        // the 100 point gap on day 2 is added to every earlier bar
+        assert_eq!(column(&df, "close")[..4], [200.0, 201.0, 202.0, 203.0]);
+
+        let df = ContinuousContractBuilder::new(RollRule::VolumeSwitch, AdjustmentMethod::Ratio).build(contracts()).unwrap();
+        let closes = column(&df, "close");
+        assert!((closes[2] - 202.0).abs() < 1e-9);
+        assert!((closes[0] - 100.0 * 202.0 / 102.0).abs() < 1e-9);
+        assert_eq!(closes[9], 209.0);
+        assert_eq!(column(&df, "volume")[..3], [5.0, 5.0, 0.5]);
+    }
+}
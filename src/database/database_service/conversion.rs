@@ -0,0 +1,241 @@
+use anyhow::{anyhow, Result};
+use bson::{Bson, Document};
+use chrono::TimeZone;
+use chrono_tz::Tz;
+use mongodb::bson;
+use polars::prelude::*;
+use std::collections::HashMap;
+
+use crate::database::models::eod_models::OhlcvMetaData;
+use crate::database::utility_functions::parse_source_datetime;
+
+const MILLISECONDS_PER_DAY: i64 = 86_400_000;
+
+fn insert_column(documents: &mut [Document], name: &str, values: impl Iterator<Item = Option<Bson>>) {
+    for (document, value) in documents.iter_mut().zip(values) {
+        document.insert(name, value.unwrap_or(Bson::Null));
+    }
+}
+
+/*
+    Converts a df returned by the data apis into one document per row, a column at a time.
+    - "date" and "datetime" columns become the "datetime" time field, whether they are strings,
+      dates or datetimes. Strings are parsed with parse_source_datetime(), a string that is not a
+      date is an error. Strings and naive datetimes without an offset are in the source timezone of
+      the series, timezone aware datetimes are stored as the UTC instant they represent, and dates
+      stay at midnight UTC whatever the source timezone, so daily bars share one date key
+    - "metadata" holds the OhlcvMetaData as json and becomes an embedded document
+    - "volume" is always stored as a double, whether the api returned integers or fractions
+    - every other float, integer or boolean column is stored under its own name, so extra series
+      like open interest, vwap or trade count are kept
+    - any other column is an error rather than being silently dropped
+*/
+pub fn dataframe_to_documents(df: &DataFrame, source_timezone: Tz) -> Result<Vec<Document>> {
+    let mut documents = vec![Document::new(); df.height()];
+    for series in df.get_columns() {
+        let name = match series.name() {
+            "date" => "datetime",
+            name => name,
+        };
+        match (name, series.dtype()) {
+            ("datetime", DataType::String) => {
+                let values = series
+                    .str()?
+                    .into_iter()
+                    .map(|value| match value {
+                        Some(string) => Ok(Some(Bson::DateTime(parse_source_datetime(string, source_timezone)?))),
+                        None => Ok(None),
+                    })
+                    .collect::<Result<Vec<Option<Bson>>>>()?;
+                insert_column(&mut documents, name, values.into_iter());
+            }
+            ("metadata", DataType::String) => {
+                // metadata is the same json string on every row so each distinct value is parsed once
+                let mut parsed: HashMap<&str, Bson> = HashMap::new();
+                let mut values = Vec::with_capacity(series.len());
+                for value in series.str()?.into_iter() {
+                    let value = match value {
+                        Some(string) => match parsed.get(string) {
+                            Some(metadata_bson) => Some(metadata_bson.clone()),
+                            None => {
+                                let metadata: OhlcvMetaData = serde_json::from_str(string)?;
+                                let metadata_bson = bson::to_bson(&metadata)?;
+                                parsed.insert(string, metadata_bson.clone());
+                                Some(metadata_bson)
+                            }
+                        },
+                        None => None,
+                    };
+                    values.push(value);
+                }
+                insert_column(&mut documents, name, values.into_iter());
+            }
+            (_, DataType::Datetime(time_unit, timezone)) => {
+                let divisor = match time_unit {
+                    TimeUnit::Nanoseconds => 1_000_000,
+                    TimeUnit::Microseconds => 1_000,
+                    TimeUnit::Milliseconds => 1,
+                };
+                // naive datetimes hold the wall clock of the source timezone, aware ones an instant
+                let wall_clock = timezone.is_none() && source_timezone != Tz::UTC;
+                let physical = series.cast(&DataType::Int64)?;
+                let values = physical
+                    .i64()?
+                    .into_iter()
+                    .map(|value| match value {
+                        Some(value) if wall_clock => {
+                            let local = bson::DateTime::from_millis(value / divisor).to_chrono().naive_utc();
+                            let datetime = source_timezone
+                                .from_local_datetime(&local)
+                                .earliest()
+                                .ok_or_else(|| anyhow!("dataframe_to_documents() {} does not exist in timezone {}", local, source_timezone))?;
+                            Ok(Some(Bson::DateTime(bson::DateTime::from_chrono(datetime))))
+                        }
+                        value => Ok(value.map(|value| Bson::DateTime(bson::DateTime::from_millis(value / divisor)))),
+                    })
+                    .collect::<Result<Vec<Option<Bson>>>>()?;
+                insert_column(&mut documents, name, values.into_iter());
+            }
+            (_, DataType::Date) => {
+                let physical = series.cast(&DataType::Int32)?;
+                let values = physical
+                    .i32()?
+                    .into_iter()
+                    .map(|value| value.map(|days| Bson::DateTime(bson::DateTime::from_millis(days as i64 * MILLISECONDS_PER_DAY))));
+                insert_column(&mut documents, name, values);
+            }
+            ("volume", dtype) if dtype.is_numeric() => {
+                let values = series.cast(&DataType::Float64)?;
+                insert_column(&mut documents, name, values.f64()?.into_iter().map(|value| value.map(Bson::Double)));
+            }
+            (_, dtype) if dtype.is_float() => {
+                let values = series.cast(&DataType::Float64)?;
+                insert_column(&mut documents, name, values.f64()?.into_iter().map(|value| value.map(Bson::Double)));
+            }
+            (_, dtype) if dtype.is_integer() => {
+                // strict so u64 values that do not fit into bson's i64 error instead of becoming null
+                let values = series.strict_cast(&DataType::Int64)?;
+                insert_column(&mut documents, name, values.i64()?.into_iter().map(|value| value.map(Bson::Int64)));
+            }
+            (_, DataType::Boolean) => {
+                insert_column(&mut documents, name, series.bool()?.into_iter().map(|value| value.map(Bson::Boolean)));
+            }
+            (_, dtype) => {
+                return Err(anyhow!(
+                    "dataframe_to_documents() column {} has unsupported type {}",
+                    series.name(),
+                    dtype
+                ))
+            }
+        }
+    }
+    Ok(documents)
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata_json() -> String {
+        serde_json::to_string(&OhlcvMetaData {
+            metadata_collection_name: "equity_spot_1d".to_string(),
+            ticker: "AAPL".to_string(),
+            source: "eod".to_string(),
+            exchange: "US".to_string(),
+            currency: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_dataframe_to_documents() {
+        let df = df!(
+            "date" => ["2024-01-02", "2024-01-03"],
+            "open" => [1.0, 2.0],
+            "high" => [1.5, 2.5],
+            "low" => [0.5, 1.5],
+            "close" => [1.2, 2.2],
+            "adjusted_close" => [Some(1.1), None],
+            "volume" => [100i64, 200],
+            "open_interest" => [7i32, 8],
+            "vwap" => [1.15f32, 2.15],
+            "metadata" => [metadata_json(), metadata_json()],
+        )
+        .unwrap();
+
+        let columnar = dataframe_to_documents(&df, Tz::UTC).unwrap();
+        assert_eq!(columnar.len(), 2);
+        assert_eq!(
+            columnar[1].get_datetime("datetime").unwrap(),
+            &bson::DateTime::from_millis(1_704_240_000_000)
+        );
+        assert!(columnar[0].get("date").is_none());
+        assert_eq!(columnar[0].get_f64("open").unwrap(), 1.0);
+        assert_eq!(columnar[1].get_f64("close").unwrap(), 2.2);
+        assert_eq!(columnar[0].get_f64("adjusted_close").unwrap(), 1.1);
+        assert_eq!(columnar[1].get_f64("volume").unwrap(), 200.0);
+        let metadata = columnar[0].get_document("metadata").unwrap();
+        assert_eq!((metadata.get_str("ticker").unwrap(), metadata.get_str("exchange").unwrap()), ("AAPL", "US"));
+
+        // extra numeric columns that the row by row loop dropped are kept
+        assert_eq!(columnar[0].get_i64("open_interest").unwrap(), 7);
+        assert!(columnar[1].get_f64("vwap").is_ok());
+        assert_eq!(columnar[1].get("adjusted_close"), Some(&Bson::Null));
+    }
+
+    #[test]
+    fn test_dataframe_to_documents_volume() {
+        // equity volumes above i32::MAX and fractional crypto volumes both end up as doubles
+        let equity = df!(
+            "date" => ["2024-01-02", "2024-01-03"],
+            "volume" => [3_000_000_000i64, 9_007_199_254_740_000],
+        )
+        .unwrap();
+        let crypto = df!(
+            "date" => ["2024-01-02", "2024-01-03"],
+            "volume" => [0.5, 12_345.678_9],
+        )
+        .unwrap();
+
+        let equity = dataframe_to_documents(&equity, Tz::UTC).unwrap();
+        assert_eq!(equity[0].get("volume"), Some(&Bson::Double(3_000_000_000.0)));
+        assert_eq!(equity[1].get_f64("volume").unwrap(), 9_007_199_254_740_000.0);
+        let crypto = dataframe_to_documents(&crypto, Tz::UTC).unwrap();
+        assert_eq!(crypto[0].get_f64("volume").unwrap(), 0.5);
+        assert_eq!(crypto[1].get_f64("volume").unwrap(), 12_345.678_9);
+    }
+
+    #[test]
+    fn test_dataframe_to_documents_source_timezone() {
+        // a Tokyo source writes its wall clock, daily dates stay on the date whatever the exchange
+        let tokyo = Tz::Asia__Tokyo;
+        let strings = df!("datetime" => ["2024-01-02 09:00:00", "2024-01-02T15:00:00+09:00"]).unwrap();
+        let documents = dataframe_to_documents(&strings, tokyo).unwrap();
+        assert_eq!(documents[0].get_datetime("datetime").unwrap(), &bson::DateTime::from_millis(1_704_153_600_000));
+        assert_eq!(documents[1].get_datetime("datetime").unwrap(), &bson::DateTime::from_millis(1_704_175_200_000));
+
+        let naive = Int64Chunked::from_vec("datetime", vec![1_704_186_000_000]).into_datetime(TimeUnit::Milliseconds, None);
+        let documents = dataframe_to_documents(&DataFrame::new(vec![naive.into_series()]).unwrap(), tokyo).unwrap();
+        assert_eq!(documents[0].get_datetime("datetime").unwrap(), &bson::DateTime::from_millis(1_704_153_600_000));
+
+        let dates = df!("date" => ["2024-01-02"]).unwrap();
+        for timezone in [tokyo, Tz::America__New_York] {
+            let documents = dataframe_to_documents(&dates, timezone).unwrap();
+            assert_eq!(documents[0].get_datetime("datetime").unwrap(), &bson::DateTime::from_millis(1_704_153_600_000));
+        }
+    }
+
+    #[test]
+    fn test_dataframe_to_documents_unsupported_column() {
+        let df = df!(
+            "date" => ["2024-01-02"],
+            "exchange_name" => ["NASDAQ"],
+        )
+        .unwrap();
+        assert!(dataframe_to_documents(&df, Tz::UTC).is_err());
+
+        let df = df!("date" => ["2024-01-02", "02/01/2024"]).unwrap();
+        assert!(dataframe_to_documents(&df, Tz::UTC).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+use anyhow::Result;
+use chrono::{Datelike, Duration, Weekday};
+use polars::prelude::*;
+
+use crate::database::models::event_models::{AnnouncementTime, EarningsEvent, SplitEvent};
+use crate::database::utility_functions::utc_datetime_series;
+
+/*
+    The earnings event to store given the one fetched and the one stored for the same fiscal period, if any.
+    Event studies need the date an announcement was actually made, so:
+    - the date of a confirmed event, one already reported on, is final and never moved by later fetches
+    - the date of an unconfirmed event follows the datasource, counting every move in date_changes
+    - estimates and actuals are taken from the fetch unless it no longer has them
+    - last_updated only moves when something else did, so storing an unchanged event modifies nothing
+*/
+pub fn merge_earnings(stored: Option<&EarningsEvent>, mut fetched: EarningsEvent, fetched_at: bson::DateTime) -> EarningsEvent {
+    fetched.last_updated = fetched_at;
+    let stored = match stored {
+        Some(stored) => stored,
+        None => {
+            fetched.first_seen = fetched_at;
+            fetched.date_changes = 0;
+            return fetched;
+        }
+    };
+    fetched.first_seen = stored.first_seen;
+    fetched.date_changes = stored.date_changes;
+    if stored.confirmed {
+        if stored.announcement_date != fetched.announcement_date {
+            log::info!(
+                "merge_earnings() keeping confirmed announcement date {} of {}.{} over {}",
+                stored.announcement_date,
+                &stored.ticker,
+                &stored.exchange,
+                fetched.announcement_date
+            );
+        }
+        fetched.announcement_date = stored.announcement_date;
+        fetched.time_of_day = stored.time_of_day;
+        fetched.confirmed = true;
+    } else if stored.announcement_date != fetched.announcement_date {
+        fetched.date_changes += 1;
+    }
+    fetched.estimate = fetched.estimate.or(stored.estimate);
+    fetched.actual = fetched.actual.or(stored.actual);
+    fetched.surprise = fetched.surprise.or(stored.surprise);
+    fetched.surprise_percent = fetched.surprise_percent.or(stored.surprise_percent);
+    if fetched == (EarningsEvent { last_updated: fetched_at, ..stored.clone() }) {
+        fetched.last_updated = stored.last_updated;
+    }
+    fetched
+}
+
+/*
+    The first trading day whose close reflects the announcement, which is where post-earnings returns are
+    measured from: the announcement date, or the next weekday for announcements after the close. Announcements
+    of unknown time are taken as before the close. Exchange holidays are not known and count as trading days.
+*/
+pub fn reaction_date(event: &EarningsEvent) -> bson::DateTime {
+    let mut date = event.announcement_date.to_chrono().date_naive();
+    if event.time_of_day == AnnouncementTime::AfterMarket {
+        date += Duration::days(1);
+    }
+    while matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+        date += Duration::days(1);
+    }
+    bson::DateTime::from_chrono(date.and_hms_opt(0, 0, 0).expect("midnight is a valid time").and_utc())
+}
+
+fn datetime_series(name: &str, datetimes: impl Iterator<Item = bson::DateTime>) -> Series {
+    utc_datetime_series(name, Int64Chunked::from_vec(name, datetimes.map(|datetime| datetime.timestamp_millis()).collect()))
+}
+
+// earnings events as a df ordered by announcement date, which is the datetime column
+pub fn earnings_df(events: &[EarningsEvent]) -> Result<DataFrame> {
+    let mut events = events.iter().collect::<Vec<&EarningsEvent>>();
+    events.sort_by_key(|event| event.announcement_date);
+    let optional = |name: &str, field: fn(&EarningsEvent) -> Option<f64>| {
+        Series::new(name, events.iter().map(|event| field(event)).collect::<Vec<Option<f64>>>())
+    };
+    let time_of_day = events
+        .iter()
+        .map(|event| match event.time_of_day {
+            AnnouncementTime::BeforeMarket => "before_market",
+            AnnouncementTime::DuringMarket => "during_market",
+            AnnouncementTime::AfterMarket => "after_market",
+            AnnouncementTime::Unknown => "unknown",
+        })
+        .collect::<Vec<&str>>();
+    let df = DataFrame::new(vec![
+        datetime_series("datetime", events.iter().map(|event| event.announcement_date)),
+        datetime_series("reaction_date", events.iter().map(|event| reaction_date(event))),
+        datetime_series("fiscal_period_end", events.iter().map(|event| event.fiscal_period_end)),
+        Series::new("time_of_day", time_of_day),
+        optional("estimate", |event| event.estimate),
+        optional("actual", |event| event.actual),
+        optional("surprise", |event| event.surprise),
+        optional("surprise_percent", |event| event.surprise_percent),
+        Series::new("confirmed", events.iter().map(|event| event.confirmed).collect::<Vec<bool>>()),
+        Series::new("date_changes", events.iter().map(|event| event.date_changes).collect::<Vec<i32>>()),
+    ])?;
+    Ok(df)
+}
+
+// splits as a df ordered by split date, which is the datetime column
+pub fn splits_df(events: &[SplitEvent]) -> Result<DataFrame> {
+    let mut events = events.iter().collect::<Vec<&SplitEvent>>();
+    events.sort_by_key(|event| event.split_date);
+    let df = DataFrame::new(vec![
+        datetime_series("datetime", events.iter().map(|event| event.split_date)),
+        Series::new("old_shares", events.iter().map(|event| event.old_shares).collect::<Vec<f64>>()),
+        Series::new("new_shares", events.iter().map(|event| event.new_shares).collect::<Vec<f64>>()),
+        Series::new("price_factor", events.iter().map(|event| event.price_factor()).collect::<Vec<f64>>()),
+    ])?;
+    Ok(df)
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::utility_functions::parse_datetime;
+
+    fn event(announcement_date: &str, time_of_day: AnnouncementTime, actual: Option<f64>) -> EarningsEvent {
+        EarningsEvent {
+            ticker: "AAPL".to_string(),
+            exchange: "US".to_string(),
+            source: "eod".to_string(),
+            fiscal_period_end: parse_datetime("2023-06-30").unwrap(),
+            announcement_date: parse_datetime(announcement_date).unwrap(),
+            time_of_day,
+            currency: Some("USD".to_string()),
+            estimate: Some(1.19),
+            actual,
+            surprise: actual.map(|actual| actual - 1.19),
+            surprise_percent: None,
+            confirmed: actual.is_some(),
+            date_changes: 0,
+            first_seen: bson::DateTime::from_millis(0),
+            last_updated: bson::DateTime::from_millis(0),
+        }
+    }
+
+    #[test]
+    fn test_earnings_events() {
+        let (first_fetch, second_fetch, third_fetch) =
+            (parse_datetime("2023-07-01").unwrap(), parse_datetime("2023-07-15").unwrap(), parse_datetime("2023-08-01").unwrap());
+
+        // an expected date moves once, then is reported on and no longer moves
+        let stored = merge_earnings(None, event("2023-07-20", AnnouncementTime::Unknown, None), first_fetch);
+        let stored = merge_earnings(Some(&stored), event("2023-07-28", AnnouncementTime::AfterMarket, None), second_fetch);
+        assert_eq!((stored.date_changes, stored.first_seen, stored.confirmed), (1, first_fetch, false));
+        let stored = merge_earnings(Some(&stored), event("2023-07-28", AnnouncementTime::AfterMarket, Some(1.26)), third_fetch);
+        let refetched =
+            merge_earnings(Some(&stored), event("2023-07-27", AnnouncementTime::BeforeMarket, None), parse_datetime("2023-08-15").unwrap());
+        assert_eq!(refetched.announcement_date, stored.announcement_date);
+        assert_eq!((refetched.time_of_day, refetched.actual, refetched.date_changes), (AnnouncementTime::AfterMarket, Some(1.26), 1));
+        // the refetch changed nothing, so the stored event is written back as it was
+        assert_eq!(refetched, stored);
+
+        // after the close on a friday the market reacts on monday, before the open on the day itself
+        assert_eq!(reaction_date(&refetched), parse_datetime("2023-07-31").unwrap());
+        assert_eq!(reaction_date(&event("2023-07-27", AnnouncementTime::BeforeMarket, None)), parse_datetime("2023-07-27").unwrap());
+
+        let df = earnings_df(&[refetched, event("2023-04-27", AnnouncementTime::AfterMarket, Some(1.52))]).unwrap();
+        assert_eq!(df.column("actual").unwrap().f64().unwrap().into_no_null_iter().collect::<Vec<f64>>(), vec![1.52, 1.26]);
+    }
+}
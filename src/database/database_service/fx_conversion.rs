@@ -0,0 +1,217 @@
+use anyhow::{anyhow, Result};
+use polars::prelude::*;
+use std::collections::HashMap;
+
+use crate::database::utility_functions::{collection_interval_millis, major_currency};
+
+// collection forex pairs are read from unless set on the FxConversion
+pub const DEFAULT_FX_COLLECTION: &str = "fx_spot_1d";
+// currency pairs are triangulated through when there is no direct pair
+const TRIANGULATION_CURRENCY: &str = "USD";
+// columns holding prices, which are converted, anything else such as volume is left as is
+const PRICE_COLUMNS: [&str; 11] = [
+    "open", "high", "low", "close", "adjusted_close", "last", "bid", "ask", "change", "price", "vwap",
+];
+
+// where the forex pairs used to convert series into base_currency are stored, e.g., EURUSD in fx_spot_1d
+#[derive(Debug, Clone)]
+pub struct FxConversion {
+    pub base_currency: String,
+    pub fx_collection: String,
+    pub fx_exchange: String,
+    pub fx_source: String,
+}
+
+impl FxConversion {
+    pub fn new(base_currency: &str) -> Self {
+        Self {
+            base_currency: base_currency.to_uppercase(),
+            fx_collection: DEFAULT_FX_COLLECTION.to_string(),
+            fx_exchange: "FOREX".to_string(),
+            fx_source: "eod".to_string(),
+        }
+    }
+
+    pub fn fx_collection(mut self, fx_collection: &str) -> Self {
+        self.fx_collection = fx_collection.to_string();
+        self
+    }
+
+    // the pairs that can take currency to the base currency, directly, inverted or through USD
+    pub fn candidate_pairs(&self, currency: &str) -> Vec<String> {
+        let (currency, _) = major_currency(currency);
+        let (base, _) = major_currency(&self.base_currency);
+        let mut pairs: Vec<String> = Vec::new();
+        for (from, to) in [(currency.as_str(), base.as_str()), (&currency, TRIANGULATION_CURRENCY), (TRIANGULATION_CURRENCY, &base)] {
+            for pair in [format!("{}{}", from, to), format!("{}{}", to, from)] {
+                if from != to && !pairs.contains(&pair) {
+                    pairs.push(pair);
+                }
+            }
+        }
+        pairs
+    }
+}
+
+// the closes of a pair, keyed by the time each close was known, i.e., the end of its bar
+struct PairRates {
+    known_at: Vec<i64>,
+    rates: Vec<f64>,
+}
+
+impl PairRates {
+    // latest rate known at or before a time
+    fn rate_at(&self, time: i64) -> Option<f64> {
+        match self.known_at.partition_point(|known_at| *known_at <= time) {
+            0 => None,
+            row => Some(self.rates[row - 1]),
+        }
+    }
+}
+
+/*
+    Forex closes for converting series between currencies. Rates are aligned by bar ends rather than
+    bar stamps, so the daily close of a pair is used for the daily bars of the same date but only from
+    the next day for intraday bars, which would otherwise see a rate from later in the day.
+*/
+pub struct FxRates {
+    pairs: HashMap<String, PairRates>,
+}
+
+impl FxRates {
+    // pairs as (ticker, collection, df) as read with read_series(), empty series are left out
+    pub fn new(pairs: Vec<(String, &str, DataFrame)>) -> Result<Self> {
+        let mut pair_rates = HashMap::new();
+        for (pair, collection_name, df) in pairs.into_iter() {
+            let interval = collection_interval_millis(collection_name);
+            let datetimes = df.column("datetime")?.cast(&DataType::Int64)?;
+            let closes = df.column("close")?.cast(&DataType::Float64)?;
+            let (known_at, rates): (Vec<i64>, Vec<f64>) = datetimes
+                .i64()?
+                .into_iter()
+                .zip(closes.f64()?)
+                .filter_map(|(datetime, close)| match (datetime, close) {
+                    (Some(datetime), Some(close)) if close > 0.0 => Some((datetime + interval, close)),
+                    _ => None,
+                })
+                .unzip();
+            if !known_at.is_empty() {
+                pair_rates.insert(pair.to_uppercase(), PairRates { known_at, rates });
+            }
+        }
+        Ok(Self { pairs: pair_rates })
+    }
+
+    fn direct_rate(&self, from: &str, to: &str, time: i64) -> Option<Option<f64>> {
+        if from == to {
+            return Some(Some(1.0));
+        }
+        if let Some(pair) = self.pairs.get(&format!("{}{}", from, to)) {
+            return Some(pair.rate_at(time));
+        }
+        self.pairs
+            .get(&format!("{}{}", to, from))
+            .map(|pair| pair.rate_at(time).map(|rate| 1.0 / rate))
+    }
+
+    // units of to per unit of from at a time, None before the first rate
+    pub fn rate(&self, from: &str, to: &str, time: i64) -> Result<Option<f64>> {
+        let (from, from_factor) = major_currency(from);
+        let (to, to_factor) = major_currency(to);
+        let rate = match self.direct_rate(&from, &to, time) {
+            Some(rate) => rate,
+            None => match (
+                self.direct_rate(&from, TRIANGULATION_CURRENCY, time),
+                self.direct_rate(TRIANGULATION_CURRENCY, &to, time),
+            ) {
+                (Some(from_usd), Some(usd_to)) => from_usd.zip(usd_to).map(|(from_usd, usd_to)| from_usd * usd_to),
+                _ => return Err(anyhow!("FxRates has no pair to convert {} to {}, directly or through {}", from, to, TRIANGULATION_CURRENCY)),
+            },
+        };
+        Ok(rate.map(|rate| rate * from_factor / to_factor))
+    }
+
+    /*
+        Converts the price columns of a series in currency into to_currency, using the rate known at the
+        end of each bar given the interval of the series' collection. Rows before the first rate are null.
+    */
+    pub fn convert(&self, df: &DataFrame, collection_name: &str, currency: &str, to_currency: &str) -> Result<DataFrame> {
+        let interval = collection_interval_millis(collection_name);
+        let datetimes = df.column("datetime")?.cast(&DataType::Int64)?;
+        let rates = datetimes
+            .i64()?
+            .into_iter()
+            .map(|datetime| match datetime {
+                Some(datetime) => self.rate(currency, to_currency, datetime + interval),
+                None => Ok(None),
+            })
+            .collect::<Result<Vec<Option<f64>>>>()?;
+        let rates = Float64Chunked::from_iter(rates);
+
+        let mut converted = df.clone();
+        for name in PRICE_COLUMNS.iter().filter(|name| df.get_column_names().contains(name)) {
+            let prices = df.column(name)?.cast(&DataType::Float64)?;
+            let prices = (prices.f64()? * &rates).with_name(name);
+            converted.with_column(prices.into_series())?;
+        }
+        Ok(converted)
+    }
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::utility_functions::utc_datetime_series;
+
+    const DAY: i64 = 86_400_000;
+
+    fn series(days: &[i64], closes: &[f64]) -> DataFrame {
+        let datetimes = Int64Chunked::from_vec("datetime", days.iter().map(|day| day * DAY).collect());
+        DataFrame::new(vec![
+            utc_datetime_series("datetime", datetimes),
+            Series::new("close", closes.to_vec()),
+            Series::new("volume", vec![100.0; days.len()]),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_fx_conversion() {
+        let conversion = FxConversion::new("eur");
+        assert_eq!(conversion.candidate_pairs("GBP"), vec!["GBPEUR", "EURGBP", "GBPUSD", "USDGBP", "USDEUR", "EURUSD"]);
+        assert_eq!(conversion.candidate_pairs("USD"), vec!["USDEUR", "EURUSD"]);
+        assert_eq!(conversion.candidate_pairs("GBX")[..2], ["GBPEUR", "EURGBP"]);
+
+        let rates = FxRates::new(vec![
+            ("EURUSD".to_string(), "fx_spot_1d", series(&[0, 1, 2], &[1.10, 1.20, 1.25])),
+            ("GBPUSD".to_string(), "fx_spot_1d", series(&[0, 1, 2], &[1.25, 1.30, 1.40])),
+            ("USDJPY".to_string(), "fx_spot_1d", series(&[], &[])),
+        ])
+        .unwrap();
+        // inverted, triangulated and minor unit rates, as of the close of day 1
+        let close_of_day_1 = 2 * DAY;
+        assert!((rates.rate("USD", "EUR", close_of_day_1).unwrap().unwrap() - 1.0 / 1.20).abs() < 1e-12);
+        assert!((rates.rate("GBP", "EUR", close_of_day_1).unwrap().unwrap() - 1.30 / 1.20).abs() < 1e-12);
+        assert!((rates.rate("GBX", "USD", close_of_day_1).unwrap().unwrap() - 0.013).abs() < 1e-12);
+        assert_eq!(rates.rate("EUR", "USD", DAY - 1).unwrap(), None);
+        assert!(rates.rate("JPY", "EUR", close_of_day_1).is_err());
+
+        // daily bars use the close of the same day
+        let daily = rates.convert(&series(&[1, 2], &[100.0, 100.0]), "equity_spot_1d", "USD", "EUR").unwrap();
+        let closes = daily.column("close").unwrap().f64().unwrap().into_no_null_iter().collect::<Vec<f64>>();
+        assert!((closes[0] - 100.0 / 1.20).abs() < 1e-9 && (closes[1] - 100.0 / 1.25).abs() < 1e-9);
+        assert_eq!(daily.column("volume").unwrap().f64().unwrap().get(0), Some(100.0));
+
+        // a 1h bar during day 1 only knows the close of day 0, and one during day 0 knows nothing
+        let hourly = DataFrame::new(vec![
+            utc_datetime_series("datetime", Int64Chunked::from_vec("datetime", vec![DAY / 2, DAY + DAY / 2])),
+            Series::new("close", vec![100.0, 100.0]),
+        ])
+        .unwrap();
+        let hourly = rates.convert(&hourly, "equity_spot_1h", "EUR", "USD").unwrap();
+        let closes = hourly.column("close").unwrap().f64().unwrap().into_iter().collect::<Vec<Option<f64>>>();
+        assert_eq!(closes[0], None);
+        assert!((closes[1].unwrap() - 110.0).abs() < 1e-9);
+    }
+}
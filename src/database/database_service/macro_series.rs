@@ -0,0 +1,195 @@
+use anyhow::Result;
+use polars::prelude::*;
+use std::collections::HashMap;
+
+use crate::database::models::eod_models::OhlcvMetaData;
+use crate::database::models::macro_models::{MacroObservation, MacroValue};
+use crate::database::utility_functions::utc_datetime_series;
+
+// values closer than this to the latest vintage are the same release, not a revision
+const REVISION_TOLERANCE: f64 = 1e-9;
+
+// days from the end of a reference period to its publication, for indicators whose lag is known
+const INDICATOR_PUBLICATION_LAG_DAYS: &[(&str, i64)] = &[
+    ("gdp_current_usd", 200),
+    ("gdp_growth_annual", 200),
+    ("gdp_per_capita_usd", 200),
+    ("inflation_consumer_prices_annual", 120),
+    ("inflation_gdp_deflator_annual", 200),
+    ("unemployment_total_percent", 120),
+];
+
+/*
+    How long after the end of its reference period a value is published, used to estimate the release
+    dates of backfilled history. Indicators without a known lag fall back to a conservative lag for their
+    period, a release estimated late only hides a value for longer while one estimated early leaks it.
+*/
+pub fn publication_lag(indicator: &str, period: &str) -> chrono::Duration {
+    let days = INDICATOR_PUBLICATION_LAG_DAYS
+        .iter()
+        .find(|(name, _)| *name == indicator)
+        .map(|(_, days)| *days)
+        .unwrap_or(match period {
+            "Monthly" => 45,
+            "Quarterly" => 90,
+            _ => 200,
+        });
+    chrono::Duration::days(days)
+}
+
+/*
+    The vintages to store after fetching an indicator, given the vintages already stored for it:
+    - a reference period not seen before is its first release
+    - a value that differs from the latest vintage of its period is a revision
+    - anything else was already stored
+    New vintages are released on the date the datasource reports, or else the date they were first
+    fetched. The first fetch of an indicator brings its whole history, whose release dates are not known
+    and are estimated as the end of the reference period plus its publication_lag(), but no later than
+    the fetch, and flagged release_estimated.
+*/
+pub fn next_vintages(
+    stored: &[MacroObservation],
+    fetched: &[MacroValue],
+    metadata: &OhlcvMetaData,
+    fetched_at: bson::DateTime,
+) -> Vec<MacroObservation> {
+    let mut latest: HashMap<i64, &MacroObservation> = HashMap::new();
+    for observation in stored.iter() {
+        let period = observation.reference_period.timestamp_millis();
+        match latest.get(&period) {
+            Some(vintage) if vintage.revision >= observation.revision => (),
+            _ => {
+                latest.insert(period, observation);
+            }
+        }
+    }
+
+    let is_backfill = stored.is_empty();
+    let mut vintages = Vec::new();
+    for value in fetched.iter() {
+        let (revision, datetime, release_estimated) = match latest.get(&value.reference_period.timestamp_millis()) {
+            None => match (value.release_date, is_backfill) {
+                (Some(release_date), _) => (0, release_date, false),
+                (None, true) => {
+                    let lag = publication_lag(&metadata.ticker, &value.period);
+                    let release_date = bson::DateTime::from_chrono(value.reference_period.to_chrono() + lag);
+                    (0, release_date.min(fetched_at), true)
+                }
+                (None, false) => (0, fetched_at, false),
+            },
+            Some(vintage) if (vintage.value - value.value).abs() > REVISION_TOLERANCE => {
+                let release_date = value.release_date.filter(|release_date| *release_date > vintage.datetime);
+                (vintage.revision + 1, release_date.unwrap_or(fetched_at), false)
+            }
+            Some(_) => continue,
+        };
+        vintages.push(MacroObservation {
+            datetime,
+            reference_period: value.reference_period,
+            period: value.period.clone(),
+            value: value.value,
+            revision,
+            release_estimated,
+            metadata: metadata.clone(),
+        });
+    }
+    vintages
+}
+
+/*
+    The indicator as it was known at as_of, the latest vintage of each reference period released by
+    then, or the latest vintages if as_of is None. datetime is the release date of each value, which is
+    what to align it to prices on, and reference_period the period it measures.
+*/
+pub fn point_in_time(observations: &[MacroObservation], as_of: Option<bson::DateTime>) -> Result<DataFrame> {
+    let mut latest: HashMap<i64, &MacroObservation> = HashMap::new();
+    let known = observations
+        .iter()
+        .filter(|observation| match as_of {
+            Some(as_of) => observation.datetime <= as_of,
+            None => true,
+        });
+    for observation in known {
+        let period = observation.reference_period.timestamp_millis();
+        match latest.get(&period) {
+            Some(vintage) if vintage.revision >= observation.revision => (),
+            _ => {
+                latest.insert(period, observation);
+            }
+        }
+    }
+    vintages(latest.into_values())
+}
+
+// vintages as a df, one row per vintage ordered by reference period and revision
+pub fn vintages<'a>(observations: impl IntoIterator<Item = &'a MacroObservation>) -> Result<DataFrame> {
+    let mut observations = observations.into_iter().collect::<Vec<&MacroObservation>>();
+    observations.sort_by_key(|observation| (observation.reference_period, observation.revision));
+
+    let reference_periods = observations.iter().map(|observation| observation.reference_period.timestamp_millis()).collect();
+    let release_dates = observations.iter().map(|observation| observation.datetime.timestamp_millis()).collect();
+    let df = DataFrame::new(vec![
+        utc_datetime_series("reference_period", Int64Chunked::from_vec("reference_period", reference_periods)),
+        utc_datetime_series("datetime", Int64Chunked::from_vec("datetime", release_dates)),
+        Series::new("value", observations.iter().map(|observation| observation.value).collect::<Vec<f64>>()),
+        Series::new("revision", observations.iter().map(|observation| observation.revision).collect::<Vec<i32>>()),
+        Series::new(
+            "release_estimated",
+            observations.iter().map(|observation| observation.release_estimated).collect::<Vec<bool>>(),
+        ),
+    ])?;
+    Ok(df)
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::utility_functions::parse_datetime;
+
+    fn value(reference_period: &str, value: f64) -> MacroValue {
+        MacroValue {
+            reference_period: parse_datetime(reference_period).unwrap(),
+            period: "Annual".to_string(),
+            value,
+            release_date: None,
+        }
+    }
+
+    #[test]
+    fn test_macro_vintages() {
+        let metadata = OhlcvMetaData {
+            metadata_collection_name: "macro_indicator_1d".to_string(),
+            ticker: "gdp_growth_annual".to_string(),
+            source: "eod".to_string(),
+            exchange: "USA".to_string(),
+            currency: None,
+        };
+
+        // the history fetched first is estimated as released a publication lag after each period, or at
+        // the fetch for a period whose lag has not passed yet
+        let first_fetch = parse_datetime("2023-02-01").unwrap();
+        let mut stored = next_vintages(&[], &[value("2021-12-31", 5.9), value("2022-12-31", 2.1)], &metadata, first_fetch);
+        assert_eq!(stored.len(), 2);
+        assert!(stored.iter().all(|vintage| vintage.release_estimated && vintage.revision == 0));
+        assert_eq!(stored[0].datetime, parse_datetime("2022-07-19").unwrap());
+        assert_eq!(stored[1].datetime, first_fetch);
+        assert_eq!(publication_lag("unknown_indicator", "Monthly"), chrono::Duration::days(45));
+
+        // 2022 is revised and 2023 released, 2021 is unchanged
+        let second_fetch = parse_datetime("2024-02-01").unwrap();
+        let fetched = [value("2021-12-31", 5.9), value("2022-12-31", 1.9), value("2023-12-31", 2.5)];
+        let new_vintages = next_vintages(&stored, &fetched, &metadata, second_fetch);
+        assert_eq!(new_vintages.len(), 2);
+        assert_eq!((new_vintages[0].revision, new_vintages[0].datetime), (1, second_fetch));
+        assert_eq!((new_vintages[1].revision, new_vintages[1].release_estimated), (0, false));
+        stored.extend(new_vintages);
+        assert!(next_vintages(&stored, &fetched, &metadata, second_fetch).is_empty());
+
+        let values = |df: &DataFrame| df.column("value").unwrap().f64().unwrap().into_no_null_iter().collect::<Vec<f64>>();
+        assert_eq!(values(&point_in_time(&stored, None).unwrap()), vec![5.9, 1.9, 2.5]);
+        assert_eq!(values(&point_in_time(&stored, Some(parse_datetime("2023-06-30").unwrap())).unwrap()), vec![5.9, 2.1]);
+        assert_eq!(values(&point_in_time(&stored, Some(parse_datetime("2022-06-30").unwrap())).unwrap()), Vec::<f64>::new());
+        assert_eq!(vintages(stored.iter()).unwrap().height(), 4);
+    }
+}
@@ -1,2 +1,21 @@
+pub mod continuous_contract;
+pub mod conversion;
+pub mod corporate_events;
+pub mod fx_conversion;
+pub mod macro_series;
 pub mod mongodb;
-pub use mongodb::MongoDbClient;
\ No newline at end of file
+pub mod news_sentiment;
+pub mod option_chain;
+pub mod order_book;
+pub mod quote_poller;
+pub mod refresh_daemon;
+pub mod stream_ingestor;
+pub mod tick_bars;
+pub mod yield_curve;
+pub use continuous_contract::ContinuousContractBuilder;
+pub use fx_conversion::FxConversion;
+pub use mongodb::MongoDbClient;
+pub use quote_poller::QuotePoller;
+pub use refresh_daemon::RefreshDaemon;
+pub use stream_ingestor::StreamIngestor;
+pub use tick_bars::{BarSpec, TickBarBuilder};
@@ -1,45 +1,187 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use bson::{doc, Bson, Document};
-use chrono::{DateTime, Utc, NaiveDateTime};
-use dotenv::dotenv;
-use futures::{TryStreamExt, StreamExt};
+use chrono_tz::Tz;
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
 use mongodb::{
     bson,
     options::{
         CreateCollectionOptions, FindOptions, TimeseriesGranularity, TimeseriesOptions,
-        FindOneOptions
+        FindOneOptions, ReplaceOptions
     },
     Client,
 };
 use polars::prelude::*;
 use std::collections::{HashMap, HashSet};
-use std::env;
+use std::sync::Arc;
+use std::time::Instant;
 
-use crate::database::data_apis::EodApi;
-use crate::database::models::eod_models::{OhlcvMetaData, TimeseriesMetaDataStruct, MongoTickerParams, ReadSeriesFromMongoDb};
-use crate::database::utility_functions::{get_current_datetime_bson, string_to_datetime, has_business_day_between};
+use crate::database::config::Config;
+use crate::database::database_service::conversion::dataframe_to_documents;
+use crate::database::data_apis::{EodApi, MacroSource, SyntheticApi};
+use crate::database::models::eod_models::{OhlcvMetaData, TimeseriesMetaDataStruct, MongoTickerParams, SeriesVerification};
+use crate::database::models::schema_models::{CollectionKind, CollectionSchema, CollectionType, FieldType, Granularity};
+use crate::database::models::ingest_models::{Coverage, IngestAction, IngestReport, TickerReport};
+use crate::database::models::quote_models::Quote;
+use crate::database::models::event_models::{EarningsEvent, IpoEvent, SplitEvent};
+use crate::database::models::futures_models::FuturesContractSpec;
+use crate::database::models::macro_models::MacroObservation;
+use crate::database::models::news_models::NewsItem;
+use crate::database::models::option_models::{ChainFilter, OptionQuote};
+use crate::database::models::order_book_models::OrderBookSnapshot;
+use crate::database::models::rates_models::CurvePoint;
+use crate::database::models::tick_models::{QuoteTick, TradeTick};
+use crate::database::database_service::continuous_contract::ContinuousContractBuilder;
+use crate::database::database_service::corporate_events::{earnings_df, merge_earnings, splits_df};
+use crate::database::database_service::fx_conversion::{FxConversion, FxRates};
+use crate::database::database_service::macro_series::{next_vintages, point_in_time, vintages};
+use crate::database::database_service::news_sentiment::{align_sentiment, new_news_items, news_df};
+use crate::database::database_service::option_chain::chain_filter_document;
+use crate::database::database_service::order_book::book_series;
+use crate::database::database_service::tick_bars::{BarSpec, TickBarBuilder};
+use crate::database::database_service::yield_curve::curve_matrix;
+use crate::database::utility_functions::{
+    exchange_timezone, get_current_datetime_bson, instrument_currency, localize_datetimes, needs_refresh, parse_datetime,
+    parse_timezone, utc_datetime_series,
+};
+
+// collection in the metadata database that holds the CollectionSchema of every series collection
+const SCHEMA_COLLECTION_NAME: &str = "collection_schemas";
+// collection in the metadata database that holds the FuturesContractSpec of every futures contract
+const FUTURES_CONTRACTS_COLLECTION_NAME: &str = "futures_contracts";
+// collections in the events database that hold the EarningsEvent, IpoEvent and SplitEvent of every instrument
+const EARNINGS_COLLECTION_NAME: &str = "earnings_events";
+const IPOS_COLLECTION_NAME: &str = "ipo_events";
+const SPLITS_COLLECTION_NAME: &str = "split_events";
+// appended to the series database name for the events database unless it is set on the builder
+const EVENTS_DB_SUFFIX: &str = "_events";
+
+// number of tickers read or written at the same time unless set on the builder
+const DEFAULT_CONCURRENCY: usize = 16;
+
+// how far before a series forex pairs are read from, so its first rows have a rate across long weekends
+const FX_LOOKBACK_MILLIS: i64 = 14 * 86_400_000;
+// how far before the first bar sentiment is read so the first bars have the latest sentiment known
+const SENTIMENT_LOOKBACK_MILLIS: i64 = 14 * 86_400_000;
 
+// cloning is cheap as both the mongodb and reqwest clients are reference counted internally
+#[derive(Clone)]
 pub struct MongoDbClient {
     client: Client,
     db_name: String,
     db_metadata_name: String,
+    // database of the corporate event registries, kept out of the metadata database so it only holds series metadata
+    db_events_name: String,
+    eod_api: Option<EodApi>,
+    synthetic_api: SyntheticApi,
+    // the sources ingest_macro() fetches from, by the source name of the tickers
+    macro_sources: HashMap<String, Arc<dyn MacroSource>>,
+    // maximum number of tickers read or written at the same time
+    concurrency: usize,
 }
 
-impl MongoDbClient {
-    pub async fn new() -> Self {
-        dotenv().ok();
-        let client_url = env::var("MONGODB_URI").expect("Could not parse MongoDB URI from .env");
-        let database_name = env::var("MONGODB_NAME").expect("Could not parse MongoDB name from .env");
-        let database_metadata_name = env::var("MONGODB_METADATA_NAME").expect("Could not parse MongoDB metadata name from .env");
-        let mongo_client = Client::with_uri_str(client_url)
-            .await
-            .expect("Could not create MongoDB Client from DB URI");
+#[derive(Default)]
+pub struct MongoDbClientBuilder {
+    uri: Option<String>,
+    series_db: Option<String>,
+    metadata_db: Option<String>,
+    events_db: Option<String>,
+    eod_api: Option<EodApi>,
+    synthetic_api: Option<SyntheticApi>,
+    macro_sources: HashMap<String, Arc<dyn MacroSource>>,
+    concurrency: Option<usize>,
+}
+
+impl MongoDbClientBuilder {
+    pub fn config(mut self, config: &Config) -> Self {
+        self.uri = config.mongodb.uri.clone().or(self.uri);
+        self.series_db = config.mongodb.series_db.clone().or(self.series_db);
+        self.metadata_db = config.mongodb.metadata_db.clone().or(self.metadata_db);
+        self.events_db = config.mongodb.events_db.clone().or(self.events_db);
+        self.concurrency = config.mongodb.concurrency.or(self.concurrency);
+        if let Some(api_token) = &config.eod.api_token {
+            self.eod_api = Some(EodApi::with_token(api_token.clone()));
+        }
+        self.synthetic_api = Some(SyntheticApi::new(config.synthetic.clone()));
+        self
+    }
+
+    pub fn uri(mut self, uri: impl Into<String>) -> Self {
+        self.uri = Some(uri.into());
+        self
+    }
+
+    pub fn series_db(mut self, series_db: impl Into<String>) -> Self {
+        self.series_db = Some(series_db.into());
+        self
+    }
+
+    pub fn metadata_db(mut self, metadata_db: impl Into<String>) -> Self {
+        self.metadata_db = Some(metadata_db.into());
+        self
+    }
+
+    pub fn events_db(mut self, events_db: impl Into<String>) -> Self {
+        self.events_db = Some(events_db.into());
+        self
+    }
+
+    pub fn eod_api(mut self, eod_api: EodApi) -> Self {
+        self.eod_api = Some(eod_api);
+        self
+    }
+
+    pub fn synthetic_api(mut self, synthetic_api: SyntheticApi) -> Self {
+        self.synthetic_api = Some(synthetic_api);
+        self
+    }
+
+    // registers a source of macro indicators for tickers whose source is name, replacing EOD for "eod"
+    pub fn macro_source(mut self, name: impl Into<String>, macro_source: impl MacroSource + 'static) -> Self {
+        self.macro_sources.insert(name.into(), Arc::new(macro_source));
+        self
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    pub async fn build(self) -> Result<MongoDbClient> {
+        let client_url = self.uri.ok_or_else(|| anyhow!("MongoDbClientBuilder is missing the MongoDB uri"))?;
+        let database_name = self.series_db.ok_or_else(|| anyhow!("MongoDbClientBuilder is missing the series database name"))?;
+        let database_metadata_name = self.metadata_db.ok_or_else(|| anyhow!("MongoDbClientBuilder is missing the metadata database name"))?;
+        let database_events_name = self.events_db.unwrap_or_else(|| format!("{}{}", database_name, EVENTS_DB_SUFFIX));
+        let mongo_client = Client::with_uri_str(client_url).await?;
         log::info!("Established Client for MongoDB!");
-        Self {
-            client: mongo_client.clone(),
+        let mut macro_sources = self.macro_sources;
+        if let Some(eod_api) = self.eod_api.clone() {
+            macro_sources.entry("eod".to_string()).or_insert_with(|| Arc::new(eod_api));
+        }
+        Ok(MongoDbClient {
+            client: mongo_client,
             db_name: database_name,
             db_metadata_name: database_metadata_name,
-        }
+            db_events_name: database_events_name,
+            eod_api: self.eod_api,
+            synthetic_api: self.synthetic_api.unwrap_or_default(),
+            macro_sources,
+            concurrency: self.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1),
+        })
+    }
+}
+
+impl MongoDbClient {
+    pub async fn new() -> Self {
+        let config = Config::load().expect("Could not load config");
+        Self::builder()
+            .config(&config)
+            .build()
+            .await
+            .expect("Could not create MongoDB Client from config")
+    }
+
+    pub fn builder() -> MongoDbClientBuilder {
+        MongoDbClientBuilder::default()
     }
 
     pub async fn create_series_collection(
@@ -54,10 +196,19 @@ impl MongoDbClient {
         let timeseries_options = TimeseriesOptions::builder()
             .time_field("datetime".to_string())
             .meta_field(Some("metadata".to_string()))
-            .granularity(match collection_name.chars().last() {
-                Some('h') | Some('d') => Some(TimeseriesGranularity::Hours),
-                Some('m') => Some(TimeseriesGranularity::Minutes),
-                Some('s') => Some(TimeseriesGranularity::Seconds),
+            .granularity(match CollectionType::parse(collection_name) {
+                CollectionType { granularity: Some(Granularity::Hours(_) | Granularity::Days(_)), .. } => Some(TimeseriesGranularity::Hours),
+                CollectionType { granularity: Some(Granularity::Minutes(_)), .. } => Some(TimeseriesGranularity::Minutes),
+                CollectionType { granularity: Some(Granularity::Seconds(_)), .. } => Some(TimeseriesGranularity::Seconds),
+                // live collections store a snapshot every poll, ticks, books and news a row whenever one arrives
+                CollectionType {
+                    kind: CollectionKind::LiveQuotes
+                        | CollectionKind::TradeTicks
+                        | CollectionKind::QuoteTicks
+                        | CollectionKind::BookSnapshots
+                        | CollectionKind::NewsItems,
+                    ..
+                } => Some(TimeseriesGranularity::Seconds),
                 _ => None,
             })
             .build();
@@ -91,7 +242,17 @@ impl MongoDbClient {
             .collection(&ticker.series_collection_name);
 
         let current_date = get_current_datetime_bson();
-        let start_date = string_to_datetime("1970-01-01");
+        let start_date = bson::DateTime::from_millis(0);
+        let source_timezone = default_source_timezone(ticker);
+        let currency = match (instrument_currency(&ticker.ticker, &ticker.exchange), self.eod_api.as_ref()) {
+            (Some(currency), _) => Some(currency),
+            // exchanges that list instruments in several currencies, or that are not known, are asked per instrument
+            (None, Some(eod_api)) if ticker.source == "eod" => eod_api.get_currency(ticker).await.unwrap_or_else(|e| {
+                log::warn!("insert_metadata() could not look up the currency of {}.{}: {:#}", &ticker.ticker, &ticker.exchange, e);
+                None
+            }),
+            (None, _) => None,
+        };
         let metadata = TimeseriesMetaDataStruct {
             ticker: ticker.ticker.clone(),
             exchange: ticker.exchange.clone(),
@@ -100,6 +261,9 @@ impl MongoDbClient {
             from: start_date,
             to: current_date,
             last_updated: current_date,
+            source_timezone: source_timezone.name().to_string(),
+            exchange_timezone: exchange_timezone(&ticker.exchange).name().to_string(),
+            currency,
         };
 
         collection
@@ -132,10 +296,11 @@ impl MongoDbClient {
         Ok(true)
     }
 
+    // the dfs fetched for every ticker, with the error of each ticker its datasource could not fetch
     pub async fn get_data_from_apis(
         &self,
         tickers: Vec<MongoTickerParams>,
-    ) -> Result<Vec<DataFrame>> {
+    ) -> Result<Vec<(MongoTickerParams, Result<DataFrame>)>> {
         // sort tickers by api source (eod, binance, etc.). Output will be a tuple: ("eod", Vec<MongoTickerParams>)
         let mut sorted_tickers = Vec::new();
         let datasource_apis: HashSet<&String> = tickers.iter().map(|tuple| &tuple.source).collect();
@@ -157,297 +322,1692 @@ impl MongoDbClient {
         for datasource in sorted_tickers.into_iter() {
             match datasource {
                 ("eod", _) => {
-                    let eod_client = EodApi::new().await;
                     let ticker_infos = datasource.1;
+                    let eod_client = match self.eod_api.as_ref() {
+                        Some(eod_client) => eod_client,
+                        None => {
+                            for ticker in ticker_infos.into_iter() {
+                                dfs.push((ticker, Err(anyhow!("get_data_from_apis() EOD API token is not configured!"))));
+                            }
+                            continue;
+                        }
+                    };
                     let eod_dfs = eod_client.batch_get_series_all(ticker_infos).await?;
                     log::info!("get_data_from_apis() Sucessfully retrieved data from EOD API!");
                     dfs.extend(eod_dfs);
                 }
-                _ => log::error!("Datasource: {} is not supported!", datasource.0),
+                ("synthetic", _) => {
+                    let synthetic_dfs = self.synthetic_api.batch_get_series_all(datasource.1).await?;
+                    log::info!("get_data_from_apis() Sucessfully generated data from the synthetic datasource!");
+                    dfs.extend(synthetic_dfs);
+                }
+                (source, _) => {
+                    log::error!("Datasource: {} is not supported!", source);
+                    for ticker in datasource.1.into_iter() {
+                        let e = anyhow!("get_data_from_apis() datasource {} is not supported!", source);
+                        dfs.push((ticker, Err(e)));
+                    }
+                }
             }
         }
 
         Ok(dfs)
     }
 
+    // naive datetimes in each df are in the source timezone of its series, looked up once for all the dfs
     pub async fn insert_series(&self, dfs: Vec<DataFrame>) -> Result<bool> {
-        // iterate through each df
-        for df in dfs.iter() {
-            let col_names = df.get_column_names();
-            let mut doc_vec = Vec::new();
-            // iterate through each row of df
-            for i in 0..df.height() {
-                let row = df.get_row(i).unwrap();
-                let mut doc_row = Document::new();
-                // iterate through each element within row of df
-                for (i, name) in col_names.iter().enumerate().take(df.width()) {
-                    match (*name, row.0.get(i)) {
-                        (_, Some(AnyValue::Null)) => {
-                            doc_row.insert(name.to_string(), Bson::Null);
-                        }
-                        ("open", Some(AnyValue::Float64(number)))
-                        | ("high", Some(AnyValue::Float64(number)))
-                        | ("low", Some(AnyValue::Float64(number)))
-                        | ("close", Some(AnyValue::Float64(number)))
-                        | ("adjusted_close", Some(AnyValue::Float64(number))) => {
-                            doc_row.insert(name.to_string(), Bson::Double(*number));
-                        }
-                        ("volume", Some(AnyValue::Int64(number))) => {
-                            doc_row.insert(name.to_string(), *number);
-                        }
-                        ("datetime", Some(AnyValue::String(string))) => {
-                            let datetime = string_to_datetime(string);
-                            doc_row.insert(name.to_string(), Bson::DateTime(datetime));
-                        }
-                        ("date", Some(AnyValue::String(string))) => {
-                            let date = string_to_datetime(string);
-                            doc_row.insert("datetime".to_string(), Bson::DateTime(date));
-                        }
-                        ("metadata", Some(AnyValue::String(string))) => {
-                            let metadata: OhlcvMetaData =
-                                serde_json::from_str(string)?;
-                            let metadata_bson = bson::to_bson(&metadata)?;
-                            doc_row.insert(name.to_string(), metadata_bson);
-                        }
-                        _ => ()// log::error!("insert_series() Could not parse column!"),
-                    }
-                }
-                doc_vec.push(doc_row);
-            }
+        let dfs = dfs.into_iter().filter(|df| df.height() > 0).collect::<Vec<DataFrame>>();
+        let tickers = dfs
+            .iter()
+            .map(|df| Ok(metadata_ticker(series_metadata(df)?)))
+            .collect::<Result<Vec<MongoTickerParams>>>()?;
+        let source_timezones = self.source_timezones(&tickers).await?;
+        futures::stream::iter(dfs.iter().zip(source_timezones))
+            .map(|(df, source_timezone)| self.insert_series_df(df, source_timezone))
+            .buffer_unordered(self.concurrency)
+            .try_collect::<Vec<usize>>()
+            .await?;
 
-            let document = doc_vec.pop().expect("Could not pop collection name!");
-            let metadata = document.get("metadata").expect("Could not get metadata!");
-            let metadata = metadata.as_document().expect("Metadata is not a document!");
-            let collection_name = metadata.get("metadata_collection_name").expect("Could not get metadata_collection_name!");
-            let collection_name = collection_name.as_str().expect("Could not convert metadata_collection_name to string!"); 
-            
-            let collection = self
-                .client
-                .clone()
-                .database(&self.db_name)
-                .collection::<Document>(collection_name);
-            collection.insert_many(doc_vec, None).await?;
+        Ok(true)
+    }
+
+    // the source timezone of each ticker's series as recorded in its metadata, in the order of tickers
+    pub async fn source_timezones(&self, tickers: &[MongoTickerParams]) -> Result<Vec<Tz>> {
+        if tickers.is_empty() {
+            return Ok(Vec::new());
         }
+        let metadata_vec = self.find_metadata_batch(tickers).await?;
+        tickers.iter().map(|ticker| series_source_timezone(&metadata_vec, ticker)).collect()
+    }
 
-        Ok(true)
+    /*
+        Inserts every row of a df from the data apis, returning the rows inserted. An empty df inserts nothing.
+        Naive datetimes in the df are in source_timezone, see source_timezones().
+    */
+    pub async fn insert_series_df(&self, df: &DataFrame, source_timezone: Tz) -> Result<usize> {
+        self.insert_series_df_after(df, None, source_timezone).await
+    }
+
+    // inserts the rows of a df after a datetime, those at or before it are already stored when refreshing
+    async fn insert_series_df_after(&self, df: &DataFrame, after: Option<bson::DateTime>, source_timezone: Tz) -> Result<usize> {
+        if df.height() == 0 {
+            return Ok(0);
+        }
+        let mut doc_vec = dataframe_to_documents(df, source_timezone)?;
+        if let Some(after) = after {
+            doc_vec.retain(|document| document.get_datetime("datetime").map_or(true, |datetime| *datetime > after));
+        }
+        if doc_vec.is_empty() {
+            return Ok(0);
+        }
+        let metadata = series_metadata(df)?;
+
+        let collection = self
+            .client
+            .clone()
+            .database(&self.db_name)
+            .collection::<Document>(&metadata.metadata_collection_name);
+        let result = collection.insert_many(doc_vec, None).await?;
+
+        Ok(result.inserted_ids.len())
     }
 
     pub async fn update_metadata_dates(&self, tickers: &[MongoTickerParams]) -> Result<bool> {
+        futures::stream::iter(tickers.iter())
+            .map(|ticker| self.update_ticker_metadata_dates(ticker))
+            .buffer_unordered(self.concurrency)
+            .try_collect::<Vec<()>>()
+            .await?;
+        Ok(true)
+    }
+
+    pub async fn update_ticker_metadata_dates(&self, ticker: &MongoTickerParams) -> Result<()> {
         let metadata_db = self.client.clone().database(&self.db_metadata_name);
         let series_db = self.client.clone().database(&self.db_name);
+        log::info!("update_metadata_dates() updating metadata for ticker {} in collection {}", &ticker.ticker, &ticker.series_collection_name);
+        let metadata_collection = metadata_db.collection::<TimeseriesMetaDataStruct>(&ticker.series_collection_name);
+        let series_filter = doc! {
+            "metadata.ticker": &ticker.ticker,
+            "metadata.exchange": &ticker.exchange,
+            "metadata.metadata_collection_name": &ticker.series_collection_name,
+            "metadata.source": &ticker.source,
+        };
+
+        let metadata_filter = doc! {
+            "ticker": &ticker.ticker,
+            "exchange": &ticker.exchange,
+            "series_collection_name": &ticker.series_collection_name,
+            "source": &ticker.source,
+        };
+
+        let mut min_max_dates = HashMap::new();
+
+        // get max date
+        let series_collection = series_db.collection::<Document>(&ticker.series_collection_name);
+        let max_date_options= FindOneOptions::builder().sort(doc! { "datetime": -1 }).build();
+        let max_date_result = series_collection.find_one(Some(series_filter.clone()), max_date_options).await?;
+        if let Some(max_date_row) = max_date_result {
+            let _max_date= max_date_row.get("datetime").expect("Could not get datetime!");
+            min_max_dates.insert("max_date", _max_date.clone());
+        }
+
+        // get min date
+        let min_date_options= FindOneOptions::builder().sort(doc! { "datetime": 1 }).build();
+        let min_date_result = series_collection.find_one(Some(series_filter), min_date_options).await?;
+        if let Some(min_date_row) = min_date_result {
+            let _min_date = min_date_row.get("datetime").expect("Could not get datetime!");
+            min_max_dates.insert("min_date", _min_date.clone());
+        }
+
+        let min_date = min_max_dates.get("min_date").ok_or_else(|| {
+            anyhow!("update_metadata_dates() found no series rows for ticker {} in collection {}", &ticker.ticker, &ticker.series_collection_name)
+        })?;
+        let max_date = min_max_dates.get("max_date").ok_or_else(|| {
+            anyhow!("update_metadata_dates() found no series rows for ticker {} in collection {}", &ticker.ticker, &ticker.series_collection_name)
+        })?;
+
+        metadata_collection
+            .update_one(
+                metadata_filter,
+                doc! {
+                    "$set": {
+                        "from": min_date,
+                        "to": max_date,
+                        "last_updated": get_current_datetime_bson(),
+                    }
+                },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn read_series(&self, tickers: Vec<MongoTickerParams>) -> Result<Vec<(String, DataFrame)>> {
+        // schemas are looked up once per collection rather than once per ticker
+        let mut schemas = HashMap::new();
         for ticker in tickers.iter() {
-            log::info!("update_metadata_dates() updating metadata for ticker {} in collection {}", &ticker.ticker, &ticker.series_collection_name);
-            let metadata_collection = metadata_db.collection::<TimeseriesMetaDataStruct>(&ticker.series_collection_name);
-            let series_filter = doc! {
-                "metadata.ticker": &ticker.ticker,
-                "metadata.exchange": &ticker.exchange,
-                "metadata.metadata_collection_name": &ticker.series_collection_name,
-                "metadata.source": &ticker.source,
-            };
+            if !schemas.contains_key(&ticker.series_collection_name) {
+                let schema = self.get_schema(&ticker.series_collection_name).await?;
+                schemas.insert(ticker.series_collection_name.clone(), schema);
+            }
+        }
 
-            let metadata_filter = doc! {
-                "ticker": &ticker.ticker,
-                "exchange": &ticker.exchange,
-                "series_collection_name": &ticker.series_collection_name,
-                "source": &ticker.source,
-            };
+        // buffered keeps the output in the same order as the tickers
+        let reads = tickers
+            .into_iter()
+            .map(|ticker| {
+                let schema = schemas[&ticker.series_collection_name].clone();
+                self.read_ticker_series(ticker, schema)
+            })
+            .collect::<Vec<_>>();
+        futures::stream::iter(reads)
+            .buffered(self.concurrency)
+            .try_collect()
+            .await
+    }
+
+    // read_series() with the datetime column of every series localized to the timezone of its exchange
+    pub async fn read_series_localized(&self, tickers: Vec<MongoTickerParams>) -> Result<Vec<(String, DataFrame)>> {
+        let dfs = self.read_series(tickers.clone()).await?;
+        self.localize_series(&tickers, dfs).await
+    }
+
+    // localizes series read for tickers, in the same order, e.g., after converting them into another currency
+    pub async fn localize_series(
+        &self,
+        tickers: &[MongoTickerParams],
+        dfs: Vec<(String, DataFrame)>,
+    ) -> Result<Vec<(String, DataFrame)>> {
+        let metadata_vec = self.find_metadata_batch(tickers).await?;
+        let timezones = tickers
+            .iter()
+            .map(|ticker| match find_ticker_metadata(&metadata_vec, ticker) {
+                Some(metadata) => parse_timezone(&metadata.exchange_timezone),
+                None => Ok(exchange_timezone(&ticker.exchange)),
+            })
+            .collect::<Result<Vec<Tz>>>()?;
+        let daily = tickers
+            .iter()
+            .map(|ticker| CollectionType::parse(&ticker.series_collection_name).is_daily())
+            .collect::<Vec<bool>>();
+
+        dfs.into_iter()
+            .zip(timezones.into_iter().zip(daily))
+            .map(|((ticker, df), (timezone, daily))| Ok((ticker, localize_datetimes(&df, timezone, daily)?)))
+            .collect()
+    }
+
+    /*
+        read_series() with prices converted into the base currency of the conversion using the forex pairs
+        stored in its fx_collection, see FxRates. The currency of a series is the one recorded in its metadata,
+        or the one its exchange quotes in for metadata written before currencies were recorded.
+    */
+    pub async fn read_series_in_currency(
+        &self,
+        tickers: Vec<MongoTickerParams>,
+        conversion: &FxConversion,
+    ) -> Result<Vec<(String, DataFrame)>> {
+        let metadata_vec = self.find_metadata_batch(&tickers).await?;
+        let currencies = tickers
+            .iter()
+            .map(|ticker| {
+                find_ticker_metadata(&metadata_vec, ticker)
+                    .and_then(|metadata| metadata.currency.clone())
+                    .or_else(|| instrument_currency(&ticker.ticker, &ticker.exchange))
+                    .ok_or_else(|| anyhow!("read_series_in_currency() currency of {}.{} is not known", &ticker.ticker, &ticker.exchange))
+            })
+            .collect::<Result<Vec<String>>>()?;
+
+        // every pair that could be needed is read, pairs that are not stored come back empty
+        let mut pairs = currencies
+            .iter()
+            .flat_map(|currency| conversion.candidate_pairs(currency))
+            .collect::<Vec<String>>();
+        pairs.sort();
+        pairs.dedup();
+        let fx_from = tickers.iter().map(|ticker| ticker.from).min().unwrap_or(bson::DateTime::MIN);
+        let fx_to = tickers.iter().map(|ticker| ticker.to).max().unwrap_or(bson::DateTime::MAX);
+        let fx_tickers = pairs
+            .into_iter()
+            .map(|pair| MongoTickerParams {
+                ticker: pair,
+                exchange: conversion.fx_exchange.clone(),
+                series_collection_name: conversion.fx_collection.clone(),
+                source: conversion.fx_source.clone(),
+                // rates from before the first row are needed to convert it
+                from: bson::DateTime::from_millis(fx_from.timestamp_millis().saturating_sub(FX_LOOKBACK_MILLIS)),
+                to: fx_to,
+            })
+            .collect::<Vec<MongoTickerParams>>();
+        let fx_series = self
+            .read_series(fx_tickers)
+            .await?
+            .into_iter()
+            .map(|(pair, df)| (pair, conversion.fx_collection.as_str(), df))
+            .collect::<Vec<(String, &str, DataFrame)>>();
+        let rates = FxRates::new(fx_series)?;
+
+        let dfs = self.read_series(tickers.clone()).await?;
+        dfs.into_iter()
+            .zip(tickers.iter().zip(currencies.iter()))
+            .map(|((name, df), (ticker, currency))| {
+                let df = rates.convert(&df, &ticker.series_collection_name, currency, &conversion.base_currency)?;
+                Ok((name, df))
+            })
+            .collect()
+    }
+
+    /*
+        Adds the daily sentiment stored for each ticker in sentiment_collection, e.g., equity_sentiment_1d, to
+        series read for tickers, in the same order, as sentiment and sentiment_count columns aligned to the
+        bars by news_sentiment::align_sentiment(). The sentiment of a ticker is the series with its ticker,
+        exchange and source, tickers without any have null columns.
+    */
+    pub async fn attach_sentiment(
+        &self,
+        tickers: &[MongoTickerParams],
+        dfs: Vec<(String, DataFrame)>,
+        sentiment_collection: &str,
+    ) -> Result<Vec<(String, DataFrame)>> {
+        let sentiment_tickers = tickers
+            .iter()
+            .map(|ticker| MongoTickerParams {
+                ticker: ticker.ticker.clone(),
+                exchange: ticker.exchange.clone(),
+                series_collection_name: sentiment_collection.to_string(),
+                source: ticker.source.clone(),
+                from: bson::DateTime::from_millis(ticker.from.timestamp_millis().saturating_sub(SENTIMENT_LOOKBACK_MILLIS)),
+                to: ticker.to,
+            })
+            .collect::<Vec<MongoTickerParams>>();
+        let sentiment_dfs = self.read_series(sentiment_tickers).await?;
+
+        dfs.into_iter()
+            .zip(tickers.iter().zip(sentiment_dfs))
+            .map(|((name, df), (ticker, (_, sentiment)))| {
+                let df = align_sentiment(&df, &ticker.series_collection_name, &sentiment, sentiment_collection)?;
+                Ok((name, df))
+            })
+            .collect()
+    }
+
+    // read_series() with the daily sentiment of each ticker, see attach_sentiment()
+    pub async fn read_series_with_sentiment(
+        &self,
+        tickers: Vec<MongoTickerParams>,
+        sentiment_collection: &str,
+    ) -> Result<Vec<(String, DataFrame)>> {
+        let dfs = self.read_series(tickers.clone()).await?;
+        self.attach_sentiment(&tickers, dfs, sentiment_collection).await
+    }
+
+    async fn read_ticker_series(&self, ticker: MongoTickerParams, schema: CollectionSchema) -> Result<(String, DataFrame)> {
+        let ticker_name = ticker.ticker.clone();
+        let chunks = self
+            .read_series_stream_with_schema(ticker, schema.clone(), usize::MAX)
+            .await?
+            .try_collect::<Vec<DataFrame>>()
+            .await?;
 
-            let mut min_max_dates = HashMap::new();
+        let mut chunks = chunks.into_iter();
+        let mut df = match chunks.next() {
+            Some(df) => df,
+            None => SeriesColumnBuilder::new(&schema, 0).finish()?,
+        };
+        for chunk in chunks {
+            df.vstack_mut(&chunk)?;
+        }
+
+        Ok((ticker_name, df))
+    }
 
-            // get max date
-            let series_collection = series_db.collection::<Document>(&ticker.series_collection_name);
-            let max_date_options= FindOneOptions::builder().sort(doc! { "datetime": -1 }).build();
-            let max_date_result = series_collection.find_one(Some(series_filter.clone()), max_date_options).await?;
-            if let Some(max_date_row) = max_date_result {
-                let _max_date= max_date_row.get("datetime").expect("Could not get datetime!");
-                min_max_dates.insert("max_date", _max_date.clone());
+    // streams a series as dataframes of at most rows_per_chunk rows, building columns straight from the cursor
+    pub async fn read_series_stream(
+        &self,
+        ticker: MongoTickerParams,
+        rows_per_chunk: usize,
+    ) -> Result<BoxStream<'static, Result<DataFrame>>> {
+        let schema = self.get_schema(&ticker.series_collection_name).await?;
+        self.read_series_stream_with_schema(ticker, schema, rows_per_chunk).await
+    }
+
+    async fn read_series_stream_with_schema(
+        &self,
+        ticker: MongoTickerParams,
+        schema: CollectionSchema,
+        rows_per_chunk: usize,
+    ) -> Result<BoxStream<'static, Result<DataFrame>>> {
+        log::info!("read_series_stream() reading series for ticker {} in collection {}", &ticker.ticker, &ticker.series_collection_name);
+        let ticker_collection = self
+            .client
+            .clone()
+            .database(&self.db_name)
+            .collection::<Document>(&ticker.series_collection_name);
+        let ticker_filter = doc! {
+            "metadata.ticker": &ticker.ticker,
+            "metadata.exchange": &ticker.exchange,
+            "metadata.metadata_collection_name": &ticker.series_collection_name,
+            "metadata.source": &ticker.source,
+            "datetime": {
+                "$gte": ticker.from,
+                "$lte": ticker.to
             }
+        };
 
-            // get min date
-            let min_date_options= FindOneOptions::builder().sort(doc! { "datetime": 1 }).build();
-            let min_date_result = series_collection.find_one(Some(series_filter), min_date_options).await?;
-            if let Some(min_date_row) = min_date_result {
-                let _min_date = min_date_row.get("datetime").expect("Could not get datetime!");
-                min_max_dates.insert("min_date", _min_date.clone());
+        // only the fields in the schema are sent back, metadata is identical on every row
+        let mut projection = doc! { "_id": 0, "datetime": 1 };
+        for field in schema.fields.iter() {
+            projection.insert(field.name.clone(), 1);
+        }
+        let options = FindOptions::builder()
+            .sort(doc! { "datetime": 1 })
+            .projection(projection)
+            .build();
+
+        let cursor = ticker_collection.find(ticker_filter, options).await?;
+        let rows_per_chunk = rows_per_chunk.max(1);
+        let chunks = futures::stream::try_unfold((cursor, schema), move |(mut cursor, schema)| async move {
+            let mut builder = SeriesColumnBuilder::new(&schema, rows_per_chunk.min(MAX_CHUNK_CAPACITY));
+            while builder.len() < rows_per_chunk {
+                match cursor.try_next().await? {
+                    Some(document) => builder.push(&document)?,
+                    None => break,
+                }
             }
+            match builder.len() {
+                0 => Ok(None),
+                _ => Ok(Some((builder.finish()?, (cursor, schema)))),
+            }
+        });
 
-            metadata_collection
-                .update_one(
-                    metadata_filter,
-                    doc! {
-                        "$set": {
-                            "from": min_max_dates.get("min_date").expect("Could not get max_date!"),
-                            "to": min_max_dates.get("max_date").expect("Could not get max_date!"),
-                            "last_updated": get_current_datetime_bson(),
-                        }
-                    },
-                    None,
-                )
-                .await?;
-        }  
-        Ok(true)
+        Ok(chunks.boxed())
     }
 
-    pub async fn read_series(&self, tickers: Vec<MongoTickerParams>) -> Result<Vec<(String, DataFrame)>> {
-        let series_db = self.client.clone().database(&self.db_name);
-        let mut dfs = Vec::new();
+    pub async fn register_schema(&self, schema: &CollectionSchema) -> Result<()> {
+        log::info!("register_schema() registering {} fields for collection {}", schema.fields.len(), &schema.collection_name);
+        let schema_collection = self
+            .client
+            .clone()
+            .database(&self.db_metadata_name)
+            .collection::<CollectionSchema>(SCHEMA_COLLECTION_NAME);
+        let options = ReplaceOptions::builder().upsert(true).build();
+        schema_collection
+            .replace_one(doc! { "collection_name": &schema.collection_name }, schema, options)
+            .await?;
+        Ok(())
+    }
+
+    // collections without a registered schema hold ohlcv series, or quotes if they are live collections
+    pub async fn get_schema(&self, collection_name: &str) -> Result<CollectionSchema> {
+        let schema_collection = self
+            .client
+            .clone()
+            .database(&self.db_metadata_name)
+            .collection::<CollectionSchema>(SCHEMA_COLLECTION_NAME);
+        let schema = schema_collection
+            .find_one(doc! { "collection_name": collection_name }, None)
+            .await?;
+        Ok(schema.unwrap_or_else(|| CollectionSchema::default_for(collection_name)))
+    }
+
+    /*
+        Rewrites the integer volumes written before volume became a double, so a collection never mixes
+        the two, and moves a registered schema's volume field over to float. Returns the rows rewritten.
+        Updating the measurements of a timeseries collection needs MongoDB 7.0 or later.
+    */
+    pub async fn migrate_volume(&self, collection_name: &str) -> Result<u64> {
+        log::info!("migrate_volume() rewriting integer volumes in collection {}", collection_name);
+        let series_collection = self
+            .client
+            .clone()
+            .database(&self.db_name)
+            .collection::<Document>(collection_name);
+        let update = vec![doc! { "$set": { "volume": { "$toDouble": "$volume" } } }];
+        let result = series_collection
+            .update_many(doc! { "volume": { "$type": ["int", "long"] } }, update, None)
+            .await?;
+
+        let mut schema = self.get_schema(collection_name).await?;
+        let mut schema_changed = false;
+        for field in schema.fields.iter_mut() {
+            if field.name == "volume" && field.field_type == FieldType::Integer {
+                field.field_type = FieldType::Float;
+                schema_changed = true;
+            }
+        }
+        if schema_changed {
+            self.register_schema(&schema).await?;
+        }
+
+        log::info!("migrate_volume() rewrote {} rows in collection {}", result.modified_count, collection_name);
+        Ok(result.modified_count)
+    }
+
+    pub async fn find_metadata(&self, ticker: &MongoTickerParams) -> Result<Vec<TimeseriesMetaDataStruct>> {
+        let metadata_collection = self
+            .client
+            .clone()
+            .database(&self.db_metadata_name)
+            .collection::<TimeseriesMetaDataStruct>(&ticker.series_collection_name);
+
+        let metadata_filter = doc! {
+            "ticker": &ticker.ticker,
+            "exchange": &ticker.exchange,
+            "series_collection_name": &ticker.series_collection_name,
+            "source": &ticker.source,
+        };
+
+        let mut series_metadata = metadata_collection
+            .find(metadata_filter, None)
+            .await?;
+
+        let mut metadata_vec = Vec::new();
+        while let Some(result) = series_metadata.try_next().await? {
+            metadata_vec.push(result);
+        }
+
+        Ok(metadata_vec)
+    }
+
+    // looks up the metadata of many tickers with one $or query per collection
+    pub async fn find_metadata_batch(&self, tickers: &[MongoTickerParams]) -> Result<Vec<TimeseriesMetaDataStruct>> {
+        let metadata_db = self.client.clone().database(&self.db_metadata_name);
+        let mut tickers_by_collection: HashMap<&str, Vec<&MongoTickerParams>> = HashMap::new();
         for ticker in tickers.iter() {
-            log::info!("read_series() reading series for ticker {} in collection {}", &ticker.ticker, &ticker.series_collection_name);
-            let ticker_collection = series_db.collection::<Document>(&ticker.series_collection_name);
-            let ticker_filter = doc! {
-                "metadata.ticker": &ticker.ticker,
-                "metadata.exchange": &ticker.exchange,
-                "metadata.metadata_collection_name": &ticker.series_collection_name,
-                "metadata.source": &ticker.source,
-                "datetime": {
-                    "$gte": ticker.from,
-                    "$lte": ticker.to
-                }
-            };
+            tickers_by_collection
+                .entry(ticker.series_collection_name.as_str())
+                .or_default()
+                .push(ticker);
+        }
+
+        let lookups = tickers_by_collection.into_iter().map(|(collection_name, tickers)| {
+            let metadata_collection = metadata_db.collection::<TimeseriesMetaDataStruct>(collection_name);
+            let metadata_filters = tickers
+                .iter()
+                .map(|ticker| doc! {
+                    "ticker": &ticker.ticker,
+                    "exchange": &ticker.exchange,
+                    "series_collection_name": &ticker.series_collection_name,
+                    "source": &ticker.source,
+                })
+                .collect::<Vec<Document>>();
+            async move {
+                let cursor = metadata_collection.find(doc! { "$or": metadata_filters }, None).await?;
+                Ok::<_, anyhow::Error>(cursor.try_collect::<Vec<TimeseriesMetaDataStruct>>().await?)
+            }
+        });
+        let metadata = futures::future::try_join_all(lookups).await?;
 
-            let options = FindOptions::builder()
-                .sort(doc! { "datetime": 1 })
-                .build();
+        Ok(metadata.into_iter().flatten().collect())
+    }
 
-            let mut cursor = ticker_collection.find(ticker_filter, options)
-                .await?;
+    // creates or incrementally refreshes the series for each ticker without reading them back
+    pub async fn ingest(&self, tickers: &[MongoTickerParams]) -> Result<IngestReport> {
+        let ingest_start = Instant::now();
+        let mut report = IngestReport::new();
 
-            let mut ohlcv_vec = Vec::new();
-            while let Some(result) = cursor.next().await {
-                match result {
-                    Ok(document) => {
-                        let ohlcv_row: ReadSeriesFromMongoDb = bson::from_bson(Bson::Document(document)).unwrap();
-                        ohlcv_vec.push(ohlcv_row);
+        // ensure collection exits
+        let ensure_collection_exists = self.ensure_series_collection_exists(tickers).await?;
+        assert!(ensure_collection_exists, "ensure_series_collection_exists() failed!");
+
+        // segragate tickers into new and existing
+        let phase_start = Instant::now();
+        let mut new_tickers = Vec::new();
+        let mut existing_tickers = Vec::new();
+        let mut known_metadata = Vec::new();
+        let metadata_batch = self.find_metadata_batch(tickers).await;
+        for ticker in tickers.iter() {
+            let mut ticker_report = TickerReport::new(ticker);
+            let ticker_metadata = match &metadata_batch {
+                Ok(metadata_batch) => Ok(metadata_batch
+                    .iter()
+                    .filter(|metadata| {
+                        metadata.ticker == ticker.ticker
+                            && metadata.exchange == ticker.exchange
+                            && metadata.series_collection_name == ticker.series_collection_name
+                            && metadata.source == ticker.source
+                    })
+                    .cloned()
+                    .collect::<Vec<TimeseriesMetaDataStruct>>()),
+                Err(e) => Err(anyhow!("ingest() batched metadata lookup failed: {:#}", e)),
+            };
+            match ticker_metadata {
+                Ok(mut metadata_vec) => match metadata_vec.len() {
+                    0 => match self.insert_metadata(ticker).await {
+                        Ok(inserted_metadata) => {
+                            ticker_report.action = IngestAction::Created;
+                            known_metadata.push(inserted_metadata.clone());
+                            let ticker_param_updated = MongoTickerParams {
+                                ticker: inserted_metadata.ticker,
+                                exchange: inserted_metadata.exchange,
+                                series_collection_name: inserted_metadata.series_collection_name,
+                                source: inserted_metadata.source,
+                                from: inserted_metadata.from,
+                                to: inserted_metadata.to,
+                            };
+                            new_tickers.push(ticker_param_updated);
+                        }
+                        Err(e) => ticker_report.fail(&e),
+                    },
+                    1 => {
+                        let metadata = metadata_vec.pop().expect("Could not pop metadata!");
+                        ticker_report.coverage_before = Some(Coverage::from(&metadata));
+                        known_metadata.push(metadata.clone());
+                        let new_from = metadata.to;
+                        let current_date = get_current_datetime_bson();
+                        if needs_refresh(&metadata.series_collection_name, new_from, current_date) {
+                            ticker_report.action = IngestAction::Refreshed;
+                            let ticker_param_updated = MongoTickerParams {
+                                ticker: metadata.ticker,
+                                exchange: metadata.exchange,
+                                series_collection_name: metadata.series_collection_name,
+                                source: metadata.source,
+                                from: new_from,
+                                to: current_date,
+                            };
+                            existing_tickers.push(ticker_param_updated);
+                        }
+                    }
+                    _ => ticker_report.fail(&anyhow!(
+                        "ingest() found more than one metadata document for ticker: {}, exchange: {}, source: {}",
+                        ticker.ticker, ticker.exchange, ticker.source
+                    )),
+                },
+                Err(e) => ticker_report.fail(&e),
+            }
+            report.tickers.push(ticker_report);
+        }
+        report.timings.metadata_lookup = phase_start.elapsed();
+
+        // get series rows for new and old tickers. A failed batch fails every ticker in it, a failed fetch its ticker
+        let phase_start = Instant::now();
+        let mut dfs_fetched = Vec::new();
+        // refreshes start from the last stored row, which comes back from the datasource and is not inserted again
+        for (batch, refreshing) in [(new_tickers, false), (existing_tickers, true)] {
+            match self.get_data_from_apis(batch.clone()).await {
+                Ok(dfs) => {
+                    for (ticker, df) in dfs.into_iter() {
+                        match df.and_then(|df| Ok((df, series_source_timezone(&known_metadata, &ticker)?))) {
+                            Ok((df, source_timezone)) => {
+                                let after = refreshing.then_some(ticker.from);
+                                dfs_fetched.push((ticker, df, after, source_timezone));
+                            }
+                            Err(e) => {
+                                if let Some(ticker_report) = report.ticker_mut(&ticker.ticker, &ticker.exchange, &ticker.series_collection_name, &ticker.source) {
+                                    ticker_report.fail(&e);
+                                }
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    for ticker in batch.iter() {
+                        if let Some(ticker_report) = report.ticker_mut(&ticker.ticker, &ticker.exchange, &ticker.series_collection_name, &ticker.source) {
+                            ticker_report.fail(&e);
+                        }
                     }
-                    Err(e) => println!("Error: {}", e),
                 }
             }
+        }
+        report.timings.fetch = phase_start.elapsed();
 
-            let datetime: Series = Series::new("datetime", ohlcv_vec.iter().map(|s| {
-                let datetime = DateTime::<Utc>::from(s.datetime).to_rfc3339();
-                NaiveDateTime::parse_from_str(&datetime, "%Y-%m-%dT%H:%M:%S%:z").unwrap()
-            }).collect::<Vec<_>>());
-            let open: Series = Series::new("open", ohlcv_vec.iter().map(|s| s.open).collect::<Vec<_>>());
-            let high: Series = Series::new("high", ohlcv_vec.iter().map(|s| s.high).collect::<Vec<_>>());
-            let low: Series = Series::new("low", ohlcv_vec.iter().map(|s| s.low).collect::<Vec<_>>());
-            let close: Series = Series::new("close", ohlcv_vec.iter().map(|s| s.close).collect::<Vec<_>>());
-            let adjusted_close: Series = Series::new("adjusted_close", ohlcv_vec.iter().map(|s| s.adjusted_close).collect::<Vec<_>>());
-            let volume: Series = Series::new("volume", ohlcv_vec.iter().map(|s| s.volume).collect::<Vec<_>>());
+        // insert series rows to db
+        let phase_start = Instant::now();
+        // futures are collected before buffering so the ingest future stays Send
+        let inserts = dfs_fetched
+            .iter()
+            .map(|(ticker, df, after, source_timezone)| async move {
+                (ticker, df.height(), self.insert_series_df_after(df, *after, *source_timezone).await)
+            })
+            .collect::<Vec<_>>();
+        let inserted_dfs = futures::stream::iter(inserts)
+            .buffer_unordered(self.concurrency)
+            .collect::<Vec<_>>()
+            .await;
+        for (ticker, rows_fetched, inserted) in inserted_dfs.into_iter() {
+            if let Some(ticker_report) = report.ticker_mut(&ticker.ticker, &ticker.exchange, &ticker.series_collection_name, &ticker.source) {
+                ticker_report.record_insert(rows_fetched, inserted);
+            }
+        }
+        report.timings.insert = phase_start.elapsed();
 
-            let df = DataFrame::new(vec![datetime, open, high, low, close, volume, adjusted_close]).unwrap();
-            // let ticker_collection_name = format!("{}_{}_{}", ticker.ticker, ticker.source, ticker.series_collection_name);
-            dfs.push((ticker.ticker.clone(), df));
+        // update metadata dates for every ticker that made it this far
+        let phase_start = Instant::now();
+        let healthy_tickers = tickers
+            .iter()
+            .filter(|ticker| {
+                report.tickers.iter().any(|ticker_report| {
+                    ticker_report.is_for(&ticker.ticker, &ticker.exchange, &ticker.series_collection_name, &ticker.source)
+                        && ticker_report.action != IngestAction::Failed
+                })
+            })
+            .collect::<Vec<&MongoTickerParams>>();
+        let updates = healthy_tickers
+            .into_iter()
+            .map(|ticker| async move {
+                let updated = match self.update_ticker_metadata_dates(ticker).await {
+                    Ok(_) => self.find_metadata(ticker).await,
+                    Err(e) => Err(e),
+                };
+                (ticker, updated)
+            })
+            .collect::<Vec<_>>();
+        let updated_tickers = futures::stream::iter(updates)
+            .buffer_unordered(self.concurrency)
+            .collect::<Vec<_>>()
+            .await;
+        for (ticker, updated) in updated_tickers.into_iter() {
+            let ticker_report = report
+                .ticker_mut(&ticker.ticker, &ticker.exchange, &ticker.series_collection_name, &ticker.source)
+                .expect("ingest() lost report for ticker!");
+            match updated {
+                Ok(metadata_vec) => ticker_report.coverage_after = metadata_vec.first().map(Coverage::from),
+                Err(e) => ticker_report.fail(&e),
+            }
         }
+        report.timings.metadata_update = phase_start.elapsed();
 
-        Ok(dfs)
+        report.timings.total = ingest_start.elapsed();
+        report.finished_at = get_current_datetime_bson();
+        for failed in report.failed() {
+            log::error!("ingest() failed for ticker {} in collection {}: {:?}", failed.ticker, failed.series_collection_name, failed.errors);
+        }
+
+        Ok(report)
     }
 
-    
-    pub async fn run(&self, tickers: Vec<(&str, &str, &str, &str, &str, &str)>) -> Result<Vec<(String, DataFrame)>> {
+    pub async fn run(&self, tickers: Vec<(&str, &str, &str, &str, &str, &str)>) -> Result<(Vec<(String, DataFrame)>, IngestReport)> {
+        let run_start = Instant::now();
+
         // convert str to mongodb params
         let tickers = tickers.into_iter()
-            .map(|(ticker, exchange, collection_name,source, from, to)| MongoTickerParams {
+            .map(|(ticker, exchange, collection_name,source, from, to)| Ok(MongoTickerParams {
                 ticker: ticker.to_string(),
                 exchange: exchange.to_string(),
                 series_collection_name: collection_name.to_string(),
                 source: source.to_string(),
-                from: string_to_datetime(from),
-                to: string_to_datetime(to),
+                from: parse_datetime(from)?,
+                to: parse_datetime(to)?,
+            }))
+            .collect::<Result<Vec<MongoTickerParams>>>()?;
+
+        let mut report = self.ingest(&tickers).await?;
+
+        // read series based on dates provided, skipping tickers that failed to ingest
+        let phase_start = Instant::now();
+        let healthy_tickers = tickers
+            .into_iter()
+            .filter(|ticker| {
+                report.tickers.iter().any(|ticker_report| {
+                    ticker_report.is_for(&ticker.ticker, &ticker.exchange, &ticker.series_collection_name, &ticker.source)
+                        && ticker_report.action != IngestAction::Failed
+                })
             })
             .collect::<Vec<MongoTickerParams>>();
+        let dfs = self.read_series(healthy_tickers).await?;
+        report.timings.read = phase_start.elapsed();
+        report.timings.total = run_start.elapsed();
 
-        // ensure collection exits
-        let ensure_collection_exists = self.ensure_series_collection_exists(&tickers).await?;
-        assert!(ensure_collection_exists, "ensure_series_collection_exists() failed!");
+        Ok((dfs, report))
+    }
 
-        // segragate tickers into new and existing
-        let mut new_tickers = Vec::new();
-        let mut existing_tickers = Vec::new();
+    // creates the collections and metadata documents of tickers that are written to outside of ingest()
+    pub async fn ensure_metadata_exists(&self, tickers: &[MongoTickerParams]) -> Result<()> {
+        self.ensure_series_collection_exists(tickers).await?;
+        let metadata_batch = self.find_metadata_batch(tickers).await?;
         for ticker in tickers.iter() {
-            let metadata_collection = self
+            let registered = metadata_batch.iter().any(|metadata| {
+                metadata.ticker == ticker.ticker
+                    && metadata.exchange == ticker.exchange
+                    && metadata.series_collection_name == ticker.series_collection_name
+                    && metadata.source == ticker.source
+            });
+            if !registered {
+                self.insert_metadata(ticker).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // fetches the current quote of each ticker and appends it to its live collection. Returns the quotes stored
+    pub async fn poll_quotes(&self, tickers: &[MongoTickerParams]) -> Result<usize> {
+        if let Some(ticker) = tickers.iter().find(|ticker| ticker.source != "eod") {
+            return Err(anyhow!("poll_quotes() datasource {} has no live quotes", ticker.source));
+        }
+        self.ensure_metadata_exists(tickers).await?;
+
+        let quotes = match self.eod_api.as_ref() {
+            Some(eod_api) => eod_api.get_quotes(tickers).await?,
+            None => return Err(anyhow!("poll_quotes() EOD API token is not configured!")),
+        };
+
+        // outside market hours the source keeps sending the last quote, which is only stored once
+        let latest_quotes = self.latest_quotes(tickers).await?;
+        let mut quotes_by_collection: HashMap<String, Vec<Quote>> = HashMap::new();
+        for quote in quotes.into_iter() {
+            let is_stored = latest_quotes.iter().any(|latest| {
+                latest.metadata.ticker == quote.metadata.ticker
+                    && latest.metadata.exchange == quote.metadata.exchange
+                    && latest.metadata.metadata_collection_name == quote.metadata.metadata_collection_name
+                    && latest.datetime == quote.datetime
+            });
+            if !is_stored {
+                quotes_by_collection
+                    .entry(quote.metadata.metadata_collection_name.clone())
+                    .or_default()
+                    .push(quote);
+            }
+        }
+
+        let mut quotes_stored = 0;
+        let mut updated_tickers = Vec::new();
+        for (collection_name, quotes) in quotes_by_collection.into_iter() {
+            let collection = self
+                .client
+                .clone()
+                .database(&self.db_name)
+                .collection::<Quote>(&collection_name);
+            collection.insert_many(&quotes, None).await?;
+            quotes_stored += quotes.len();
+            updated_tickers.extend(tickers.iter().filter(|ticker| {
+                quotes.iter().any(|quote| {
+                    quote.metadata.ticker == ticker.ticker
+                        && quote.metadata.exchange == ticker.exchange
+                        && quote.metadata.metadata_collection_name == ticker.series_collection_name
+                })
+            }).cloned());
+        }
+        self.update_metadata_dates(&updated_tickers).await?;
+
+        log::info!("poll_quotes() stored {} new quotes for {} tickers", quotes_stored, tickers.len());
+        Ok(quotes_stored)
+    }
+
+    // most recent quote stored for each ticker, tickers without any quote are left out
+    pub async fn latest_quotes(&self, tickers: &[MongoTickerParams]) -> Result<Vec<Quote>> {
+        let lookups = tickers.iter().map(|ticker| {
+            let collection = self
                 .client
                 .clone()
-                .database(&self.db_metadata_name)
-                .collection::<TimeseriesMetaDataStruct>(&ticker.series_collection_name);
-
-            let metadata_filter = doc! {
-                "ticker": &ticker.ticker,
-                "exchange": &ticker.exchange,
-                "series_collection_name": &ticker.series_collection_name,
-                "source": &ticker.source,
+                .database(&self.db_name)
+                .collection::<Quote>(&ticker.series_collection_name);
+            let filter = doc! {
+                "metadata.ticker": &ticker.ticker,
+                "metadata.exchange": &ticker.exchange,
+                "metadata.metadata_collection_name": &ticker.series_collection_name,
+                "metadata.source": &ticker.source,
             };
+            let options = FindOneOptions::builder().sort(doc! { "datetime": -1 }).build();
+            async move { Ok::<_, anyhow::Error>(collection.find_one(filter, options).await?) }
+        }).collect::<Vec<_>>();
 
-            let mut series_metadata = metadata_collection
-                .find(metadata_filter.clone(), None)
-                .await?;
+        let quotes = futures::stream::iter(lookups)
+            .buffered(self.concurrency)
+            .try_collect::<Vec<Option<Quote>>>()
+            .await?;
+        Ok(quotes.into_iter().flatten().collect())
+    }
 
-            let mut metadata_vec = Vec::new();
-            while let Some(result) = series_metadata.try_next().await? {
-                metadata_vec.push(result);
+    pub async fn insert_trade_ticks(&self, ticks: &[TradeTick]) -> Result<usize> {
+        self.insert_records(ticks, |tick| (&tick.metadata, tick.datetime)).await
+    }
+
+    pub async fn insert_quote_ticks(&self, ticks: &[QuoteTick]) -> Result<usize> {
+        self.insert_records(ticks, |tick| (&tick.metadata, tick.datetime)).await
+    }
+
+    // appends records to the collections named in their metadata, registering tickers that are new. Returns the records stored
+    async fn insert_records<T>(&self, records: &[T], key: impl Fn(&T) -> (&OhlcvMetaData, bson::DateTime)) -> Result<usize>
+    where
+        T: serde::Serialize + Send + Sync,
+    {
+        let mut records_by_collection: HashMap<String, Vec<&T>> = HashMap::new();
+        let mut tickers: Vec<MongoTickerParams> = Vec::new();
+        for record in records.iter() {
+            let (metadata, datetime) = key(record);
+            records_by_collection
+                .entry(metadata.metadata_collection_name.clone())
+                .or_default()
+                .push(record);
+            let existing = tickers.iter_mut().find(|ticker| {
+                ticker.ticker == metadata.ticker
+                    && ticker.exchange == metadata.exchange
+                    && ticker.series_collection_name == metadata.metadata_collection_name
+                    && ticker.source == metadata.source
+            });
+            match existing {
+                Some(ticker) => {
+                    ticker.from = ticker.from.min(datetime);
+                    ticker.to = ticker.to.max(datetime);
+                }
+                None => tickers.push(MongoTickerParams {
+                    ticker: metadata.ticker.clone(),
+                    exchange: metadata.exchange.clone(),
+                    series_collection_name: metadata.metadata_collection_name.clone(),
+                    source: metadata.source.clone(),
+                    from: datetime,
+                    to: datetime,
+                }),
             }
+        }
+        self.ensure_metadata_exists(&tickers).await?;
 
-            match metadata_vec.len() {
-                0 => {
-                    let inserted_metadata = self.insert_metadata(ticker).await?;
-                    let ticker_param_updated = MongoTickerParams {
-                        ticker: inserted_metadata.ticker,
-                        exchange: inserted_metadata.exchange,
-                        series_collection_name: inserted_metadata.series_collection_name,
-                        source: inserted_metadata.source,
-                        from: inserted_metadata.from,
-                        to: inserted_metadata.to,
-                    };
-                    new_tickers.push(ticker_param_updated);
+        let mut records_stored = 0;
+        for (collection_name, records) in records_by_collection.into_iter() {
+            let collection = self
+                .client
+                .clone()
+                .database(&self.db_name)
+                .collection::<&T>(&collection_name);
+            let result = collection.insert_many(&records, None).await?;
+            records_stored += result.inserted_ids.len();
+        }
+        self.update_metadata_dates(&tickers).await?;
+
+        log::info!("insert_records() stored {} records for {} tickers", records_stored, tickers.len());
+        Ok(records_stored)
+    }
+
+    pub async fn insert_book_snapshots(&self, snapshots: &[OrderBookSnapshot]) -> Result<usize> {
+        for snapshot in snapshots.iter() {
+            snapshot.validate()?;
+        }
+        self.insert_records(snapshots, |snapshot| (&snapshot.metadata, snapshot.datetime)).await
+    }
+
+    // the last snapshot of a ticker at or before a time, None if the book was never snapshotted before then
+    pub async fn read_book_at(&self, ticker: &MongoTickerParams, at: bson::DateTime) -> Result<Option<OrderBookSnapshot>> {
+        let collection = self
+            .client
+            .clone()
+            .database(&self.db_name)
+            .collection::<OrderBookSnapshot>(&ticker.series_collection_name);
+        let filter = doc! {
+            "metadata.ticker": &ticker.ticker,
+            "metadata.exchange": &ticker.exchange,
+            "metadata.metadata_collection_name": &ticker.series_collection_name,
+            "metadata.source": &ticker.source,
+            "datetime": { "$lte": at },
+        };
+        let options = FindOneOptions::builder().sort(doc! { "datetime": -1 }).build();
+        Ok(collection.find_one(filter, options).await?)
+    }
+
+    // mid, spread and depth imbalance of every snapshot between from and to, see order_book::book_series()
+    pub async fn read_book_series(&self, ticker: MongoTickerParams, depth: usize) -> Result<(String, DataFrame)> {
+        log::info!("read_book_series() reading snapshots for ticker {} in collection {}", &ticker.ticker, &ticker.series_collection_name);
+        let collection = self
+            .client
+            .clone()
+            .database(&self.db_name)
+            .collection::<OrderBookSnapshot>(&ticker.series_collection_name);
+        let filter = doc! {
+            "metadata.ticker": &ticker.ticker,
+            "metadata.exchange": &ticker.exchange,
+            "metadata.metadata_collection_name": &ticker.series_collection_name,
+            "metadata.source": &ticker.source,
+            "datetime": {
+                "$gte": ticker.from,
+                "$lte": ticker.to
+            }
+        };
+        // only the levels the series are computed from are sent back
+        let depth = depth.max(1) as i64;
+        let projection = doc! {
+            "bid_prices": { "$slice": depth },
+            "bid_sizes": { "$slice": depth },
+            "ask_prices": { "$slice": depth },
+            "ask_sizes": { "$slice": depth },
+        };
+        let options = FindOptions::builder()
+            .sort(doc! { "datetime": 1 })
+            .projection(projection)
+            .build();
+        let snapshots = collection
+            .find(filter, options)
+            .await?
+            .try_collect::<Vec<OrderBookSnapshot>>()
+            .await?;
+        Ok((ticker.ticker, book_series(&snapshots, depth as usize)?))
+    }
+
+    pub async fn insert_option_quotes(&self, quotes: &[OptionQuote]) -> Result<usize> {
+        self.insert_records(quotes, |quote| (&quote.metadata, quote.datetime)).await
+    }
+
+    // the chain of an underlying on the day of date, sorted by expiry, strike and right
+    pub async fn read_option_chain(
+        &self,
+        ticker: &MongoTickerParams,
+        date: bson::DateTime,
+        filter: &ChainFilter,
+    ) -> Result<Vec<OptionQuote>> {
+        log::info!("read_option_chain() reading chain for ticker {} in collection {}", &ticker.ticker, &ticker.series_collection_name);
+        let collection = self
+            .client
+            .clone()
+            .database(&self.db_name)
+            .collection::<OptionQuote>(&ticker.series_collection_name);
+        let mut chain_filter = doc! {
+            "metadata.ticker": &ticker.ticker,
+            "metadata.exchange": &ticker.exchange,
+            "metadata.metadata_collection_name": &ticker.series_collection_name,
+            "metadata.source": &ticker.source,
+        };
+        chain_filter.extend(chain_filter_document(date, filter));
+        let options = FindOptions::builder()
+            .sort(doc! { "contract.expiry": 1, "contract.strike": 1, "contract.right": 1 })
+            .build();
+        let quotes = collection
+            .find(chain_filter, options)
+            .await?
+            .try_collect::<Vec<OptionQuote>>()
+            .await?;
+        Ok(quotes)
+    }
+
+    pub async fn register_futures_contract(&self, spec: &FuturesContractSpec) -> Result<()> {
+        log::info!("register_futures_contract() registering contract {} of root {}", &spec.ticker, &spec.root);
+        let contracts_collection = self
+            .client
+            .clone()
+            .database(&self.db_metadata_name)
+            .collection::<FuturesContractSpec>(FUTURES_CONTRACTS_COLLECTION_NAME);
+        let filter = doc! {
+            "root": &spec.root,
+            "exchange": &spec.exchange,
+            "series_collection_name": &spec.series_collection_name,
+            "source": &spec.source,
+            "expiry": spec.expiry,
+        };
+        let options = ReplaceOptions::builder().upsert(true).build();
+        contracts_collection.replace_one(filter, spec, options).await?;
+        Ok(())
+    }
+
+    // the contracts registered for the root in ticker.ticker, in expiry order
+    pub async fn list_futures_contracts(&self, ticker: &MongoTickerParams) -> Result<Vec<FuturesContractSpec>> {
+        let contracts_collection = self
+            .client
+            .clone()
+            .database(&self.db_metadata_name)
+            .collection::<FuturesContractSpec>(FUTURES_CONTRACTS_COLLECTION_NAME);
+        let filter = doc! {
+            "root": &ticker.ticker,
+            "exchange": &ticker.exchange,
+            "series_collection_name": &ticker.series_collection_name,
+            "source": &ticker.source,
+        };
+        let options = FindOptions::builder().sort(doc! { "expiry": 1 }).build();
+        let contracts = contracts_collection
+            .find(filter, options)
+            .await?
+            .try_collect::<Vec<FuturesContractSpec>>()
+            .await?;
+        Ok(contracts)
+    }
+
+    // continuous series of the root in ticker.ticker between from and to, built from its contracts still trading by from
+    pub async fn read_continuous_series(
+        &self,
+        ticker: MongoTickerParams,
+        builder: &ContinuousContractBuilder,
+    ) -> Result<(String, DataFrame)> {
+        let contracts = self
+            .list_futures_contracts(&ticker)
+            .await?
+            .into_iter()
+            .filter(|contract| contract.last_trade >= ticker.from)
+            .collect::<Vec<FuturesContractSpec>>();
+        if contracts.is_empty() {
+            return Err(anyhow!(
+                "read_continuous_series() no contracts of {} in {} trade after {}",
+                &ticker.ticker,
+                &ticker.series_collection_name,
+                ticker.from
+            ));
+        }
+        let contract_tickers = contracts
+            .iter()
+            .map(|contract| MongoTickerParams {
+                ticker: contract.ticker.clone(),
+                ..ticker.clone()
+            })
+            .collect::<Vec<MongoTickerParams>>();
+        let series = self.read_series(contract_tickers).await?;
+        let contract_series = contracts
+            .into_iter()
+            .zip(series)
+            .map(|(contract, (_, df))| (contract, df))
+            .collect::<Vec<(FuturesContractSpec, DataFrame)>>();
+        Ok((ticker.ticker, builder.build(contract_series)?))
+    }
+
+    pub async fn insert_curve_points(&self, points: &[CurvePoint]) -> Result<usize> {
+        self.insert_records(points, |point| (&point.metadata, point.datetime)).await
+    }
+
+    // the curve in ticker.ticker between from and to as a date x tenor matrix, see yield_curve::curve_matrix()
+    pub async fn read_curve(&self, ticker: MongoTickerParams) -> Result<(String, DataFrame)> {
+        log::info!("read_curve() reading curve {} in collection {}", &ticker.ticker, &ticker.series_collection_name);
+        let collection = self
+            .client
+            .clone()
+            .database(&self.db_name)
+            .collection::<CurvePoint>(&ticker.series_collection_name);
+        let filter = doc! {
+            "metadata.ticker": &ticker.ticker,
+            "metadata.exchange": &ticker.exchange,
+            "metadata.metadata_collection_name": &ticker.series_collection_name,
+            "metadata.source": &ticker.source,
+            "datetime": {
+                "$gte": ticker.from,
+                "$lte": ticker.to
+            }
+        };
+        let points = collection
+            .find(filter, None)
+            .await?
+            .try_collect::<Vec<CurvePoint>>()
+            .await?;
+        Ok((ticker.ticker, curve_matrix(&points)?))
+    }
+
+    /*
+        Fetches the macro indicators in tickers from the MacroSource registered for their source and stores
+        their new releases and revisions as vintages, see macro_series::next_vintages(). The ticker is the
+        indicator and the exchange the country. Returns the vintages stored.
+    */
+    pub async fn ingest_macro(&self, tickers: &[MongoTickerParams]) -> Result<usize> {
+        let mut vintages_stored = 0;
+        for ticker in tickers.iter() {
+            let fetched = match self.macro_sources.get(&ticker.source) {
+                Some(macro_source) => macro_source.get_macro_indicator(ticker).await?,
+                None if ticker.source == "eod" => return Err(anyhow!("ingest_macro() EOD API token is not configured!")),
+                None => {
+                    log::error!("ingest_macro() datasource {} has no macro indicators", &ticker.source);
+                    continue;
+                }
+            };
+            let metadata = OhlcvMetaData {
+                metadata_collection_name: ticker.series_collection_name.clone(),
+                ticker: ticker.ticker.clone(),
+                source: ticker.source.clone(),
+                exchange: ticker.exchange.clone(),
+                currency: None,
+            };
+            let stored = self.find_macro_observations(ticker, None).await?;
+            let vintages = next_vintages(&stored, &fetched, &metadata, get_current_datetime_bson());
+            log::info!("ingest_macro() {} new vintages of {} for {}", vintages.len(), &ticker.ticker, &ticker.exchange);
+            if !vintages.is_empty() {
+                vintages_stored += self.insert_records(&vintages, |vintage| (&vintage.metadata, vintage.datetime)).await?;
+            }
+        }
+        Ok(vintages_stored)
+    }
+
+    // every vintage of an indicator, only those of reference periods between from and to if given
+    async fn find_macro_observations(
+        &self,
+        ticker: &MongoTickerParams,
+        reference_periods: Option<(bson::DateTime, bson::DateTime)>,
+    ) -> Result<Vec<MacroObservation>> {
+        let collection = self
+            .client
+            .clone()
+            .database(&self.db_name)
+            .collection::<MacroObservation>(&ticker.series_collection_name);
+        let mut filter = doc! {
+            "metadata.ticker": &ticker.ticker,
+            "metadata.exchange": &ticker.exchange,
+            "metadata.metadata_collection_name": &ticker.series_collection_name,
+            "metadata.source": &ticker.source,
+        };
+        if let Some((from, to)) = reference_periods {
+            filter.insert("reference_period", doc! { "$gte": from, "$lte": to });
+        }
+        let observations = collection
+            .find(filter, None)
+            .await?
+            .try_collect::<Vec<MacroObservation>>()
+            .await?;
+        Ok(observations)
+    }
+
+    // the indicator's reference periods between from and to as known at as_of, the latest vintages if None
+    pub async fn read_macro_series(
+        &self,
+        ticker: MongoTickerParams,
+        as_of: Option<bson::DateTime>,
+    ) -> Result<(String, DataFrame)> {
+        log::info!("read_macro_series() reading {} for {}", &ticker.ticker, &ticker.exchange);
+        let observations = self.find_macro_observations(&ticker, Some((ticker.from, ticker.to))).await?;
+        Ok((ticker.ticker, point_in_time(&observations, as_of)?))
+    }
+
+    // every vintage of the indicator's reference periods between from and to
+    pub async fn read_macro_vintages(&self, ticker: MongoTickerParams) -> Result<(String, DataFrame)> {
+        let observations = self.find_macro_observations(&ticker, Some((ticker.from, ticker.to))).await?;
+        Ok((ticker.ticker, vintages(observations.iter())?))
+    }
+
+    /*
+        Fetches the earnings announcements and splits of tickers between their from and to and stores them
+        in the events database, see corporate_events::merge_earnings() for how announcement dates already
+        stored are kept. Events are keyed by the ticker, exchange and source of the instrument, so tickers
+        of several collections for the same instrument are fetched once. Returns the events inserted or
+        changed, events fetched again unchanged keep their last_updated and are not counted.
+    */
+    pub async fn ingest_events(&self, tickers: &[MongoTickerParams]) -> Result<usize> {
+        let mut instruments: Vec<MongoTickerParams> = Vec::new();
+        for ticker in tickers.iter() {
+            let seen = instruments.iter_mut().find(|instrument| {
+                instrument.ticker == ticker.ticker && instrument.exchange == ticker.exchange && instrument.source == ticker.source
+            });
+            match seen {
+                Some(instrument) => {
+                    instrument.from = instrument.from.min(ticker.from);
+                    instrument.to = instrument.to.max(ticker.to);
+                }
+                None => instruments.push(ticker.clone()),
+            }
+        }
+
+        let mut events_stored = 0;
+        for instrument in instruments.iter() {
+            let eod_api = match (instrument.source.as_str(), self.eod_api.as_ref()) {
+                ("eod", Some(eod_api)) => eod_api,
+                ("eod", None) => return Err(anyhow!("ingest_events() EOD API token is not configured!")),
+                (source, _) => {
+                    log::error!("ingest_events() datasource {} has no corporate events", source);
+                    continue;
+                }
+            };
+            let fetched = eod_api.get_earnings(std::slice::from_ref(instrument), instrument.from, instrument.to).await?;
+            let stored = self.list_earnings(instrument).await?;
+            let fetched_at = get_current_datetime_bson();
+            let earnings = self
+                .client
+                .clone()
+                .database(&self.db_events_name)
+                .collection::<EarningsEvent>(EARNINGS_COLLECTION_NAME);
+            for event in fetched.into_iter() {
+                let stored_event = stored.iter().find(|stored_event| stored_event.fiscal_period_end == event.fiscal_period_end);
+                let event = merge_earnings(stored_event, event, fetched_at);
+                let filter = doc! {
+                    "ticker": &event.ticker,
+                    "exchange": &event.exchange,
+                    "source": &event.source,
+                    "fiscal_period_end": event.fiscal_period_end,
+                };
+                let options = ReplaceOptions::builder().upsert(true).build();
+                let result = earnings.replace_one(filter, &event, options).await?;
+                if result.upserted_id.is_some() || result.modified_count > 0 {
+                    events_stored += 1;
+                }
+            }
+
+            let splits = self
+                .client
+                .clone()
+                .database(&self.db_events_name)
+                .collection::<SplitEvent>(SPLITS_COLLECTION_NAME);
+            let filter = doc! { "ticker": &instrument.ticker, "exchange": &instrument.exchange, "source": &instrument.source };
+            let stored = splits.find(filter, None).await?.try_collect::<Vec<SplitEvent>>().await?;
+            for mut event in eod_api.get_splits(instrument, instrument.from, instrument.to).await?.into_iter() {
+                let unchanged = stored.iter().find(|stored_event| {
+                    stored_event.split_date == event.split_date
+                        && (SplitEvent { last_updated: event.last_updated, ..(*stored_event).clone() }) == event
+                });
+                if let Some(stored_event) = unchanged {
+                    event.last_updated = stored_event.last_updated;
+                }
+                let filter = doc! {
+                    "ticker": &event.ticker,
+                    "exchange": &event.exchange,
+                    "source": &event.source,
+                    "split_date": event.split_date,
+                };
+                let options = ReplaceOptions::builder().upsert(true).build();
+                let result = splits.replace_one(filter, &event, options).await?;
+                if result.upserted_id.is_some() || result.modified_count > 0 {
+                    events_stored += 1;
+                }
+            }
+            log::info!("ingest_events() stored events of {}.{}", &instrument.ticker, &instrument.exchange);
+        }
+        Ok(events_stored)
+    }
+
+    // fetches the ipos of every exchange between from and to from source and stores them, returns the ipos stored
+    pub async fn ingest_ipos(&self, source: &str, from: bson::DateTime, to: bson::DateTime) -> Result<usize> {
+        let fetched = match (source, self.eod_api.as_ref()) {
+            ("eod", Some(eod_api)) => eod_api.get_ipos(from, to).await?,
+            ("eod", None) => return Err(anyhow!("ingest_ipos() EOD API token is not configured!")),
+            (source, _) => return Err(anyhow!("ingest_ipos() datasource {} has no ipos", source)),
+        };
+        let ipos = self
+            .client
+            .clone()
+            .database(&self.db_events_name)
+            .collection::<IpoEvent>(IPOS_COLLECTION_NAME);
+        for event in fetched.iter() {
+            let filter = doc! { "ticker": &event.ticker, "exchange": &event.exchange, "source": &event.source };
+            let options = ReplaceOptions::builder().upsert(true).build();
+            ipos.replace_one(filter, event, options).await?;
+        }
+        log::info!("ingest_ipos() stored {} ipos", fetched.len());
+        Ok(fetched.len())
+    }
+
+    // the earnings events of the instrument of ticker, ticker.from and ticker.to are not applied
+    pub async fn list_earnings(&self, ticker: &MongoTickerParams) -> Result<Vec<EarningsEvent>> {
+        let earnings = self
+            .client
+            .clone()
+            .database(&self.db_events_name)
+            .collection::<EarningsEvent>(EARNINGS_COLLECTION_NAME);
+        let filter = doc! { "ticker": &ticker.ticker, "exchange": &ticker.exchange, "source": &ticker.source };
+        let options = FindOptions::builder().sort(doc! { "announcement_date": 1 }).build();
+        let events = earnings.find(filter, options).await?.try_collect::<Vec<EarningsEvent>>().await?;
+        Ok(events)
+    }
+
+    // earnings of the instrument of ticker announced between from and to, see corporate_events::earnings_df()
+    pub async fn read_earnings(&self, ticker: MongoTickerParams) -> Result<(String, DataFrame)> {
+        let events = self
+            .list_earnings(&ticker)
+            .await?
+            .into_iter()
+            .filter(|event| event.announcement_date >= ticker.from && event.announcement_date <= ticker.to)
+            .collect::<Vec<EarningsEvent>>();
+        Ok((ticker.ticker, earnings_df(&events)?))
+    }
+
+    // splits of the instrument of ticker between from and to, see corporate_events::splits_df()
+    pub async fn read_splits(&self, ticker: MongoTickerParams) -> Result<(String, DataFrame)> {
+        let splits = self
+            .client
+            .clone()
+            .database(&self.db_events_name)
+            .collection::<SplitEvent>(SPLITS_COLLECTION_NAME);
+        let filter = doc! {
+            "ticker": &ticker.ticker,
+            "exchange": &ticker.exchange,
+            "source": &ticker.source,
+            "split_date": { "$gte": ticker.from, "$lte": ticker.to },
+        };
+        let events = splits.find(filter, None).await?.try_collect::<Vec<SplitEvent>>().await?;
+        Ok((ticker.ticker, splits_df(&events)?))
+    }
+
+    // the ipo the instrument of ticker listed with, if one is stored
+    pub async fn find_ipo(&self, ticker: &MongoTickerParams) -> Result<Option<IpoEvent>> {
+        let ipos = self
+            .client
+            .clone()
+            .database(&self.db_events_name)
+            .collection::<IpoEvent>(IPOS_COLLECTION_NAME);
+        let filter = doc! { "ticker": &ticker.ticker, "exchange": &ticker.exchange, "source": &ticker.source };
+        Ok(ipos.find_one(filter, None).await?)
+    }
+
+    /*
+        Fetches the news about tickers from their datasource and stores the items not stored yet. A ticker
+        whose collection already has news is fetched from its latest item, otherwise from its from. Items are
+        stored under the metadata of the ticker they were fetched for. Returns the items stored.
+    */
+    pub async fn ingest_news(&self, tickers: &[MongoTickerParams]) -> Result<usize> {
+        let mut items_stored = 0;
+        for ticker in tickers.iter() {
+            let mut ticker = ticker.clone();
+            if let Some(metadata) = self.find_metadata(&ticker).await?.first() {
+                ticker.from = metadata.to;
+            }
+            ticker.to = get_current_datetime_bson();
+            let fetched = match ticker.source.as_str() {
+                "eod" => match self.eod_api.as_ref() {
+                    Some(eod_api) => eod_api.get_news(&ticker).await?,
+                    None => return Err(anyhow!("ingest_news() EOD API token is not configured!")),
                 },
-                1 => {
-                    let metadata = metadata_vec.pop().expect("Could not pop metadata!");
-                    let new_from = metadata.to;
-                    let current_date = get_current_datetime_bson();
-                    let is_day_between = has_business_day_between(new_from, current_date);
-                    if is_day_between {
-                        let ticker_param_updated = MongoTickerParams {
-                            ticker: metadata.ticker,
-                            exchange: metadata.exchange,
-                            series_collection_name: metadata.series_collection_name,
-                            source: metadata.source,
-                            from: new_from,
-                            to: current_date,
-                        };
-                        existing_tickers.push(ticker_param_updated);
-                    }
-                }    
-                _ => println!("run() found more than one metadata document for ticker: {}, exchange: {}, source: {}", ticker.ticker, ticker.exchange, ticker.source),
+                source => {
+                    log::error!("ingest_news() datasource {} has no news", source);
+                    continue;
+                }
+            };
+            let stored = self.find_news(&ticker).await?;
+            let items = new_news_items(&stored, fetched);
+            log::info!("ingest_news() {} new items about {}.{}", items.len(), &ticker.ticker, &ticker.exchange);
+            if !items.is_empty() {
+                items_stored += self.insert_records(&items, |item| (&item.metadata, item.datetime)).await?;
             }
         }
+        Ok(items_stored)
+    }
 
-        // insert series rows to db for new and old tickers
-        let dfs_new = self.get_data_from_apis(new_tickers).await?;
-        let insert_new_dfs = self.insert_series(dfs_new).await?;
-        assert!(insert_new_dfs, "insert_series() failed!");
+    // the news items about a ticker published between from and to, in datetime order
+    pub async fn find_news(&self, ticker: &MongoTickerParams) -> Result<Vec<NewsItem>> {
+        let collection = self
+            .client
+            .clone()
+            .database(&self.db_name)
+            .collection::<NewsItem>(&ticker.series_collection_name);
+        let filter = doc! {
+            "metadata.ticker": &ticker.ticker,
+            "metadata.exchange": &ticker.exchange,
+            "metadata.metadata_collection_name": &ticker.series_collection_name,
+            "metadata.source": &ticker.source,
+            "datetime": { "$gte": ticker.from, "$lte": ticker.to },
+        };
+        let options = FindOptions::builder().sort(doc! { "datetime": 1 }).build();
+        let items = collection.find(filter, options).await?.try_collect::<Vec<NewsItem>>().await?;
+        Ok(items)
+    }
 
-        let dfs_existing = self.get_data_from_apis(existing_tickers).await?;
-        let insert_existing_dfs = self.insert_series(dfs_existing).await?;
-        assert!(insert_existing_dfs, "insert_series() failed!");
+    // the news items about a ticker between from and to as a df, see news_sentiment::news_df()
+    pub async fn read_news(&self, ticker: MongoTickerParams) -> Result<(String, DataFrame)> {
+        let items = self.find_news(&ticker).await?;
+        Ok((ticker.ticker, news_df(&items)?))
+    }
 
-        // update metadata dates
-        let metadata_update = self.update_metadata_dates(&tickers).await?;
-        assert!(metadata_update, "update_metadata_dates() failed!");
+    // aggregates the trade ticks of a ticker into bars a chunk at a time, so the ticks are never held in memory at once
+    pub async fn read_tick_bars(&self, ticker: MongoTickerParams, spec: BarSpec) -> Result<(String, DataFrame)> {
+        if CollectionType::parse(&ticker.series_collection_name).kind != CollectionKind::TradeTicks {
+            return Err(anyhow!("read_tick_bars() {} is not a trade tick collection", &ticker.series_collection_name));
+        }
+        let ticker_name = ticker.ticker.clone();
+        let mut builder = TickBarBuilder::new(spec)?;
+        let mut chunks = self.read_series_stream(ticker, TICK_CHUNK_ROWS).await?;
+        while let Some(chunk) = chunks.try_next().await? {
+            builder.push_df(&chunk)?;
+        }
+        Ok((ticker_name, builder.finish()?))
+    }
 
-        // read series based on dates provided
-        let dfs = self.read_series(tickers).await?;
+    pub async fn list_metadata_collections(&self) -> Result<Vec<String>> {
+        let metadata_db = self.client.clone().database(&self.db_metadata_name);
+        let mut collection_names = metadata_db
+            .list_collection_names(None)
+            .await?
+            .into_iter()
+            .filter(|collection_name| collection_name != SCHEMA_COLLECTION_NAME && collection_name != FUTURES_CONTRACTS_COLLECTION_NAME)
+            .collect::<Vec<String>>();
+        collection_names.sort();
+        Ok(collection_names)
+    }
 
-        Ok(dfs)
+    pub async fn list_registered_series(&self, collection_name: &str) -> Result<Vec<TimeseriesMetaDataStruct>> {
+        let metadata_collection = self
+            .client
+            .clone()
+            .database(&self.db_metadata_name)
+            .collection::<TimeseriesMetaDataStruct>(collection_name);
+
+        let mut cursor = metadata_collection.find(None, None).await?;
+        let mut metadata_vec = Vec::new();
+        while let Some(result) = cursor.try_next().await? {
+            metadata_vec.push(result);
+        }
+
+        Ok(metadata_vec)
+    }
+
+    // removes every series row and the metadata document for a ticker. Returns (series rows, metadata docs) deleted
+    pub async fn delete_series(&self, ticker: &MongoTickerParams) -> Result<(u64, u64)> {
+        log::info!("delete_series() deleting ticker {} from collection {}", &ticker.ticker, &ticker.series_collection_name);
+        let series_filter = doc! {
+            "metadata.ticker": &ticker.ticker,
+            "metadata.exchange": &ticker.exchange,
+            "metadata.metadata_collection_name": &ticker.series_collection_name,
+            "metadata.source": &ticker.source,
+        };
+        let metadata_filter = doc! {
+            "ticker": &ticker.ticker,
+            "exchange": &ticker.exchange,
+            "series_collection_name": &ticker.series_collection_name,
+            "source": &ticker.source,
+        };
+
+        let series_result = self
+            .client
+            .clone()
+            .database(&self.db_name)
+            .collection::<Document>(&ticker.series_collection_name)
+            .delete_many(series_filter, None)
+            .await?;
+        let metadata_result = self
+            .client
+            .clone()
+            .database(&self.db_metadata_name)
+            .collection::<Document>(&ticker.series_collection_name)
+            .delete_many(metadata_filter, None)
+            .await?;
+
+        Ok((series_result.deleted_count, metadata_result.deleted_count))
+    }
+
+    // compares the metadata document of a ticker against the rows actually stored in the series collection
+    pub async fn verify_series(&self, ticker: &MongoTickerParams) -> Result<SeriesVerification> {
+        log::info!("verify_series() verifying ticker {} in collection {}", &ticker.ticker, &ticker.series_collection_name);
+        let series_collection = self
+            .client
+            .clone()
+            .database(&self.db_name)
+            .collection::<Document>(&ticker.series_collection_name);
+        let series_filter = doc! {
+            "metadata.ticker": &ticker.ticker,
+            "metadata.exchange": &ticker.exchange,
+            "metadata.metadata_collection_name": &ticker.series_collection_name,
+            "metadata.source": &ticker.source,
+        };
+
+        let pipeline = vec![
+            doc! { "$match": series_filter },
+            doc! { "$group": { "_id": "$datetime", "count": { "$sum": 1 } } },
+            doc! { "$group": {
+                "_id": Bson::Null,
+                "rows": { "$sum": "$count" },
+                "duplicate_datetimes": { "$sum": { "$cond": [{ "$gt": ["$count", 1] }, 1, 0] } },
+                "min_date": { "$min": "$_id" },
+                "max_date": { "$max": "$_id" },
+            } },
+        ];
+        let mut cursor = series_collection.aggregate(pipeline, None).await?;
+        let summary = cursor.try_next().await?.unwrap_or_default();
+
+        let mut metadata_vec = self.find_metadata(ticker).await?;
+        let verification = SeriesVerification {
+            ticker: ticker.ticker.clone(),
+            exchange: ticker.exchange.clone(),
+            series_collection_name: ticker.series_collection_name.clone(),
+            source: ticker.source.clone(),
+            metadata_documents: metadata_vec.len(),
+            rows: summary.get("rows").and_then(|rows| rows.as_i32().map(i64::from).or_else(|| rows.as_i64())).unwrap_or(0),
+            duplicate_datetimes: summary.get("duplicate_datetimes").and_then(|dups| dups.as_i32().map(i64::from).or_else(|| dups.as_i64())).unwrap_or(0),
+            series_from: summary.get_datetime("min_date").ok().copied(),
+            series_to: summary.get_datetime("max_date").ok().copied(),
+            metadata: metadata_vec.pop(),
+        };
+
+        Ok(verification)
+    }
+}
+
+// metadata document of a ticker among those found for a batch of tickers
+fn find_ticker_metadata<'a>(
+    metadata_vec: &'a [TimeseriesMetaDataStruct],
+    ticker: &MongoTickerParams,
+) -> Option<&'a TimeseriesMetaDataStruct> {
+    metadata_vec.iter().find(|metadata| {
+        metadata.ticker == ticker.ticker
+            && metadata.exchange == ticker.exchange
+            && metadata.series_collection_name == ticker.series_collection_name
+            && metadata.source == ticker.source
+    })
+}
+
+// the timezone a datasource reports naive datetimes in for new series, which is recorded in their metadata
+fn default_source_timezone(ticker: &MongoTickerParams) -> Tz {
+    match ticker.source.as_str() {
+        "eod" => EodApi::source_timezone(ticker),
+        _ => Tz::UTC,
+    }
+}
+
+// the source timezone recorded in the metadata of a series, or the default one of its datasource without metadata
+fn series_source_timezone(metadata_vec: &[TimeseriesMetaDataStruct], ticker: &MongoTickerParams) -> Result<Tz> {
+    match find_ticker_metadata(metadata_vec, ticker) {
+        Some(metadata) => parse_timezone(&metadata.source_timezone),
+        None => Ok(default_source_timezone(ticker)),
+    }
+}
+
+// upper bound on how many rows are preallocated per chunk, so usize::MAX can mean "one chunk"
+const MAX_CHUNK_CAPACITY: usize = 1 << 16;
+// rows of ticks read per chunk when aggregating them into bars
+const TICK_CHUNK_ROWS: usize = 100_000;
+
+enum ColumnValues {
+    Float(Vec<Option<f64>>),
+    Integer(Vec<Option<i64>>),
+    Boolean(Vec<Option<bool>>),
+}
+
+// accumulates documents column by column, following a collection schema, and turns them into a dataframe
+struct SeriesColumnBuilder {
+    // milliseconds since the epoch in UTC
+    datetime: Vec<i64>,
+    columns: Vec<(String, ColumnValues)>,
+}
+
+impl SeriesColumnBuilder {
+    fn new(schema: &CollectionSchema, capacity: usize) -> Self {
+        let columns = schema
+            .fields
+            .iter()
+            .map(|field| {
+                let values = match field.field_type {
+                    FieldType::Float => ColumnValues::Float(Vec::with_capacity(capacity)),
+                    FieldType::Integer => ColumnValues::Integer(Vec::with_capacity(capacity)),
+                    FieldType::Boolean => ColumnValues::Boolean(Vec::with_capacity(capacity)),
+                };
+                (field.name.clone(), values)
+            })
+            .collect();
+        Self {
+            datetime: Vec::with_capacity(capacity),
+            columns,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.datetime.len()
+    }
+
+    fn push(&mut self, document: &Document) -> Result<()> {
+        let datetime = document.get_datetime("datetime")?;
+        self.datetime.push(datetime.timestamp_millis());
+        for (name, values) in self.columns.iter_mut() {
+            let value = document.get(name.as_str()).unwrap_or(&Bson::Null);
+            match (values, value) {
+                (ColumnValues::Float(values), Bson::Null) => values.push(None),
+                (ColumnValues::Integer(values), Bson::Null) => values.push(None),
+                (ColumnValues::Boolean(values), Bson::Null) => values.push(None),
+                (ColumnValues::Float(values), Bson::Double(number)) => values.push(Some(*number)),
+                (ColumnValues::Float(values), Bson::Int32(number)) => values.push(Some(*number as f64)),
+                (ColumnValues::Float(values), Bson::Int64(number)) => values.push(Some(*number as f64)),
+                (ColumnValues::Integer(values), Bson::Int32(number)) => values.push(Some(*number as i64)),
+                (ColumnValues::Integer(values), Bson::Int64(number)) => values.push(Some(*number)),
+                (ColumnValues::Boolean(values), Bson::Boolean(boolean)) => values.push(Some(*boolean)),
+                (_, value) => {
+                    return Err(anyhow!(
+                        "SeriesColumnBuilder field {} does not match its schema, found {:?}",
+                        name,
+                        value.element_type()
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<DataFrame> {
+        let mut columns = vec![utc_datetime_series("datetime", Int64Chunked::from_vec("datetime", self.datetime))];
+        for (name, values) in self.columns.into_iter() {
+            let series = match values {
+                ColumnValues::Float(values) => Series::new(&name, values),
+                ColumnValues::Integer(values) => Series::new(&name, values),
+                ColumnValues::Boolean(values) => Series::new(&name, values),
+            };
+            columns.push(series);
+        }
+        Ok(DataFrame::new(columns)?)
     }
 }
 
+// the ticker of a series over all its dates, to look up its metadata by
+fn metadata_ticker(metadata: OhlcvMetaData) -> MongoTickerParams {
+    MongoTickerParams {
+        ticker: metadata.ticker,
+        exchange: metadata.exchange,
+        series_collection_name: metadata.metadata_collection_name,
+        source: metadata.source,
+        from: bson::DateTime::MIN,
+        to: bson::DateTime::MAX,
+    }
+}
+
+// reads the OhlcvMetaData attached to every row of a df returned by the data apis
+pub(crate) fn series_metadata(df: &DataFrame) -> Result<OhlcvMetaData> {
+    let metadata = df
+        .column("metadata")?
+        .str()?
+        .get(0)
+        .ok_or_else(|| anyhow!("series_metadata() dataframe has no metadata!"))?;
+    Ok(serde_json::from_str(metadata)?)
+}
+
 /*---------------------------------- TESTS ---------------------------------- */
 #[cfg(test)]
 mod tests {
     use super::*;
     use chrono::{DateTime, Utc};
     use std::env;
+    use crate::database::models::schema_models::FieldSchema;
+    use crate::database::models::synthetic_models::SyntheticConfig;
+    use crate::database::utility_functions::parse_source_datetime;
     // use dotenv::dotenv;
 
+    #[test]
+    fn test_series_column_builder() {
+        let schema = CollectionSchema {
+            collection_name: "rates_curve_1d".to_string(),
+            fields: vec![
+                FieldSchema::new("yield_10y", FieldType::Float),
+                FieldSchema::new("observations", FieldType::Integer),
+            ],
+        };
+        let mut builder = SeriesColumnBuilder::new(&schema, 2);
+        builder.push(&doc! { "datetime": parse_datetime("2024-01-02").unwrap(), "yield_10y": 4.1, "observations": 3 }).unwrap();
+        builder.push(&doc! { "datetime": parse_datetime("2024-01-03").unwrap(), "yield_10y": 4, "open": 1.0 }).unwrap();
+        let df = builder.finish().unwrap();
+        assert_eq!(df.get_column_names(), vec!["datetime", "yield_10y", "observations"]);
+        assert_eq!(df.column("yield_10y").unwrap().f64().unwrap().get(1), Some(4.0));
+        assert_eq!(df.column("observations").unwrap().i64().unwrap().get(1), None);
+
+        // values that do not match the schema are an error rather than a null
+        let mut builder = SeriesColumnBuilder::new(&schema, 1);
+        assert!(builder.push(&doc! { "datetime": parse_datetime("2024-01-02").unwrap(), "yield_10y": "4.1" }).is_err());
+
+        // integer volumes written before the volume migration read back as floats next to fractional ones
+        let mut builder = SeriesColumnBuilder::new(&CollectionSchema::ohlcv("crypto_spot_1d"), 2);
+        builder.push(&doc! { "datetime": parse_datetime("2024-01-02").unwrap(), "volume": 3_000_000_000i64 }).unwrap();
+        builder.push(&doc! { "datetime": parse_datetime("2024-01-03").unwrap(), "volume": 0.25 }).unwrap();
+        let volume = builder.finish().unwrap().column("volume").unwrap().f64().unwrap().clone();
+        assert_eq!(volume.get(0), Some(3_000_000_000.0));
+        assert_eq!(volume.get(1), Some(0.25));
+    }
+
     #[tokio::test]
     async fn test_run() {
         /*
@@ -459,18 +2019,11 @@ mod tests {
             fine. Note for future, if this test passes our data is being stored correctly.
          */
 
-        // Set mock env variables
-        dotenv().ok();
-        let eod_api_token = env::var("API_TOKEN").unwrap();
-        let client_url = env::var("MONGODB_URI").unwrap();
+        // Point the client at the mock databases
+        let config = Config::load().unwrap();
         let database_name = env::var("MONGODB_NAME_MOCK").unwrap();
         let database_metadata_name = env::var("MONGODB_METADATA_NAME_MOCK").unwrap();
 
-        env::set_var("API_TOKEN", eod_api_token);
-        env::set_var("MONGODB_URI", client_url);
-        env::set_var("MONGODB_NAME", database_name);
-        env::set_var("MONGODB_METADATA_NAME", database_metadata_name);
-
         // Set parameters
         let now: DateTime<Utc> = Utc::now();
         let current_date_string = now.format("%Y-%m-%d").to_string();
@@ -481,37 +2034,44 @@ mod tests {
             exchange: exchange.to_string(),
             series_collection_name: collection_name.to_string(),
             source: source.to_string(),
-            from: string_to_datetime(from),
-            to: string_to_datetime(to),
+            from: parse_datetime(from).unwrap(),
+            to: parse_datetime(to).unwrap(),
         })
         .collect::<Vec<MongoTickerParams>>();
 
         // Test EodApi client
-        let eod_client = EodApi::new().await;
+        let eod_client = EodApi::with_token(config.eod.api_token.clone().unwrap());
         let eod_dfs = eod_client.batch_get_series_all(system_params.clone()).await.unwrap();
         let mut eod_dfs_clean = Vec::new();
-        for df in eod_dfs.into_iter() {
-            let mut df_clean = df.lazy()
-                .select([
-                    col("date") + lit("T00:00:00+00:00"),
-                    col("open"),
-                    col("high"),
-                    col("low"),
-                    col("close"),
-                    col("volume"),
-                    col("adjusted_close"),
-                ])
-                .collect()
-                .unwrap();
-            df_clean.rename("date", "datetime").unwrap();
-            let df_clean = df_clean.slice(0, df_clean.height() - 1); // remove last row as df's don't align
-            eod_dfs_clean.push(df_clean);
+        for (_, df) in eod_dfs.into_iter() {
+            // reads return the datetime as UTC milliseconds, volume as a double and the fields in schema order
+            let df = df.unwrap();
+            let millis = df
+                .column("date")
+                .unwrap()
+                .str()
+                .unwrap()
+                .into_iter()
+                .map(|date| date.map(|date| parse_source_datetime(date, Tz::UTC).unwrap().timestamp_millis()))
+                .collect::<Int64Chunked>();
+            let mut columns = vec![utc_datetime_series("datetime", millis)];
+            for name in CollectionSchema::ohlcv("equity_spot_1d").field_names() {
+                columns.push(df.column(name).unwrap().cast(&DataType::Float64).unwrap());
+            }
+            eod_dfs_clean.push(DataFrame::new(columns).unwrap());
         }
 
         // Mongo client
-        let mongo_client = MongoDbClient::new().await;
+        let mongo_client = MongoDbClient::builder()
+            .config(&config)
+            .series_db(database_name)
+            .metadata_db(database_metadata_name)
+            .build()
+            .await
+            .unwrap();
         let mut mongo_dfs_clean = Vec::new();
-        let mongo_dfs = mongo_client.run(user_input_params).await.unwrap();
+        let (mongo_dfs, report) = mongo_client.run(user_input_params).await.unwrap();
+        assert!(!report.has_failures());
         for ticker_df in mongo_dfs.into_iter() {
            mongo_dfs_clean.push(ticker_df.1); 
         }
@@ -526,6 +2086,51 @@ mod tests {
         mongo_client.database("molly_db_mock").drop(None).await.unwrap();
         mongo_client.database("molly_db_metadata_mock").drop(None).await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_run_synthetic() {
+        // run() against the synthetic datasource needs no api token, the read must match the bars generated
+        let synthetic_api = SyntheticApi::new(SyntheticConfig::new(11));
+        let mongo_client = MongoDbClient::builder()
+            .uri("mongodb://localhost:27017")
+            .series_db("molly_db_synthetic_mock")
+            .metadata_db("molly_db_metadata_synthetic_mock")
+            .synthetic_api(synthetic_api.clone())
+            .build()
+            .await
+            .unwrap();
+        let user_input_params = vec![("SYN", "US", "equity_spot_1d", "synthetic", "2024-01-01", "2024-01-31")];
+        let ticker = MongoTickerParams {
+            ticker: "SYN".to_string(),
+            exchange: "US".to_string(),
+            series_collection_name: "equity_spot_1d".to_string(),
+            source: "synthetic".to_string(),
+            from: parse_datetime("2024-01-01").unwrap(),
+            to: parse_datetime("2024-01-31").unwrap(),
+        };
+        let generated = synthetic_api.generate(&ticker, parse_datetime("2024-02-01").unwrap()).unwrap();
+        let mut columns = vec![generated.column("datetime").unwrap().clone()];
+        for name in CollectionSchema::ohlcv("equity_spot_1d").field_names() {
+            columns.push(generated.column(name).unwrap().clone());
+        }
+        let expected = DataFrame::new(columns).unwrap();
+        assert_eq!(expected.height(), 23);
+
+        let (dfs, report) = mongo_client.run(user_input_params.clone()).await.unwrap();
+        assert!(!report.has_failures());
+        assert_eq!(report.tickers[0].action, IngestAction::Created);
+        assert_eq!(dfs.len(), 1);
+        assert_eq!(dfs[0].1, expected);
+
+        // a second run finds the series up to date and reads the same bars
+        let (dfs, report) = mongo_client.run(user_input_params).await.unwrap();
+        assert_eq!(report.tickers[0].rows_written, 0);
+        assert_eq!(dfs[0].1, expected);
+
+        let mongo_client = Client::with_uri_str("mongodb://localhost:27017").await.unwrap();
+        mongo_client.database("molly_db_synthetic_mock").drop(None).await.unwrap();
+        mongo_client.database("molly_db_metadata_synthetic_mock").drop(None).await.unwrap();
+    }
 }
 
 // let eod_api_token = env::var("API_TOKEN").unwrap();
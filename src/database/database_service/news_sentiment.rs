@@ -0,0 +1,137 @@
+use anyhow::Result;
+use polars::prelude::*;
+use std::collections::HashSet;
+
+use crate::database::models::news_models::NewsItem;
+use crate::database::utility_functions::{collection_interval_millis, utc_datetime_series};
+
+// the fetched items that are not stored yet, an item is the same one if it has the same datetime and headline
+pub fn new_news_items(stored: &[NewsItem], fetched: Vec<NewsItem>) -> Vec<NewsItem> {
+    let mut seen = stored
+        .iter()
+        .map(|item| (item.datetime, item.headline.clone()))
+        .collect::<HashSet<(bson::DateTime, String)>>();
+    fetched
+        .into_iter()
+        .filter(|item| seen.insert((item.datetime, item.headline.clone())))
+        .collect()
+}
+
+// news items as a df ordered by datetime, tickers as a comma separated list
+pub fn news_df(items: &[NewsItem]) -> Result<DataFrame> {
+    let mut items = items.iter().collect::<Vec<&NewsItem>>();
+    items.sort_by_key(|item| item.datetime);
+    let datetimes = Int64Chunked::from_vec("datetime", items.iter().map(|item| item.datetime.timestamp_millis()).collect());
+    let df = DataFrame::new(vec![
+        utc_datetime_series("datetime", datetimes),
+        Series::new("headline", items.iter().map(|item| item.headline.as_str()).collect::<Vec<&str>>()),
+        Series::new("source", items.iter().map(|item| item.source.as_str()).collect::<Vec<&str>>()),
+        Series::new("url", items.iter().map(|item| item.url.as_deref()).collect::<Vec<Option<&str>>>()),
+        Series::new("tickers", items.iter().map(|item| item.tickers.join(",")).collect::<Vec<String>>()),
+        Series::new("sentiment", items.iter().map(|item| item.sentiment).collect::<Vec<Option<f64>>>()),
+    ])?;
+    Ok(df)
+}
+
+/*
+    Adds the daily sentiment of a ticker to its prices as sentiment and sentiment_count columns. Each bar gets
+    the latest sentiment known by the end of the bar, and the sentiment of a day is only known at the end of
+    it, so daily bars get the sentiment of their own date and intraday bars that of the day before. Days
+    without news have no sentiment and bars after them keep the last one, bars before the first are null.
+*/
+pub fn align_sentiment(prices: &DataFrame, price_collection: &str, sentiment: &DataFrame, sentiment_collection: &str) -> Result<DataFrame> {
+    let sentiment_interval = collection_interval_millis(sentiment_collection);
+    let sentiment_datetimes = sentiment.column("datetime")?.cast(&DataType::Int64)?;
+    let sentiment_values = sentiment.column("sentiment")?.cast(&DataType::Float64)?;
+    let sentiment_counts = sentiment.column("count")?.cast(&DataType::Int64)?;
+    let mut known = sentiment_datetimes
+        .i64()?
+        .into_iter()
+        .zip(sentiment_values.f64()?)
+        .zip(sentiment_counts.i64()?)
+        .filter_map(|((datetime, value), count)| datetime.zip(value).map(|(datetime, value)| (datetime + sentiment_interval, value, count)))
+        .collect::<Vec<(i64, f64, Option<i64>)>>();
+    known.sort_by_key(|(known_at, _, _)| *known_at);
+
+    let price_interval = collection_interval_millis(price_collection);
+    let price_datetimes = prices.column("datetime")?.cast(&DataType::Int64)?;
+    let (values, counts): (Vec<Option<f64>>, Vec<Option<i64>>) = price_datetimes
+        .i64()?
+        .into_iter()
+        .map(|datetime| {
+            let bar_end = match datetime {
+                Some(datetime) => datetime + price_interval,
+                None => return (None, None),
+            };
+            match known.partition_point(|(known_at, _, _)| *known_at <= bar_end) {
+                0 => (None, None),
+                row => (Some(known[row - 1].1), known[row - 1].2),
+            }
+        })
+        .unzip();
+
+    let mut aligned = prices.clone();
+    aligned.with_column(Series::new("sentiment", values))?;
+    aligned.with_column(Series::new("sentiment_count", counts))?;
+    Ok(aligned)
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86_400_000;
+
+    #[test]
+    fn test_align_sentiment() {
+        let sentiment = DataFrame::new(vec![
+            utc_datetime_series("datetime", Int64Chunked::from_vec("datetime", vec![DAY, 3 * DAY])),
+            Series::new("sentiment", vec![0.5, -0.2]),
+            Series::new("count", vec![12i64, 3]),
+        ])
+        .unwrap();
+        let prices = |millis: Vec<i64>| {
+            DataFrame::new(vec![
+                utc_datetime_series("datetime", Int64Chunked::from_vec("datetime", millis)),
+                Series::new("close", vec![100.0; 4]),
+            ])
+            .unwrap()
+        };
+
+        // daily bars see the sentiment of their own date, and keep it through days without news
+        let daily = align_sentiment(&prices(vec![0, DAY, 2 * DAY, 3 * DAY]), "equity_spot_1d", &sentiment, "equity_sentiment_1d").unwrap();
+        let values = daily.column("sentiment").unwrap().f64().unwrap().into_iter().collect::<Vec<Option<f64>>>();
+        assert_eq!(values, vec![None, Some(0.5), Some(0.5), Some(-0.2)]);
+        assert_eq!(daily.column("sentiment_count").unwrap().i64().unwrap().get(3), Some(3));
+
+        // hourly bars during day 1 only know the sentiment of day 0, which has none
+        let hourly = align_sentiment(&prices(vec![DAY, DAY + DAY / 2, 2 * DAY, 2 * DAY + DAY / 2]), "equity_spot_1h", &sentiment, "equity_sentiment_1d").unwrap();
+        let values = hourly.column("sentiment").unwrap().f64().unwrap().into_iter().collect::<Vec<Option<f64>>>();
+        assert_eq!(values, vec![None, None, Some(0.5), Some(0.5)]);
+
+        let items = |headlines: &[&str]| {
+            headlines
+                .iter()
+                .map(|headline| NewsItem {
+                    datetime: bson::DateTime::from_millis(DAY),
+                    headline: headline.to_string(),
+                    source: "example.com".to_string(),
+                    url: None,
+                    tickers: vec!["AAPL.US".to_string(), "MSFT.US".to_string()],
+                    sentiment: Some(0.5),
+                    metadata: crate::database::models::eod_models::OhlcvMetaData {
+                        metadata_collection_name: "equity_news_items".to_string(),
+                        ticker: "AAPL".to_string(),
+                        source: "eod".to_string(),
+                        exchange: "US".to_string(),
+                        currency: None,
+                    },
+                })
+                .collect::<Vec<NewsItem>>()
+        };
+        let fresh = new_news_items(&items(&["a"]), items(&["a", "b", "b"]));
+        assert_eq!(fresh.iter().map(|item| item.headline.as_str()).collect::<Vec<&str>>(), vec!["b"]);
+        assert_eq!(news_df(&fresh).unwrap().column("tickers").unwrap().str().unwrap().get(0), Some("AAPL.US,MSFT.US"));
+    }
+}
@@ -0,0 +1,161 @@
+use anyhow::Result;
+use bson::{doc, Document};
+use polars::prelude::*;
+
+use crate::database::models::option_models::{ChainFilter, OptionQuote, OptionRight};
+use crate::database::utility_functions::utc_datetime_series;
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+// midnight UTC of the day a datetime falls on, which is where daily chain snapshots are stored
+pub fn chain_date(datetime: bson::DateTime) -> bson::DateTime {
+    let millis = datetime.timestamp_millis();
+    bson::DateTime::from_millis(millis - millis.rem_euclid(MILLIS_PER_DAY))
+}
+
+// the part of a find filter that selects the contracts of a chain snapshot, next to the metadata of the underlying
+pub fn chain_filter_document(date: bson::DateTime, filter: &ChainFilter) -> Document {
+    let date = chain_date(date);
+    let mut document = doc! {
+        "datetime": {
+            "$gte": date,
+            "$lt": bson::DateTime::from_millis(date.timestamp_millis() + MILLIS_PER_DAY)
+        }
+    };
+
+    let mut expiry = Document::new();
+    if let Some(days) = filter.min_days_to_expiry {
+        expiry.insert("$gte", bson::DateTime::from_millis(date.timestamp_millis() + days * MILLIS_PER_DAY));
+    }
+    if let Some(days) = filter.max_days_to_expiry {
+        expiry.insert("$lte", bson::DateTime::from_millis(date.timestamp_millis() + days * MILLIS_PER_DAY));
+    }
+    if !expiry.is_empty() {
+        document.insert("contract.expiry", expiry);
+    }
+
+    let mut strike = Document::new();
+    if let Some(min_strike) = filter.min_strike {
+        strike.insert("$gte", min_strike);
+    }
+    if let Some(max_strike) = filter.max_strike {
+        strike.insert("$lte", max_strike);
+    }
+    if !strike.is_empty() {
+        document.insert("contract.strike", strike);
+    }
+
+    if let Some(right) = filter.right {
+        document.insert("contract.right", bson::to_bson(&right).expect("OptionRight serialises to a string"));
+    }
+    document
+}
+
+/*
+    A chain as a df with one row per contract, in the order the quotes are given. Contract columns
+    come first (symbol, expiry, days_to_expiry, strike, right, multiplier) followed by the quote fields.
+*/
+pub fn option_chain_df(quotes: &[OptionQuote]) -> Result<DataFrame> {
+    let optional = |name: &str, field: fn(&OptionQuote) -> Option<f64>| {
+        Series::new(name, quotes.iter().map(field).collect::<Vec<Option<f64>>>())
+    };
+    let datetimes = Int64Chunked::from_vec("datetime", quotes.iter().map(|quote| quote.datetime.timestamp_millis()).collect());
+    let expiries = Int64Chunked::from_vec("expiry", quotes.iter().map(|quote| quote.contract.expiry.timestamp_millis()).collect());
+    let days_to_expiry = quotes
+        .iter()
+        .map(|quote| (quote.contract.expiry.timestamp_millis() - chain_date(quote.datetime).timestamp_millis()).div_euclid(MILLIS_PER_DAY))
+        .collect::<Vec<i64>>();
+    let rights = quotes
+        .iter()
+        .map(|quote| match quote.contract.right {
+            OptionRight::Call => "call",
+            OptionRight::Put => "put",
+        })
+        .collect::<Vec<&str>>();
+
+    let df = DataFrame::new(vec![
+        utc_datetime_series("datetime", datetimes),
+        Series::new("symbol", quotes.iter().map(|quote| quote.contract.symbol()).collect::<Vec<String>>()),
+        utc_datetime_series("expiry", expiries),
+        Series::new("days_to_expiry", days_to_expiry),
+        Series::new("strike", quotes.iter().map(|quote| quote.contract.strike).collect::<Vec<f64>>()),
+        Series::new("right", rights),
+        Series::new("multiplier", quotes.iter().map(|quote| quote.contract.multiplier).collect::<Vec<f64>>()),
+        optional("bid", |quote| quote.bid),
+        optional("ask", |quote| quote.ask),
+        optional("last", |quote| quote.last),
+        optional("volume", |quote| quote.volume),
+        optional("open_interest", |quote| quote.open_interest),
+        optional("implied_volatility", |quote| quote.implied_volatility),
+        optional("delta", |quote| quote.delta),
+        optional("gamma", |quote| quote.gamma),
+        optional("theta", |quote| quote.theta),
+        optional("vega", |quote| quote.vega),
+    ])?;
+    Ok(df)
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::eod_models::OhlcvMetaData;
+    use crate::database::models::option_models::OptionContract;
+    use crate::database::utility_functions::parse_datetime;
+
+    #[test]
+    fn test_option_chain() {
+        let contract = OptionContract {
+            underlying: "AAPL".to_string(),
+            expiry: parse_datetime("2024-01-19").unwrap(),
+            strike: 192.5,
+            right: OptionRight::Put,
+            multiplier: 100.0,
+        };
+        assert_eq!(contract.symbol(), "AAPL240119P00192500");
+
+        // a snapshot stamped during the day is on the same chain date as midnight
+        let date = parse_datetime("2024-01-02 15:00").unwrap();
+        let filter = ChainFilter {
+            max_days_to_expiry: Some(60),
+            right: Some(OptionRight::Put),
+            ..Default::default()
+        };
+        let document = chain_filter_document(date, &filter);
+        let day = document.get_document("datetime").unwrap();
+        assert_eq!(day.get_datetime("$gte").unwrap(), &parse_datetime("2024-01-02").unwrap());
+        assert_eq!(day.get_datetime("$lt").unwrap(), &parse_datetime("2024-01-03").unwrap());
+        let expiry = document.get_document("contract.expiry").unwrap();
+        assert_eq!(expiry.get_datetime("$lte").unwrap(), &parse_datetime("2024-03-02").unwrap());
+        assert!(!expiry.contains_key("$gte"));
+        assert!(!document.contains_key("contract.strike"));
+        assert_eq!(document.get_str("contract.right").unwrap(), "put");
+
+        let quote = OptionQuote {
+            datetime: date,
+            contract,
+            bid: Some(3.1),
+            ask: Some(3.3),
+            last: None,
+            volume: Some(1200.0),
+            open_interest: Some(15000.0),
+            implied_volatility: Some(0.21),
+            delta: Some(-0.45),
+            gamma: None,
+            theta: None,
+            vega: None,
+            metadata: OhlcvMetaData {
+                metadata_collection_name: "equity_options_1d".to_string(),
+                ticker: "AAPL".to_string(),
+                source: "eod".to_string(),
+                exchange: "US".to_string(),
+                currency: Some("USD".to_string()),
+            },
+        };
+        let df = option_chain_df(&[quote]).unwrap();
+        assert_eq!(df.width(), 17);
+        assert_eq!(df.column("days_to_expiry").unwrap().i64().unwrap().get(0), Some(17));
+        assert_eq!(df.column("right").unwrap().str().unwrap().get(0), Some("put"));
+        assert_eq!(df.column("last").unwrap().null_count(), 1);
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::Result;
+use polars::prelude::*;
+
+use crate::database::models::order_book_models::OrderBookSnapshot;
+use crate::database::utility_functions::utc_datetime_series;
+
+/*
+    Derived series of a run of snapshots, one row per snapshot:
+    - best_bid, best_ask, mid and spread, null while a side of the book is empty
+    - imbalance of the sizes on the top depth levels, see OrderBookSnapshot::imbalance()
+    - bid_depth and ask_depth, the size on the top depth levels of each side
+*/
+pub fn book_series(snapshots: &[OrderBookSnapshot], depth: usize) -> Result<DataFrame> {
+    let datetimes = Int64Chunked::from_vec(
+        "datetime",
+        snapshots.iter().map(|snapshot| snapshot.datetime.timestamp_millis()).collect(),
+    );
+    let df = DataFrame::new(vec![
+        utc_datetime_series("datetime", datetimes),
+        Series::new("sequence", snapshots.iter().map(|snapshot| snapshot.sequence).collect::<Vec<i64>>()),
+        Series::new("best_bid", snapshots.iter().map(|snapshot| snapshot.best_bid()).collect::<Vec<Option<f64>>>()),
+        Series::new("best_ask", snapshots.iter().map(|snapshot| snapshot.best_ask()).collect::<Vec<Option<f64>>>()),
+        Series::new("mid", snapshots.iter().map(|snapshot| snapshot.mid()).collect::<Vec<Option<f64>>>()),
+        Series::new("spread", snapshots.iter().map(|snapshot| snapshot.spread()).collect::<Vec<Option<f64>>>()),
+        Series::new("imbalance", snapshots.iter().map(|snapshot| snapshot.imbalance(depth)).collect::<Vec<Option<f64>>>()),
+        Series::new(
+            "bid_depth",
+            snapshots.iter().map(|snapshot| snapshot.bid_sizes.iter().take(depth).sum()).collect::<Vec<f64>>(),
+        ),
+        Series::new(
+            "ask_depth",
+            snapshots.iter().map(|snapshot| snapshot.ask_sizes.iter().take(depth).sum()).collect::<Vec<f64>>(),
+        ),
+    ])?;
+    Ok(df)
+}
+
+// the book of a snapshot as rows of side, level (0 is the best price), price and size, bids first
+pub fn book_levels(snapshot: &OrderBookSnapshot) -> Result<DataFrame> {
+    let bids = snapshot.bids();
+    let asks = snapshot.asks();
+    let sides = std::iter::repeat_n("bid", bids.len())
+        .chain(std::iter::repeat_n("ask", asks.len()))
+        .collect::<Vec<&str>>();
+    let level_numbers = (0..bids.len() as u32).chain(0..asks.len() as u32).collect::<Vec<u32>>();
+    let levels = bids.iter().chain(asks.iter());
+    let df = DataFrame::new(vec![
+        Series::new("side", sides),
+        Series::new("level", level_numbers),
+        Series::new("price", levels.clone().map(|level| level.price).collect::<Vec<f64>>()),
+        Series::new("size", levels.map(|level| level.size).collect::<Vec<f64>>()),
+    ])?;
+    Ok(df)
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::eod_models::OhlcvMetaData;
+    use crate::database::models::order_book_models::BookLevel;
+
+    fn snapshot(sequence: i64, bids: &[(f64, f64)], asks: &[(f64, f64)]) -> OrderBookSnapshot {
+        let to_levels = |levels: &[(f64, f64)]| {
+            levels.iter().map(|(price, size)| BookLevel { price: *price, size: *size }).collect::<Vec<BookLevel>>()
+        };
+        let metadata = OhlcvMetaData {
+            metadata_collection_name: "crypto_book_snapshots".to_string(),
+            ticker: "BTC-USD".to_string(),
+            source: "binance".to_string(),
+            exchange: "CC".to_string(),
+            currency: Some("USD".to_string()),
+        };
+        OrderBookSnapshot::from_levels(
+            bson::DateTime::from_millis(sequence * 1000),
+            sequence,
+            to_levels(bids),
+            to_levels(asks),
+            metadata,
+        )
+    }
+
+    fn column(df: &DataFrame, name: &str) -> Vec<Option<f64>> {
+        df.column(name).unwrap().cast(&DataType::Float64).unwrap().f64().unwrap().into_iter().collect()
+    }
+
+    #[test]
+    fn test_book_series() {
+        // levels arrive unordered and the empty level is dropped
+        let book = snapshot(1, &[(99.0, 3.0), (100.0, 1.0), (98.0, 0.0)], &[(102.0, 4.0), (101.0, 2.0)]);
+        assert!(book.validate().is_ok());
+        assert_eq!(book.bid_prices, vec![100.0, 99.0]);
+        assert_eq!(book.ask_prices, vec![101.0, 102.0]);
+
+        let one_sided = snapshot(2, &[(100.0, 1.0)], &[]);
+        let df = book_series(&[book.clone(), one_sided], 1).unwrap();
+        assert_eq!(column(&df, "mid"), vec![Some(100.5), None]);
+        assert_eq!(column(&df, "spread"), vec![Some(1.0), None]);
+        assert_eq!(column(&df, "imbalance"), vec![Some(-1.0 / 3.0), Some(1.0)]);
+        assert_eq!(column(&df, "bid_depth"), vec![Some(1.0), Some(1.0)]);
+        let deeper = book_series(std::slice::from_ref(&book), 2).unwrap();
+        assert_eq!(column(&deeper, "imbalance"), vec![Some(-0.2)]);
+
+        let levels = book_levels(&book).unwrap();
+        assert_eq!(levels.height(), 4);
+        assert_eq!(column(&levels, "price"), vec![Some(100.0), Some(99.0), Some(101.0), Some(102.0)]);
+
+        let mut crossed = book;
+        crossed.ask_prices[0] = 99.5;
+        assert!(crossed.validate().is_err());
+    }
+}
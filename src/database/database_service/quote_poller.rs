@@ -0,0 +1,46 @@
+use anyhow::Result;
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+
+use crate::database::database_service::MongoDbClient;
+use crate::database::models::eod_models::MongoTickerParams;
+
+// appends a quote snapshot of every ticker to its live collection at a fixed interval
+pub struct QuotePoller {
+    client: MongoDbClient,
+    tickers: Vec<MongoTickerParams>,
+    interval: Duration,
+}
+
+impl QuotePoller {
+    pub fn new(client: MongoDbClient, tickers: Vec<MongoTickerParams>, interval: Duration) -> Self {
+        Self {
+            client,
+            tickers,
+            interval,
+        }
+    }
+
+    // polls until ctrl-c is received. A failed poll is logged and retried on the next tick
+    pub async fn run(&self) -> Result<()> {
+        let mut interval = tokio::time::interval(self.interval);
+        // a slow poll delays the next one instead of firing a burst to catch up
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // created once so a ctrl-c received while a poll is running is not missed
+        let shutdown = tokio::signal::ctrl_c();
+        tokio::pin!(shutdown);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => (),
+                _ = &mut shutdown => {
+                    log::info!("QuotePoller received shutdown signal");
+                    return Ok(());
+                }
+            }
+            match self.client.poll_quotes(&self.tickers).await {
+                Ok(quotes_stored) => log::info!("QuotePoller stored {} quotes", quotes_stored),
+                Err(e) => log::error!("QuotePoller failed to poll quotes: {:#}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::collections::HashMap;
+
+use crate::database::database_service::MongoDbClient;
+use crate::database::models::eod_models::MongoTickerParams;
+use crate::database::models::ingest_models::IngestReport;
+use crate::database::models::schema_models::{CollectionType, Granularity};
+use crate::database::utility_functions::{get_current_datetime_bson, parse_timezone};
+
+// how often the daemon wakes up to look for new collections when nothing is due
+const POLL_INTERVAL_SECONDS: i64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshSchedule {
+    // once a day at the given local time of an exchange timezone, i.e., after that exchange has closed
+    Daily(NaiveTime, Tz),
+    Every(Duration),
+}
+
+impl RefreshSchedule {
+    // collections are named {asset}_{format}_{interval} so the interval suffix decides the schedule
+    pub fn for_collection(collection_name: &str, daily_refresh_time: NaiveTime, exchange_timezone: Tz) -> Option<Self> {
+        match CollectionType::parse(collection_name).granularity? {
+            Granularity::Days(_) => Some(RefreshSchedule::Daily(daily_refresh_time, exchange_timezone)),
+            Granularity::Hours(count) => Some(RefreshSchedule::Every(Duration::hours(count))),
+            Granularity::Minutes(count) => Some(RefreshSchedule::Every(Duration::minutes(count))),
+            Granularity::Seconds(_) => None,
+        }
+    }
+
+    pub fn next_after(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            RefreshSchedule::Daily(time, timezone) => {
+                let today = now.with_timezone(timezone).date_naive();
+                let at = |date: NaiveDate| {
+                    // a time skipped when clocks go forward runs an hour later, at the end of the gap
+                    let local = date.and_time(*time);
+                    timezone
+                        .from_local_datetime(&local)
+                        .earliest()
+                        .or_else(|| timezone.from_local_datetime(&(local + Duration::hours(1))).earliest())
+                        .map(|datetime| datetime.with_timezone(&Utc))
+                };
+                (0..3)
+                    .filter_map(|days| at(today + Duration::days(days)))
+                    .find(|next_run| *next_run > now)
+                    .unwrap_or(now + Duration::days(1))
+            }
+            RefreshSchedule::Every(interval) => now + *interval,
+        }
+    }
+}
+
+pub struct RefreshDaemon {
+    client: MongoDbClient,
+    daily_refresh_time: NaiveTime,
+}
+
+impl RefreshDaemon {
+    // daily_refresh_time is the local time of each exchange its daily series are refreshed at
+    pub fn new(client: MongoDbClient, daily_refresh_time: NaiveTime) -> Self {
+        Self {
+            client,
+            daily_refresh_time,
+        }
+    }
+
+    // incrementally refreshes every series registered in a metadata collection up to the current date
+    pub async fn refresh_collection(&self, collection_name: &str) -> Result<IngestReport> {
+        self.refresh_series(collection_name, None).await
+    }
+
+    // incrementally refreshes the series of a collection up to the current date, those of one exchange timezone if given
+    async fn refresh_series(&self, collection_name: &str, exchange_timezone: Option<&str>) -> Result<IngestReport> {
+        log::info!(
+            "RefreshDaemon refreshing series in collection {} trading in {}",
+            collection_name,
+            exchange_timezone.unwrap_or("any timezone")
+        );
+        let current_date = get_current_datetime_bson();
+        let tickers = self
+            .client
+            .list_registered_series(collection_name)
+            .await?
+            .into_iter()
+            .filter(|metadata| exchange_timezone.is_none_or(|timezone| metadata.exchange_timezone == timezone))
+            .map(|metadata| MongoTickerParams {
+                ticker: metadata.ticker,
+                exchange: metadata.exchange,
+                series_collection_name: metadata.series_collection_name,
+                source: metadata.source,
+                from: metadata.from,
+                to: current_date,
+            })
+            .collect::<Vec<MongoTickerParams>>();
+
+        self.client.ingest(&tickers).await
+    }
+
+    /*
+        The refreshes a collection needs, as the exchange timezone they are for and their schedule. Daily
+        collections are refreshed once for every exchange timezone their series trade in, after that
+        exchange has closed, intraday collections once for all of their series.
+    */
+    async fn collection_schedules(&self, collection_name: &str) -> Result<Vec<(Option<String>, RefreshSchedule)>> {
+        let schedule = match RefreshSchedule::for_collection(collection_name, self.daily_refresh_time, Tz::UTC) {
+            Some(schedule) => schedule,
+            None => {
+                log::warn!("RefreshDaemon has no schedule for collection {}", collection_name);
+                return Ok(Vec::new());
+            }
+        };
+        if let RefreshSchedule::Every(_) = schedule {
+            return Ok(vec![(None, schedule)]);
+        }
+        let mut timezones = self
+            .client
+            .list_registered_series(collection_name)
+            .await?
+            .into_iter()
+            .map(|metadata| metadata.exchange_timezone)
+            .collect::<Vec<String>>();
+        timezones.sort();
+        timezones.dedup();
+        let mut schedules = Vec::new();
+        for timezone in timezones.into_iter() {
+            match parse_timezone(&timezone) {
+                Ok(tz) => schedules.push((Some(timezone), RefreshSchedule::Daily(self.daily_refresh_time, tz))),
+                Err(e) => log::error!("RefreshDaemon can not schedule collection {}: {:#}", collection_name, e),
+            }
+        }
+        Ok(schedules)
+    }
+
+    // refresh every collection in the metadata database on its schedule until ctrl-c is received
+    pub async fn run(&self) -> Result<()> {
+        let mut next_runs: HashMap<(String, Option<String>), (RefreshSchedule, DateTime<Utc>)> = HashMap::new();
+        let shutdown = tokio::signal::ctrl_c();
+        tokio::pin!(shutdown);
+        loop {
+            // pick up collections, and exchanges in daily collections, that were added since the last iteration
+            let now = Utc::now();
+            for collection_name in self.client.list_metadata_collections().await? {
+                for (timezone, schedule) in self.collection_schedules(&collection_name).await? {
+                    let key = (collection_name.clone(), timezone);
+                    if next_runs.contains_key(&key) {
+                        continue;
+                    }
+                    let next_run = schedule.next_after(now);
+                    log::info!("RefreshDaemon scheduled collection {} ({:?}) for {}", collection_name, schedule, next_run);
+                    next_runs.insert(key, (schedule, next_run));
+                }
+            }
+
+            for ((collection_name, timezone), (schedule, next_run)) in next_runs.iter_mut() {
+                if *next_run > Utc::now() {
+                    continue;
+                }
+                match self.refresh_series(collection_name, timezone.as_deref()).await {
+                    Ok(report) => {
+                        log::info!(
+                            "RefreshDaemon refreshed {} series in collection {} ({} failed)",
+                            report.tickers.len(),
+                            collection_name,
+                            report.failed().len()
+                        );
+                    }
+                    Err(e) => log::error!("RefreshDaemon failed to refresh collection {}: {:#}", collection_name, e),
+                }
+                *next_run = schedule.next_after(Utc::now());
+            }
+
+            let now = Utc::now();
+            let wake_up = next_runs
+                .values()
+                .map(|(_, next_run)| *next_run)
+                .min()
+                .unwrap_or(now)
+                .min(now + Duration::seconds(POLL_INTERVAL_SECONDS));
+            let sleep_for = (wake_up - now).to_std().unwrap_or_default();
+
+            tokio::select! {
+                _ = tokio::time::sleep(sleep_for) => (),
+                _ = &mut shutdown => {
+                    log::info!("RefreshDaemon received shutdown signal");
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_schedule() {
+        let close = NaiveTime::from_hms_opt(18, 0, 0).unwrap();
+        let new_york = Tz::America__New_York;
+        assert_eq!(
+            RefreshSchedule::for_collection("equity_spot_1d", close, new_york),
+            Some(RefreshSchedule::Daily(close, new_york))
+        );
+        assert_eq!(
+            RefreshSchedule::for_collection("crypto_spot_1h", close, Tz::UTC),
+            Some(RefreshSchedule::Every(Duration::hours(1)))
+        );
+        assert_eq!(
+            RefreshSchedule::for_collection("equity_spot_5m", close, new_york),
+            Some(RefreshSchedule::Every(Duration::minutes(5)))
+        );
+        assert_eq!(RefreshSchedule::for_collection("equity_spot_e", close, new_york), None);
+
+        // before the close the refresh happens the same day, after the close it rolls to the next day
+        let daily = RefreshSchedule::Daily(close, new_york);
+        let morning = Utc.with_ymd_and_hms(2024, 1, 2, 14, 0, 0).unwrap();
+        let night = Utc.with_ymd_and_hms(2024, 1, 2, 23, 30, 0).unwrap();
+        assert_eq!(daily.next_after(morning), Utc.with_ymd_and_hms(2024, 1, 2, 23, 0, 0).unwrap());
+        assert_eq!(daily.next_after(night), Utc.with_ymd_and_hms(2024, 1, 3, 23, 0, 0).unwrap());
+        // summer time moves the refresh in UTC, and Tokyo refreshes long before New York on the same date
+        assert_eq!(daily.next_after(Utc.with_ymd_and_hms(2024, 7, 1, 12, 0, 0).unwrap()), Utc.with_ymd_and_hms(2024, 7, 1, 22, 0, 0).unwrap());
+        let tokyo = RefreshSchedule::Daily(close, Tz::Asia__Tokyo);
+        assert_eq!(tokyo.next_after(Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap()), Utc.with_ymd_and_hms(2024, 1, 2, 9, 0, 0).unwrap());
+    }
+}
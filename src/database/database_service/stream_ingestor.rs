@@ -0,0 +1,85 @@
+use anyhow::{anyhow, Result};
+
+use crate::database::data_apis::EodStream;
+use crate::database::database_service::mongodb::series_metadata;
+use crate::database::database_service::MongoDbClient;
+
+// writes the 1m bars of a websocket feed to their intraday collections as they complete
+pub struct StreamIngestor {
+    client: MongoDbClient,
+    stream: EodStream,
+}
+
+impl StreamIngestor {
+    pub fn new(client: MongoDbClient, stream: EodStream) -> Self {
+        Self { client, stream }
+    }
+
+    // streams until the feed closes or ctrl-c is received, bars go through the same insert path as ingest()
+    pub async fn run(&self) -> Result<()> {
+        self.client.ensure_metadata_exists(self.stream.tickers()).await?;
+        let client = &self.client;
+        let tickers = self.stream.tickers();
+        // looked up once, the metadata of the streamed series does not change while streaming
+        let source_timezones = &client.source_timezones(tickers).await?;
+        self.stream
+            .aggregate(|dfs| async move {
+                let rows = dfs.iter().map(|df| df.height()).sum::<usize>();
+                let mut updated_tickers = Vec::new();
+                for df in dfs.iter() {
+                    let metadata = series_metadata(df)?;
+                    let position = tickers.iter().position(|ticker| {
+                        ticker.ticker == metadata.ticker
+                            && ticker.exchange == metadata.exchange
+                            && ticker.series_collection_name == metadata.metadata_collection_name
+                    });
+                    let position = position.ok_or_else(|| anyhow!("StreamIngestor bars of {}.{} are not streamed", metadata.ticker, metadata.exchange))?;
+                    client.insert_series_df(df, source_timezones[position]).await?;
+                    updated_tickers.push(tickers[position].clone());
+                }
+                client.update_metadata_dates(&updated_tickers).await?;
+                log::info!("StreamIngestor wrote {} bars for {} tickers", rows, updated_tickers.len());
+                Ok(())
+            })
+            .await
+    }
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::config::Config;
+    use crate::database::data_apis::eod_stream::tests::{replay_server, ticker, RECORDED_US_TRADES};
+    use crate::database::data_apis::eod_stream::EodFeed;
+    use mongodb::Client;
+
+    #[tokio::test]
+    async fn test_stream_ingestor() {
+        // the replay completes a single bar per ticker, each flushed as a one row df
+        let config = Config::load().unwrap();
+        let client = MongoDbClient::builder()
+            .config(&config)
+            .series_db("molly_db_stream_mock")
+            .metadata_db("molly_db_metadata_stream_mock")
+            .build()
+            .await
+            .unwrap();
+        let tickers = vec![ticker("AAPL"), ticker("MSFT")];
+        let url = replay_server(&[&RECORDED_US_TRADES]).await;
+        let stream = EodStream::with_url(url, EodFeed::UsTrades, tickers.clone());
+        StreamIngestor::new(client.clone(), stream).run().await.unwrap();
+
+        let dfs = client.read_series(tickers).await.unwrap();
+        let closes = dfs
+            .iter()
+            .map(|(ticker, df)| (ticker.as_str(), df.column("close").unwrap().f64().unwrap().into_no_null_iter().collect::<Vec<f64>>()))
+            .collect::<Vec<(&str, Vec<f64>)>>();
+        assert_eq!(closes, vec![("AAPL", vec![184.90]), ("MSFT", vec![370.00])]);
+
+        // Delete mock dbs when done with test
+        let mongo_client = Client::with_uri_str("mongodb://localhost:27017").await.unwrap();
+        mongo_client.database("molly_db_stream_mock").drop(None).await.unwrap();
+        mongo_client.database("molly_db_metadata_stream_mock").drop(None).await.unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::{anyhow, Result};
+use polars::prelude::*;
+
+use crate::database::utility_functions::utc_datetime_series;
+
+// how trades are grouped into bars
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BarSpec {
+    // a bar per interval of this many milliseconds, e.g., 60_000 for 1m bars
+    Time(i64),
+    // a bar per this much traded size
+    Volume(f64),
+    // a bar per this much traded price * size
+    Dollar(f64),
+}
+
+#[derive(Debug, Clone)]
+struct OpenBar {
+    datetime: i64,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+    dollar_volume: f64,
+    trades: i64,
+}
+
+/*
+    Builds bars from trades pushed in time order, so a series can be aggregated a chunk at a time.
+    - time bars are stamped with the start of their interval and only exist for intervals with trades
+    - volume and dollar bars close on the trade that reaches the threshold and are stamped with it,
+      a trade is never split across bars so bars can overshoot the threshold
+    The last bar is returned by finish() even if it never reached its threshold or interval end.
+*/
+pub struct TickBarBuilder {
+    spec: BarSpec,
+    open_bar: Option<OpenBar>,
+    bars: Vec<OpenBar>,
+}
+
+impl TickBarBuilder {
+    pub fn new(spec: BarSpec) -> Result<Self> {
+        let is_valid = match spec {
+            BarSpec::Time(interval) => interval > 0,
+            BarSpec::Volume(threshold) | BarSpec::Dollar(threshold) => threshold > 0.0,
+        };
+        if !is_valid {
+            return Err(anyhow!("TickBarBuilder {:?} needs a positive interval or threshold", spec));
+        }
+        Ok(Self {
+            spec,
+            open_bar: None,
+            bars: Vec::new(),
+        })
+    }
+
+    pub fn push(&mut self, datetime: i64, price: f64, size: f64) {
+        let bar_datetime = match self.spec {
+            BarSpec::Time(interval) => datetime - datetime.rem_euclid(interval),
+            BarSpec::Volume(_) | BarSpec::Dollar(_) => datetime,
+        };
+        if let (BarSpec::Time(_), Some(open_bar)) = (self.spec, &self.open_bar) {
+            if open_bar.datetime != bar_datetime {
+                self.bars.extend(self.open_bar.take());
+            }
+        }
+
+        let bar = self.open_bar.get_or_insert(OpenBar {
+            datetime: bar_datetime,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: 0.0,
+            dollar_volume: 0.0,
+            trades: 0,
+        });
+        bar.high = bar.high.max(price);
+        bar.low = bar.low.min(price);
+        bar.close = price;
+        bar.volume += size;
+        bar.dollar_volume += price * size;
+        bar.trades += 1;
+
+        let is_complete = match self.spec {
+            BarSpec::Time(_) => false,
+            BarSpec::Volume(threshold) => bar.volume >= threshold,
+            BarSpec::Dollar(threshold) => bar.dollar_volume >= threshold,
+        };
+        if is_complete {
+            // information driven bars are stamped with the trade that completed them
+            bar.datetime = datetime;
+            self.bars.extend(self.open_bar.take());
+        }
+    }
+
+    // pushes every row of a df with datetime, price and size columns, as read from a trade tick collection
+    pub fn push_df(&mut self, df: &DataFrame) -> Result<()> {
+        let datetimes = df.column("datetime")?.cast(&DataType::Int64)?;
+        let prices = df.column("price")?.cast(&DataType::Float64)?;
+        let sizes = df.column("size")?.cast(&DataType::Float64)?;
+        let rows = datetimes
+            .i64()?
+            .into_iter()
+            .zip(prices.f64()?)
+            .zip(sizes.f64()?);
+        for ((datetime, price), size) in rows {
+            match (datetime, price) {
+                (Some(datetime), Some(price)) => self.push(datetime, price, size.unwrap_or(0.0)),
+                _ => log::warn!("TickBarBuilder skipping trade without a datetime or price"),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<DataFrame> {
+        self.bars.extend(self.open_bar.take());
+        let bars = self.bars;
+        let datetimes = Int64Chunked::from_vec("datetime", bars.iter().map(|bar| bar.datetime).collect());
+        let df = DataFrame::new(vec![
+            utc_datetime_series("datetime", datetimes),
+            Series::new("open", bars.iter().map(|bar| bar.open).collect::<Vec<f64>>()),
+            Series::new("high", bars.iter().map(|bar| bar.high).collect::<Vec<f64>>()),
+            Series::new("low", bars.iter().map(|bar| bar.low).collect::<Vec<f64>>()),
+            Series::new("close", bars.iter().map(|bar| bar.close).collect::<Vec<f64>>()),
+            Series::new("volume", bars.iter().map(|bar| bar.volume).collect::<Vec<f64>>()),
+            Series::new("dollar_volume", bars.iter().map(|bar| bar.dollar_volume).collect::<Vec<f64>>()),
+            Series::new("vwap", bars.iter().map(|bar| bar.dollar_volume / bar.volume).collect::<Vec<f64>>()),
+            Series::new("trades", bars.iter().map(|bar| bar.trades).collect::<Vec<i64>>()),
+        ])?;
+        Ok(df)
+    }
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (milliseconds, price, size)
+    const TRADES: [(i64, f64, f64); 6] = [
+        (0, 10.0, 100.0),
+        (20_000, 11.0, 50.0),
+        (59_999, 9.0, 50.0),
+        (60_000, 10.0, 300.0),
+        (185_000, 12.0, 100.0),
+        (190_000, 13.0, 100.0),
+    ];
+
+    fn bars(spec: BarSpec) -> DataFrame {
+        let mut builder = TickBarBuilder::new(spec).unwrap();
+        for (datetime, price, size) in TRADES {
+            builder.push(datetime, price, size);
+        }
+        builder.finish().unwrap()
+    }
+
+    fn column(df: &DataFrame, name: &str) -> Vec<f64> {
+        df.column(name).unwrap().cast(&DataType::Float64).unwrap().f64().unwrap().into_no_null_iter().collect()
+    }
+
+    #[test]
+    fn test_tick_bars() {
+        let time_bars = bars(BarSpec::Time(60_000));
+        assert_eq!(column(&time_bars, "datetime"), vec![0.0, 60_000.0, 180_000.0]);
+        assert_eq!(column(&time_bars, "open"), vec![10.0, 10.0, 12.0]);
+        assert_eq!(column(&time_bars, "high"), vec![11.0, 10.0, 13.0]);
+        assert_eq!(column(&time_bars, "low"), vec![9.0, 10.0, 12.0]);
+        assert_eq!(column(&time_bars, "volume"), vec![200.0, 300.0, 200.0]);
+        assert_eq!(column(&time_bars, "vwap"), vec![10.0, 10.0, 12.5]);
+        assert_eq!(column(&time_bars, "trades"), vec![3.0, 1.0, 2.0]);
+
+        // the 300 lot overshoots the second bar rather than being split
+        let volume_bars = bars(BarSpec::Volume(200.0));
+        assert_eq!(column(&volume_bars, "datetime"), vec![59_999.0, 60_000.0, 190_000.0]);
+        assert_eq!(column(&volume_bars, "volume"), vec![200.0, 300.0, 200.0]);
+
+        let dollar_bars = bars(BarSpec::Dollar(2_500.0));
+        assert_eq!(column(&dollar_bars, "dollar_volume"), vec![5_000.0, 2_500.0]);
+        assert_eq!(column(&dollar_bars, "close"), vec![10.0, 13.0]);
+
+        assert!(TickBarBuilder::new(BarSpec::Volume(0.0)).is_err());
+    }
+}
@@ -0,0 +1,336 @@
+use anyhow::{anyhow, Result};
+use polars::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::database::models::rates_models::CurvePoint;
+use crate::database::utility_functions::{parse_tenor, utc_datetime_series};
+
+// decay parameters tried when fitting Nelson-Siegel, in years
+const NELSON_SIEGEL_TAUS: std::ops::RangeInclusive<u32> = 1..=200;
+const NELSON_SIEGEL_TAU_STEP: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveInterpolation {
+    // straight lines between tenors, flat beyond the shortest and longest
+    Linear,
+    // natural cubic spline through every tenor, flat beyond the shortest and longest
+    CubicSpline,
+    // a Nelson-Siegel curve fitted to every tenor by least squares, which smooths and extrapolates
+    NelsonSiegel,
+}
+
+/*
+    Curve points as a date x tenor matrix, a row per datetime and a column per tenor named as quoted,
+    shortest tenor first. Tenors missing on a date are null.
+*/
+pub fn curve_matrix(points: &[CurvePoint]) -> Result<DataFrame> {
+    let datetimes = points
+        .iter()
+        .map(|point| point.datetime.timestamp_millis())
+        .collect::<BTreeSet<i64>>()
+        .into_iter()
+        .collect::<Vec<i64>>();
+    let rows = datetimes
+        .iter()
+        .enumerate()
+        .map(|(row, datetime)| (*datetime, row))
+        .collect::<HashMap<i64, usize>>();
+
+    let mut tenors: Vec<(f64, String)> = Vec::new();
+    let mut rates: HashMap<&str, Vec<Option<f64>>> = HashMap::new();
+    for point in points.iter() {
+        let column = rates.entry(point.tenor.as_str()).or_insert_with(|| {
+            tenors.push((point.tenor_years, point.tenor.clone()));
+            vec![None; datetimes.len()]
+        });
+        column[rows[&point.datetime.timestamp_millis()]] = Some(point.rate);
+    }
+    tenors.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut columns = vec![utc_datetime_series("datetime", Int64Chunked::from_vec("datetime", datetimes))];
+    for (_, tenor) in tenors.iter() {
+        columns.push(Series::new(tenor, &rates[tenor.as_str()]));
+    }
+    Ok(DataFrame::new(columns)?)
+}
+
+/*
+    Rates at the requested tenors, e.g., 7Y, for every row of a curve matrix. Each row is interpolated
+    from the tenors it has, rows with fewer than two (three for Nelson-Siegel) give nulls.
+*/
+pub fn interpolate_curve(matrix: &DataFrame, tenors: &[&str], method: CurveInterpolation) -> Result<DataFrame> {
+    let mut known = Vec::new();
+    for series in matrix.get_columns().iter().filter(|series| series.name() != "datetime") {
+        let rates = series.cast(&DataType::Float64)?.f64()?.into_iter().collect::<Vec<Option<f64>>>();
+        known.push((parse_tenor(series.name())?, rates));
+    }
+    known.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let targets = tenors.iter().map(|tenor| parse_tenor(tenor)).collect::<Result<Vec<f64>>>()?;
+
+    let mut interpolated = vec![Vec::with_capacity(matrix.height()); targets.len()];
+    for row in 0..matrix.height() {
+        let (row_tenors, row_rates): (Vec<f64>, Vec<f64>) = known
+            .iter()
+            .filter_map(|(tenor, rates)| rates[row].map(|rate| (*tenor, rate)))
+            .unzip();
+        let rates = interpolate_rates(&row_tenors, &row_rates, &targets, method);
+        for (column, rate) in interpolated.iter_mut().zip(rates) {
+            column.push(rate);
+        }
+    }
+
+    let mut columns = vec![matrix.column("datetime")?.clone()];
+    for (tenor, rates) in tenors.iter().zip(interpolated.iter()) {
+        columns.push(Series::new(tenor.trim(), rates));
+    }
+    Ok(DataFrame::new(columns)?)
+}
+
+// rates at the target tenors of a curve known at tenors sorted shortest first, all in years
+pub fn interpolate_rates(tenors: &[f64], rates: &[f64], targets: &[f64], method: CurveInterpolation) -> Vec<Option<f64>> {
+    let minimum_points = match method {
+        CurveInterpolation::Linear | CurveInterpolation::CubicSpline => 2,
+        CurveInterpolation::NelsonSiegel => 3,
+    };
+    if tenors.len() < minimum_points {
+        return vec![None; targets.len()];
+    }
+    match method {
+        CurveInterpolation::Linear => targets.iter().map(|target| Some(linear(tenors, rates, *target))).collect(),
+        CurveInterpolation::CubicSpline => {
+            let second_derivatives = natural_spline(tenors, rates);
+            targets.iter().map(|target| Some(spline(tenors, rates, &second_derivatives, *target))).collect()
+        }
+        CurveInterpolation::NelsonSiegel => match fit_nelson_siegel(tenors, rates) {
+            Ok(curve) => targets.iter().map(|target| Some(curve.rate(*target))).collect(),
+            Err(error) => {
+                log::warn!("interpolate_rates() {}", error);
+                vec![None; targets.len()]
+            }
+        },
+    }
+}
+
+// index of the segment [tenors[i], tenors[i + 1]] a target falls in, None beyond either end
+fn segment(tenors: &[f64], target: f64) -> Option<usize> {
+    match (tenors.first(), tenors.last()) {
+        (Some(first), Some(last)) if target > *first && target < *last => {
+            Some(tenors.partition_point(|tenor| *tenor <= target) - 1)
+        }
+        _ => None,
+    }
+}
+
+// the rate of the nearer end for targets on or beyond the shortest or longest tenor
+fn flat_beyond_ends(tenors: &[f64], rates: &[f64], target: f64) -> f64 {
+    match target <= tenors[0] {
+        true => rates[0],
+        false => rates[rates.len() - 1],
+    }
+}
+
+fn linear(tenors: &[f64], rates: &[f64], target: f64) -> f64 {
+    match segment(tenors, target) {
+        Some(i) => rates[i] + (rates[i + 1] - rates[i]) * (target - tenors[i]) / (tenors[i + 1] - tenors[i]),
+        None => flat_beyond_ends(tenors, rates, target),
+    }
+}
+
+// second derivatives of the natural cubic spline through the points, zero at both ends
+fn natural_spline(tenors: &[f64], rates: &[f64]) -> Vec<f64> {
+    let n = tenors.len();
+    let mut second_derivatives = vec![0.0; n];
+    if n < 3 {
+        return second_derivatives;
+    }
+    // tridiagonal system for the inner points solved with the Thomas algorithm
+    let mut diagonal = vec![0.0; n];
+    let mut rhs = vec![0.0; n];
+    for i in 1..n - 1 {
+        let (h0, h1) = (tenors[i] - tenors[i - 1], tenors[i + 1] - tenors[i]);
+        diagonal[i] = 2.0 * (h0 + h1);
+        rhs[i] = 6.0 * ((rates[i + 1] - rates[i]) / h1 - (rates[i] - rates[i - 1]) / h0);
+        if i > 1 {
+            let weight = h0 / diagonal[i - 1];
+            diagonal[i] -= weight * h0;
+            rhs[i] -= weight * rhs[i - 1];
+        }
+    }
+    for i in (1..n - 1).rev() {
+        let upper = match i + 1 < n - 1 {
+            true => (tenors[i + 1] - tenors[i]) * second_derivatives[i + 1],
+            false => 0.0,
+        };
+        second_derivatives[i] = (rhs[i] - upper) / diagonal[i];
+    }
+    second_derivatives
+}
+
+fn spline(tenors: &[f64], rates: &[f64], second_derivatives: &[f64], target: f64) -> f64 {
+    let i = match segment(tenors, target) {
+        Some(i) => i,
+        None => return flat_beyond_ends(tenors, rates, target),
+    };
+    let h = tenors[i + 1] - tenors[i];
+    let (left, right) = (tenors[i + 1] - target, target - tenors[i]);
+    second_derivatives[i] * left.powi(3) / (6.0 * h)
+        + second_derivatives[i + 1] * right.powi(3) / (6.0 * h)
+        + (rates[i] / h - second_derivatives[i] * h / 6.0) * left
+        + (rates[i + 1] / h - second_derivatives[i + 1] * h / 6.0) * right
+}
+
+// y(t) = beta0 + beta1 * (1 - e^(-t/tau)) / (t/tau) + beta2 * ((1 - e^(-t/tau)) / (t/tau) - e^(-t/tau))
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NelsonSiegel {
+    pub beta0: f64,
+    pub beta1: f64,
+    pub beta2: f64,
+    pub tau: f64,
+}
+
+impl NelsonSiegel {
+    fn loadings(tenor: f64, tau: f64) -> [f64; 3] {
+        let x = tenor / tau;
+        // both loadings tend to their limits as the tenor goes to 0
+        if x < 1e-8 {
+            return [1.0, 1.0, 0.0];
+        }
+        let slope = (1.0 - (-x).exp()) / x;
+        [1.0, slope, slope - (-x).exp()]
+    }
+
+    pub fn rate(&self, tenor: f64) -> f64 {
+        let [level, slope, curvature] = Self::loadings(tenor, self.tau);
+        self.beta0 * level + self.beta1 * slope + self.beta2 * curvature
+    }
+}
+
+// least squares betas for every tau on a grid, keeping the tau with the smallest squared error
+pub fn fit_nelson_siegel(tenors: &[f64], rates: &[f64]) -> Result<NelsonSiegel> {
+    let mut best: Option<(f64, NelsonSiegel)> = None;
+    for step in NELSON_SIEGEL_TAUS {
+        let tau = step as f64 * NELSON_SIEGEL_TAU_STEP;
+        let mut normal = [[0.0; 3]; 3];
+        let mut moments = [0.0; 3];
+        for (tenor, rate) in tenors.iter().zip(rates.iter()) {
+            let loadings = NelsonSiegel::loadings(*tenor, tau);
+            for i in 0..3 {
+                moments[i] += loadings[i] * rate;
+                for j in 0..3 {
+                    normal[i][j] += loadings[i] * loadings[j];
+                }
+            }
+        }
+        let [beta0, beta1, beta2] = match solve_3x3(normal, moments) {
+            Some(betas) => betas,
+            None => continue,
+        };
+        let curve = NelsonSiegel { beta0, beta1, beta2, tau };
+        let squared_error = tenors
+            .iter()
+            .zip(rates.iter())
+            .map(|(tenor, rate)| (curve.rate(*tenor) - rate).powi(2))
+            .sum::<f64>();
+        match best {
+            Some((best_error, _)) if best_error <= squared_error => (),
+            _ => best = Some((squared_error, curve)),
+        }
+    }
+    best.map(|(_, curve)| curve)
+        .ok_or_else(|| anyhow!("fit_nelson_siegel() could not fit {} tenors, they need to be distinct", tenors.len()))
+}
+
+// gaussian elimination with partial pivoting, None when the system is singular
+fn solve_3x3(mut matrix: [[f64; 3]; 3], mut rhs: [f64; 3]) -> Option<[f64; 3]> {
+    for column in 0..3 {
+        let pivot = (column..3).max_by(|a, b| matrix[*a][column].abs().total_cmp(&matrix[*b][column].abs()))?;
+        if matrix[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        rhs.swap(column, pivot);
+        for row in column + 1..3 {
+            let weight = matrix[row][column] / matrix[column][column];
+            let pivot_row = matrix[column];
+            for (value, pivot_value) in matrix[row].iter_mut().zip(pivot_row.iter()).skip(column) {
+                *value -= weight * pivot_value;
+            }
+            rhs[row] -= weight * rhs[column];
+        }
+    }
+    let mut solution = [0.0; 3];
+    for row in (0..3).rev() {
+        let known = (row + 1..3).map(|k| matrix[row][k] * solution[k]).sum::<f64>();
+        solution[row] = (rhs[row] - known) / matrix[row][row];
+    }
+    Some(solution)
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::eod_models::OhlcvMetaData;
+
+    fn point(day: i64, tenor: &str, rate: f64) -> CurvePoint {
+        let metadata = OhlcvMetaData {
+            metadata_collection_name: "rates_curve_1d".to_string(),
+            ticker: "UST".to_string(),
+            source: "eod".to_string(),
+            exchange: "GBOND".to_string(),
+            currency: Some("USD".to_string()),
+        };
+        CurvePoint::new(bson::DateTime::from_millis(day * 86_400_000), tenor, rate, metadata).unwrap()
+    }
+
+    #[test]
+    fn test_invalid_tenors() {
+        // malformed tenors are an error whatever characters they end in, not a panic
+        for tenor in ["", "Y", "5é", "é", "5ÿ", "1Ä"] {
+            assert!(parse_tenor(tenor).is_err(), "{:?} should not parse", tenor);
+        }
+        let matrix = curve_matrix(&[point(0, "2Y", 3.0), point(0, "10Y", 4.0)]).unwrap();
+        assert!(interpolate_curve(&matrix, &["5é"], CurveInterpolation::Linear).is_err());
+    }
+
+    #[test]
+    fn test_curve_interpolation() {
+        assert_eq!(parse_tenor("6m").unwrap(), 0.5);
+        assert_eq!(parse_tenor("10Y").unwrap(), 10.0);
+        assert!(parse_tenor("10").is_err() && parse_tenor("-1Y").is_err());
+
+        // the second day has no 5Y point and points arrive in any order
+        let points = vec![point(0, "10Y", 4.0), point(0, "2Y", 3.0), point(0, "5y", 3.5), point(1, "2Y", 3.2), point(1, "10Y", 4.2)];
+        let matrix = curve_matrix(&points).unwrap();
+        assert_eq!(matrix.get_column_names(), vec!["datetime", "2Y", "5Y", "10Y"]);
+        assert_eq!(matrix.column("5Y").unwrap().null_count(), 1);
+
+        let linear = interpolate_curve(&matrix, &["1Y", "6Y", "30Y"], CurveInterpolation::Linear).unwrap();
+        let rates = linear.column("6Y").unwrap().f64().unwrap().into_no_null_iter().collect::<Vec<f64>>();
+        assert!((rates[0] - 3.6).abs() < 1e-12 && (rates[1] - 3.7).abs() < 1e-12);
+        assert_eq!(linear.column("1Y").unwrap().f64().unwrap().get(0), Some(3.0));
+        assert_eq!(linear.column("30Y").unwrap().f64().unwrap().get(1), Some(4.2));
+
+        // a spline through points on a line is the line
+        let tenors = [1.0, 2.0, 5.0, 10.0];
+        let line = tenors.iter().map(|tenor| 1.0 + 0.1 * tenor).collect::<Vec<f64>>();
+        let spline = interpolate_rates(&tenors, &line, &[1.0, 3.0, 7.5], CurveInterpolation::CubicSpline);
+        for (rate, expected) in spline.iter().zip([1.1, 1.3, 1.75]) {
+            assert!((rate.unwrap() - expected).abs() < 1e-12);
+        }
+        let curved = [3.0, 3.4, 3.5, 4.0];
+        let spline = interpolate_rates(&tenors, &curved, &tenors, CurveInterpolation::CubicSpline);
+        assert_eq!(spline.into_iter().flatten().collect::<Vec<f64>>(), curved.to_vec());
+
+        // a curve that is Nelson-Siegel is recovered
+        let curve = NelsonSiegel { beta0: 4.5, beta1: -1.5, beta2: 1.0, tau: 2.0 };
+        let tenors = [0.25, 0.5, 1.0, 2.0, 3.0, 5.0, 7.0, 10.0, 30.0];
+        let rates = tenors.iter().map(|tenor| curve.rate(*tenor)).collect::<Vec<f64>>();
+        let fitted = fit_nelson_siegel(&tenors, &rates).unwrap();
+        assert!((fitted.tau - 2.0).abs() < 1e-9);
+        assert!((fitted.rate(20.0) - curve.rate(20.0)).abs() < 1e-9);
+        let nelson_siegel = interpolate_curve(&matrix, &["7Y"], CurveInterpolation::NelsonSiegel).unwrap();
+        assert!(nelson_siegel.column("7Y").unwrap().f64().unwrap().get(0).is_some());
+        assert_eq!(nelson_siegel.column("7Y").unwrap().null_count(), 1);
+    }
+}
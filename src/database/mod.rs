@@ -1,3 +1,4 @@
+pub mod config;
 pub mod data_apis;
 pub mod database_service;
 pub mod models;
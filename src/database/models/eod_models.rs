@@ -12,7 +12,8 @@ pub struct Ohlcv {
     pub low: f64,
     pub close: f64,
     pub adjusted_close: f64,
-    pub volume: i32,
+    // a float so crypto volumes keep their fractional part and equity volumes cannot overflow
+    pub volume: f64,
     pub metadata: OhlcvMetaData,
 }
 
@@ -25,6 +26,19 @@ pub struct TimeseriesMetaDataStruct {
     pub from: DateTime,
     pub to: DateTime,
     pub last_updated: DateTime,
+    // IANA timezone the source reports naive datetimes in, and the one the exchange trades in.
+    // Series rows are always stored in UTC, metadata written before these existed reads as UTC
+    #[serde(default = "default_timezone")]
+    pub source_timezone: String,
+    #[serde(default = "default_timezone")]
+    pub exchange_timezone: String,
+    // ISO 4217 code, or minor unit such as GBX, prices are quoted in. None where it is not known
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -54,7 +68,37 @@ pub struct ReadSeriesFromMongoDb {
     pub low: f64,
     pub close: f64,
     pub adjusted_close: Option<f64>,
-    pub volume: Option<i64>,
+    pub volume: Option<f64>,
     pub datetime: DateTime,
-    pub metadata: OhlcvMetaData,
-}
\ No newline at end of file
+    // projected away when reading, as it is the same for every row of a series
+    pub metadata: Option<OhlcvMetaData>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SeriesVerification {
+    pub ticker: String,
+    pub exchange: String,
+    pub series_collection_name: String,
+    pub source: String,
+    pub metadata_documents: usize,
+    pub rows: i64,
+    pub duplicate_datetimes: i64,
+    pub series_from: Option<DateTime>,
+    pub series_to: Option<DateTime>,
+    pub metadata: Option<TimeseriesMetaDataStruct>,
+}
+
+impl SeriesVerification {
+    // a series is consistent when it has exactly one metadata document whose dates match the stored rows
+    pub fn is_consistent(&self) -> bool {
+        match &self.metadata {
+            Some(metadata) => {
+                self.metadata_documents == 1
+                    && self.duplicate_datetimes == 0
+                    && self.series_from == Some(metadata.from)
+                    && self.series_to == Some(metadata.to)
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+/*
+    Corporate Event Models, registered in the events database and linked to instruments by the ticker,
+    exchange and source of their TimeseriesMetaDataStruct, e.g., AAPL, US, eod. Dates are the calendar date
+    of the event in exchange time at midnight UTC, the same datetime as the daily bar of that date.
+*/
+// when in the trading day an announcement is made
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnouncementTime {
+    BeforeMarket,
+    DuringMarket,
+    AfterMarket,
+    Unknown,
+}
+
+// an earnings announcement, keyed by the instrument and the end of the fiscal period it reports on
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EarningsEvent {
+    pub ticker: String,
+    pub exchange: String,
+    pub source: String,
+    pub fiscal_period_end: DateTime,
+    pub announcement_date: DateTime,
+    pub time_of_day: AnnouncementTime,
+    pub currency: Option<String>,
+    // earnings per share, estimated by consensus and as reported
+    pub estimate: Option<f64>,
+    pub actual: Option<f64>,
+    // actual less estimate, and as a percentage of the estimate
+    pub surprise: Option<f64>,
+    pub surprise_percent: Option<f64>,
+    // the announcement date was final when last fetched, i.e., the earnings had been reported on it
+    pub confirmed: bool,
+    // number of times the announcement date moved after it was first fetched
+    pub date_changes: i32,
+    pub first_seen: DateTime,
+    pub last_updated: DateTime,
+}
+
+// an initial public offering, keyed by the instrument it lists as
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IpoEvent {
+    pub ticker: String,
+    pub exchange: String,
+    pub source: String,
+    pub name: String,
+    // venue the offering lists on as reported, e.g., NASDAQ for exchange US
+    pub venue: String,
+    // first day of trading, expected until deal_type is Priced
+    pub listing_date: Option<DateTime>,
+    pub filing_date: Option<DateTime>,
+    pub amended_date: Option<DateTime>,
+    pub price_from: Option<f64>,
+    pub price_to: Option<f64>,
+    pub offer_price: Option<f64>,
+    pub shares: Option<f64>,
+    // Filed, Expected, Priced or Withdrawn
+    pub deal_type: String,
+    pub currency: Option<String>,
+    pub last_updated: DateTime,
+}
+
+// a share split, or a reverse split if new_shares is less than old_shares, keyed by the instrument and split date
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SplitEvent {
+    pub ticker: String,
+    pub exchange: String,
+    pub source: String,
+    // first day the shares trade split
+    pub split_date: DateTime,
+    pub old_shares: f64,
+    pub new_shares: f64,
+    pub last_updated: DateTime,
+}
+
+impl SplitEvent {
+    // factor prices before the split are multiplied by to be comparable with prices after, e.g., 0.25 for 4:1
+    pub fn price_factor(&self) -> f64 {
+        self.old_shares / self.new_shares
+    }
+}
@@ -0,0 +1,61 @@
+use bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+/*
+    Futures Models. The bars of each contract are a series of their own in a collection with futures
+    as its data format, e.g., equity_futures_1d, with the contract ticker as their ticker.
+*/
+// a single contract of a root, registered in the metadata database and keyed by root, exchange and expiry
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FuturesContractSpec {
+    // e.g., ES
+    pub root: String,
+    // ticker the contract's series is stored under, e.g., ESH24
+    pub ticker: String,
+    pub exchange: String,
+    pub series_collection_name: String,
+    pub source: String,
+    // expiration date, which orders the contracts of a root
+    pub expiry: DateTime,
+    // last day the contract trades, usually the expiry
+    pub last_trade: DateTime,
+    // first day a long can be assigned delivery, physically settled contracts only
+    pub first_notice: Option<DateTime>,
+    pub multiplier: f64,
+    pub tick_size: f64,
+    pub currency: Option<String>,
+}
+
+impl FuturesContractSpec {
+    // the day positions have to be out of the contract by, first notice if it has one
+    pub fn roll_deadline(&self) -> DateTime {
+        match self.first_notice {
+            Some(first_notice) if first_notice < self.last_trade => first_notice,
+            _ => self.last_trade,
+        }
+    }
+}
+
+// when a continuous series moves from one contract to the next
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RollRule {
+    // this many calendar days before the roll deadline of the held contract
+    DaysBeforeExpiry(i64),
+    // the day after the next contract trades more volume than the held one
+    VolumeSwitch,
+    // the day after the next contract has more open interest than the held one
+    OpenInterestSwitch,
+}
+
+// how prices before a roll are adjusted to remove the gap between the two contracts
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AdjustmentMethod {
+    // add the difference between the new and old contract's close
+    Difference,
+    // multiply by the ratio of the new and old contract's close, which keeps returns intact
+    Ratio,
+    // leave the gaps in
+    None,
+}
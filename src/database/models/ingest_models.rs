@@ -0,0 +1,190 @@
+use bson::DateTime;
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::database::models::eod_models::{MongoTickerParams, TimeseriesMetaDataStruct};
+use crate::database::utility_functions::get_current_datetime_bson;
+
+/*
+    Ingestion Report Models
+*/
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum IngestAction {
+    Created,
+    Refreshed,
+    UpToDate,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Coverage {
+    pub from: DateTime,
+    pub to: DateTime,
+    pub last_updated: DateTime,
+}
+
+impl From<&TimeseriesMetaDataStruct> for Coverage {
+    fn from(metadata: &TimeseriesMetaDataStruct) -> Self {
+        Self {
+            from: metadata.from,
+            to: metadata.to,
+            last_updated: metadata.last_updated,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TickerReport {
+    pub ticker: String,
+    pub exchange: String,
+    pub series_collection_name: String,
+    pub source: String,
+    pub action: IngestAction,
+    pub rows_fetched: usize,
+    pub rows_written: usize,
+    pub coverage_before: Option<Coverage>,
+    pub coverage_after: Option<Coverage>,
+    pub errors: Vec<String>,
+}
+
+impl TickerReport {
+    pub fn new(ticker: &MongoTickerParams) -> Self {
+        Self {
+            ticker: ticker.ticker.clone(),
+            exchange: ticker.exchange.clone(),
+            series_collection_name: ticker.series_collection_name.clone(),
+            source: ticker.source.clone(),
+            action: IngestAction::UpToDate,
+            rows_fetched: 0,
+            rows_written: 0,
+            coverage_before: None,
+            coverage_after: None,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn is_for(&self, ticker: &str, exchange: &str, series_collection_name: &str, source: &str) -> bool {
+        self.ticker == ticker
+            && self.exchange == exchange
+            && self.series_collection_name == series_collection_name
+            && self.source == source
+    }
+
+    // errors are kept as strings so the report can be serialised and shipped to alerting as is
+    pub fn fail(&mut self, error: &anyhow::Error) {
+        self.action = IngestAction::Failed;
+        self.errors.push(format!("{:#}", error));
+    }
+
+    // counts the rows of a df fetched for the ticker and how many of them were inserted
+    pub fn record_insert(&mut self, rows_fetched: usize, inserted: anyhow::Result<usize>) {
+        self.rows_fetched += rows_fetched;
+        match inserted {
+            Ok(rows_written) => self.rows_written += rows_written,
+            Err(e) => self.fail(&e),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct IngestTimings {
+    pub metadata_lookup: Duration,
+    pub fetch: Duration,
+    pub insert: Duration,
+    pub metadata_update: Duration,
+    pub read: Duration,
+    pub total: Duration,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct IngestReport {
+    pub started_at: DateTime,
+    pub finished_at: DateTime,
+    pub tickers: Vec<TickerReport>,
+    pub timings: IngestTimings,
+}
+
+impl IngestReport {
+    pub fn new() -> Self {
+        let now = get_current_datetime_bson();
+        Self {
+            started_at: now,
+            finished_at: now,
+            tickers: Vec::new(),
+            timings: IngestTimings::default(),
+        }
+    }
+
+    pub fn ticker_mut(
+        &mut self,
+        ticker: &str,
+        exchange: &str,
+        series_collection_name: &str,
+        source: &str,
+    ) -> Option<&mut TickerReport> {
+        self.tickers
+            .iter_mut()
+            .find(|report| report.is_for(ticker, exchange, series_collection_name, source))
+    }
+
+    pub fn failed(&self) -> Vec<&TickerReport> {
+        self.tickers
+            .iter()
+            .filter(|report| report.action == IngestAction::Failed)
+            .collect()
+    }
+
+    pub fn has_failures(&self) -> bool {
+        self.tickers
+            .iter()
+            .any(|report| report.action == IngestAction::Failed)
+    }
+}
+
+impl Default for IngestReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    fn ticker(ticker: &str) -> MongoTickerParams {
+        MongoTickerParams {
+            ticker: ticker.to_string(),
+            exchange: "US".to_string(),
+            series_collection_name: "equity_spot_1d".to_string(),
+            source: "eod".to_string(),
+            from: DateTime::from_millis(0),
+            to: DateTime::from_millis(0),
+        }
+    }
+
+    #[test]
+    fn test_ingest_report() {
+        let mut report = IngestReport::new();
+        report.tickers.push(TickerReport::new(&ticker("AAPL")));
+        report.tickers.push(TickerReport::new(&ticker("MSFT")));
+        assert!(!report.has_failures());
+
+        // rows of every df of a ticker add up, intraday tickers get one df per request
+        let aapl = report.ticker_mut("AAPL", "US", "equity_spot_1d", "eod").unwrap();
+        aapl.record_insert(250, Ok(250));
+        aapl.record_insert(10, Ok(10));
+        assert_eq!((aapl.rows_fetched, aapl.rows_written, aapl.action), (260, 260, IngestAction::UpToDate));
+
+        // a failed insert counts the rows fetched but none written, and fails the ticker alone
+        let msft = report.ticker_mut("MSFT", "US", "equity_spot_1d", "eod").unwrap();
+        msft.record_insert(5, Err(anyhow!("insert_many() failed")));
+        assert_eq!((msft.rows_fetched, msft.rows_written), (5, 0));
+        assert!(report.has_failures());
+        let failed = report.failed();
+        assert_eq!(failed.len(), 1);
+        assert_eq!((failed[0].ticker.as_str(), failed[0].errors.clone()), ("MSFT", vec!["insert_many() failed".to_string()]));
+        assert!(report.ticker_mut("AAPL", "US", "equity_spot_1h", "eod").is_none());
+    }
+}
@@ -0,0 +1,39 @@
+use bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::eod_models::OhlcvMetaData;
+
+/*
+    Macro Indicator Models, stored in collections with indicator as their data format, e.g., macro_indicator_1d.
+    The ticker is the indicator, e.g., inflation_consumer_prices_annual, and the exchange the ISO 3166 alpha-3
+    country code, e.g., USA.
+*/
+// a value as a datasource reports it
+#[derive(Debug, Clone, PartialEq)]
+pub struct MacroValue {
+    // end of the period the value measures, e.g., 2023-12-31 for 2023
+    pub reference_period: DateTime,
+    // Annual, Quarterly or Monthly, as reported
+    pub period: String,
+    pub value: f64,
+    // when the value was published, if the datasource says
+    pub release_date: Option<DateTime>,
+}
+
+/*
+    A vintage of a value, stored under its release date so a read as of a date only sees what was
+    published by then. Every revision of a reference period is a vintage of its own.
+*/
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MacroObservation {
+    // release date of this vintage
+    pub datetime: DateTime,
+    pub reference_period: DateTime,
+    pub period: String,
+    pub value: f64,
+    // 0 for the first release of a reference period, counting up with every revision
+    pub revision: i32,
+    // the release date was not published or observed, see macro_series::next_vintages()
+    pub release_estimated: bool,
+    pub metadata: OhlcvMetaData,
+}
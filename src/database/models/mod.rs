@@ -1 +1,13 @@
-pub mod eod_models;
\ No newline at end of file
+pub mod eod_models;
+pub mod event_models;
+pub mod futures_models;
+pub mod ingest_models;
+pub mod macro_models;
+pub mod news_models;
+pub mod option_models;
+pub mod order_book_models;
+pub mod quote_models;
+pub mod rates_models;
+pub mod schema_models;
+pub mod synthetic_models;
+pub mod tick_models;
\ No newline at end of file
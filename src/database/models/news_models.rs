@@ -0,0 +1,23 @@
+use bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::eod_models::OhlcvMetaData;
+
+/*
+    News Models, stored in collections with news as their data format, e.g., equity_news_items. An item
+    that mentions several tickers is stored once under the metadata of each of them.
+*/
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NewsItem {
+    // when the item was published
+    pub datetime: DateTime,
+    pub headline: String,
+    // publisher of the item, e.g., finance.yahoo.com, not the datasource it was fetched from
+    pub source: String,
+    pub url: Option<String>,
+    // every instrument the item mentions as TICKER.EXCHANGE codes, e.g., AAPL.US
+    pub tickers: Vec<String>,
+    // polarity of the item from -1, negative, to 1, positive, as scored by the datasource
+    pub sentiment: Option<f64>,
+    pub metadata: OhlcvMetaData,
+}
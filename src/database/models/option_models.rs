@@ -0,0 +1,74 @@
+use bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::eod_models::OhlcvMetaData;
+
+/*
+    Option Models, stored in collections with options as their data format, e.g., equity_options_1d
+*/
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OptionRight {
+    Call,
+    Put,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OptionContract {
+    pub underlying: String,
+    // midnight UTC of the expiration date, like daily bars
+    pub expiry: DateTime,
+    pub strike: f64,
+    pub right: OptionRight,
+    // units of the underlying per contract, 100 for US equity options
+    pub multiplier: f64,
+}
+
+impl OptionContract {
+    // OCC style symbol, e.g., AAPL240119C00190000 for the 190 call expiring 2024-01-19
+    pub fn symbol(&self) -> String {
+        let right = match self.right {
+            OptionRight::Call => 'C',
+            OptionRight::Put => 'P',
+        };
+        format!(
+            "{}{}{}{:08}",
+            self.underlying,
+            self.expiry.to_chrono().format("%y%m%d"),
+            right,
+            (self.strike * 1000.0).round() as i64
+        )
+    }
+}
+
+/*
+    A contract in the daily chain snapshot of an underlying. The metadata is that of the underlying,
+    so a chain is one series per underlying and a day's chain is read with a single datetime filter.
+*/
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OptionQuote {
+    pub datetime: DateTime,
+    pub contract: OptionContract,
+    pub bid: Option<f64>,
+    pub ask: Option<f64>,
+    pub last: Option<f64>,
+    pub volume: Option<f64>,
+    pub open_interest: Option<f64>,
+    pub implied_volatility: Option<f64>,
+    pub delta: Option<f64>,
+    pub gamma: Option<f64>,
+    pub theta: Option<f64>,
+    pub vega: Option<f64>,
+    pub metadata: OhlcvMetaData,
+}
+
+// narrows a chain down to the contracts of interest, every filter left as None matches all contracts
+#[derive(Debug, Clone, Default)]
+pub struct ChainFilter {
+    // days from the snapshot date to expiry, inclusive
+    pub min_days_to_expiry: Option<i64>,
+    pub max_days_to_expiry: Option<i64>,
+    pub min_strike: Option<f64>,
+    pub max_strike: Option<f64>,
+    pub right: Option<OptionRight>,
+}
@@ -0,0 +1,114 @@
+use anyhow::{anyhow, Result};
+use bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::eod_models::OhlcvMetaData;
+
+/*
+    Order Book Models, stored in collections ending in _book_snapshots, e.g., crypto_book_snapshots
+*/
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct BookLevel {
+    pub price: f64,
+    pub size: f64,
+}
+
+/*
+    L2 depth of a ticker at a point in time. Each side is stored as parallel price and size arrays
+    ordered best price first, which is a fraction of the size of an array of level documents and lets
+    reads project the top N levels with $slice.
+*/
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrderBookSnapshot {
+    pub datetime: DateTime,
+    // the venue's update id, gaps between consecutive snapshots mean updates were missed
+    pub sequence: i64,
+    pub bid_prices: Vec<f64>,
+    pub bid_sizes: Vec<f64>,
+    pub ask_prices: Vec<f64>,
+    pub ask_sizes: Vec<f64>,
+    pub metadata: OhlcvMetaData,
+}
+
+impl OrderBookSnapshot {
+    // builds a snapshot from levels in any order, empty levels are dropped
+    pub fn from_levels(
+        datetime: DateTime,
+        sequence: i64,
+        mut bids: Vec<BookLevel>,
+        mut asks: Vec<BookLevel>,
+        metadata: OhlcvMetaData,
+    ) -> Self {
+        bids.retain(|level| level.size > 0.0);
+        asks.retain(|level| level.size > 0.0);
+        bids.sort_by(|a, b| b.price.total_cmp(&a.price));
+        asks.sort_by(|a, b| a.price.total_cmp(&b.price));
+        Self {
+            datetime,
+            sequence,
+            bid_prices: bids.iter().map(|level| level.price).collect(),
+            bid_sizes: bids.iter().map(|level| level.size).collect(),
+            ask_prices: asks.iter().map(|level| level.price).collect(),
+            ask_sizes: asks.iter().map(|level| level.size).collect(),
+            metadata,
+        }
+    }
+
+    pub fn bids(&self) -> Vec<BookLevel> {
+        levels(&self.bid_prices, &self.bid_sizes)
+    }
+
+    pub fn asks(&self) -> Vec<BookLevel> {
+        levels(&self.ask_prices, &self.ask_sizes)
+    }
+
+    // checks the layout invariants that reads rely on, snapshots are validated before they are stored
+    pub fn validate(&self) -> Result<()> {
+        if self.bid_prices.len() != self.bid_sizes.len() || self.ask_prices.len() != self.ask_sizes.len() {
+            return Err(anyhow!("OrderBookSnapshot {} has a different number of prices and sizes", self.sequence));
+        }
+        if self.bid_prices.windows(2).any(|pair| pair[0] <= pair[1]) || self.ask_prices.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(anyhow!("OrderBookSnapshot {} levels are not ordered best price first", self.sequence));
+        }
+        if let (Some(bid), Some(ask)) = (self.best_bid(), self.best_ask()) {
+            if bid >= ask {
+                return Err(anyhow!("OrderBookSnapshot {} is crossed, bid {} ask {}", self.sequence, bid, ask));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn best_bid(&self) -> Option<f64> {
+        self.bid_prices.first().copied()
+    }
+
+    pub fn best_ask(&self) -> Option<f64> {
+        self.ask_prices.first().copied()
+    }
+
+    pub fn mid(&self) -> Option<f64> {
+        Some((self.best_bid()? + self.best_ask()?) / 2.0)
+    }
+
+    pub fn spread(&self) -> Option<f64> {
+        Some(self.best_ask()? - self.best_bid()?)
+    }
+
+    // (bid size - ask size) / (bid size + ask size) over the top depth levels, from -1 (all asks) to 1 (all bids)
+    pub fn imbalance(&self, depth: usize) -> Option<f64> {
+        let bid_size: f64 = self.bid_sizes.iter().take(depth).sum();
+        let ask_size: f64 = self.ask_sizes.iter().take(depth).sum();
+        match bid_size + ask_size > 0.0 {
+            true => Some((bid_size - ask_size) / (bid_size + ask_size)),
+            false => None,
+        }
+    }
+}
+
+fn levels(prices: &[f64], sizes: &[f64]) -> Vec<BookLevel> {
+    prices
+        .iter()
+        .zip(sizes.iter())
+        .map(|(price, size)| BookLevel { price: *price, size: *size })
+        .collect()
+}
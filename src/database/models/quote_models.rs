@@ -0,0 +1,22 @@
+use bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::eod_models::OhlcvMetaData;
+
+/*
+    Live Quote Models
+*/
+// a snapshot of a ticker as stored in a live collection, e.g., equity_spot_live
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Quote {
+    pub datetime: DateTime,
+    pub last: f64,
+    // not every source quotes the top of the book, EOD's real-time api does not
+    pub bid: Option<f64>,
+    pub ask: Option<f64>,
+    // change and change_percent are against the previous close
+    pub change: Option<f64>,
+    pub change_percent: Option<f64>,
+    pub volume: Option<f64>,
+    pub metadata: OhlcvMetaData,
+}
@@ -0,0 +1,33 @@
+use anyhow::Result;
+use bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::eod_models::OhlcvMetaData;
+use crate::database::utility_functions::parse_tenor;
+
+/*
+    Rates Models, stored in collections with curve as their data format, e.g., rates_curve_1d
+*/
+// the yield of one tenor of a curve on a date, the curve is the ticker in the metadata, e.g., UST
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CurvePoint {
+    pub datetime: DateTime,
+    // as quoted, e.g., 3M or 10Y, which names the tenor's column in curve reads
+    pub tenor: String,
+    pub tenor_years: f64,
+    // in percent, as curves are published
+    pub rate: f64,
+    pub metadata: OhlcvMetaData,
+}
+
+impl CurvePoint {
+    pub fn new(datetime: DateTime, tenor: &str, rate: f64, metadata: OhlcvMetaData) -> Result<Self> {
+        Ok(Self {
+            datetime,
+            tenor: tenor.trim().to_uppercase(),
+            tenor_years: parse_tenor(tenor)?,
+            rate,
+            metadata,
+        })
+    }
+}
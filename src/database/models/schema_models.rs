@@ -0,0 +1,315 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/*
+    Collection Name Models
+*/
+// what a series collection holds, going by the data format in its {asset}_{format}_{interval} name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectionKind {
+    Bars,
+    Futures,
+    LiveQuotes,
+    TradeTicks,
+    QuoteTicks,
+    BookSnapshots,
+    OptionQuotes,
+    CurvePoints,
+    MacroIndicators,
+    NewsItems,
+    DailySentiment,
+}
+
+// the interval of a collection, e.g., 1d or 5m
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    Seconds(i64),
+    Minutes(i64),
+    Hours(i64),
+    Days(i64),
+}
+
+impl Granularity {
+    pub fn parse(interval: &str) -> Option<Self> {
+        // split by characters, collection names come from users and may end in a multibyte character
+        let (amount, unit) = interval.split_at(interval.char_indices().last()?.0);
+        let amount = amount.parse::<i64>().ok().filter(|amount| *amount > 0)?;
+        match unit {
+            "s" => Some(Granularity::Seconds(amount)),
+            "m" => Some(Granularity::Minutes(amount)),
+            "h" => Some(Granularity::Hours(amount)),
+            "d" => Some(Granularity::Days(amount)),
+            _ => None,
+        }
+    }
+
+    pub fn millis(&self) -> i64 {
+        match self {
+            Granularity::Seconds(amount) => amount * 1_000,
+            Granularity::Minutes(amount) => amount * 60_000,
+            Granularity::Hours(amount) => amount * 3_600_000,
+            Granularity::Days(amount) => amount * 86_400_000,
+        }
+    }
+}
+
+// the interval as it is written in collection names and datasource requests
+impl fmt::Display for Granularity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Granularity::Seconds(amount) => write!(f, "{}s", amount),
+            Granularity::Minutes(amount) => write!(f, "{}m", amount),
+            Granularity::Hours(amount) => write!(f, "{}h", amount),
+            Granularity::Days(amount) => write!(f, "{}d", amount),
+        }
+    }
+}
+
+// a collection name parsed once, so what it holds is matched on rather than its suffix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollectionType {
+    pub kind: CollectionKind,
+    // None for collections without a fixed interval, e.g., ticks, live quotes or news items
+    pub granularity: Option<Granularity>,
+}
+
+impl CollectionType {
+    pub fn parse(collection_name: &str) -> Self {
+        let suffix = collection_name.rsplit('_').next().unwrap_or_default();
+        let kind = match collection_name {
+            _ if collection_name.ends_with("_trade_ticks") => CollectionKind::TradeTicks,
+            _ if collection_name.ends_with("_quote_ticks") => CollectionKind::QuoteTicks,
+            _ if collection_name.ends_with("_book_snapshots") => CollectionKind::BookSnapshots,
+            _ if collection_name.contains("_options_") => CollectionKind::OptionQuotes,
+            _ if collection_name.contains("_futures_") => CollectionKind::Futures,
+            _ if collection_name.contains("_curve_") => CollectionKind::CurvePoints,
+            _ if collection_name.contains("_indicator_") => CollectionKind::MacroIndicators,
+            _ if collection_name.contains("_news_") => CollectionKind::NewsItems,
+            _ if collection_name.contains("_sentiment_") => CollectionKind::DailySentiment,
+            // live quote collections end in _live, e.g., equity_spot_live
+            _ if suffix == "live" => CollectionKind::LiveQuotes,
+            _ => CollectionKind::Bars,
+        };
+        Self {
+            kind,
+            granularity: Granularity::parse(suffix),
+        }
+    }
+
+    pub fn is_daily(&self) -> bool {
+        matches!(self.granularity, Some(Granularity::Days(_)))
+    }
+}
+
+/*
+    Collection Schema Models
+*/
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    Float,
+    Integer,
+    Boolean,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FieldSchema {
+    pub name: String,
+    pub field_type: FieldType,
+}
+
+impl FieldSchema {
+    pub fn new(name: &str, field_type: FieldType) -> Self {
+        Self {
+            name: name.to_string(),
+            field_type,
+        }
+    }
+}
+
+// the fields a series collection holds next to its datetime, in the order reads return them
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct CollectionSchema {
+    pub collection_name: String,
+    pub fields: Vec<FieldSchema>,
+}
+
+impl CollectionSchema {
+    // schema of a collection that has not registered one, going by the suffix of its name
+    pub fn default_for(collection_name: &str) -> Self {
+        match CollectionType::parse(collection_name).kind {
+            CollectionKind::TradeTicks => Self::trade_ticks(collection_name),
+            CollectionKind::QuoteTicks => Self::quote_ticks(collection_name),
+            CollectionKind::BookSnapshots => Self::book_snapshots(collection_name),
+            CollectionKind::OptionQuotes => Self::option_quotes(collection_name),
+            CollectionKind::Futures => Self::futures(collection_name),
+            CollectionKind::CurvePoints => Self::curve_points(collection_name),
+            CollectionKind::MacroIndicators => Self::macro_observations(collection_name),
+            CollectionKind::NewsItems => Self::news_items(collection_name),
+            CollectionKind::DailySentiment => Self::daily_sentiment(collection_name),
+            CollectionKind::LiveQuotes => Self::quote(collection_name),
+            CollectionKind::Bars => Self::ohlcv(collection_name),
+        }
+    }
+
+    pub fn ohlcv(collection_name: &str) -> Self {
+        Self {
+            collection_name: collection_name.to_string(),
+            fields: vec![
+                FieldSchema::new("open", FieldType::Float),
+                FieldSchema::new("high", FieldType::Float),
+                FieldSchema::new("low", FieldType::Float),
+                FieldSchema::new("close", FieldType::Float),
+                FieldSchema::new("volume", FieldType::Float),
+                FieldSchema::new("adjusted_close", FieldType::Float),
+            ],
+        }
+    }
+
+    // bars of a single futures contract, which have open interest instead of an adjusted close
+    pub fn futures(collection_name: &str) -> Self {
+        Self {
+            collection_name: collection_name.to_string(),
+            fields: vec![
+                FieldSchema::new("open", FieldType::Float),
+                FieldSchema::new("high", FieldType::Float),
+                FieldSchema::new("low", FieldType::Float),
+                FieldSchema::new("close", FieldType::Float),
+                FieldSchema::new("volume", FieldType::Float),
+                FieldSchema::new("open_interest", FieldType::Float),
+            ],
+        }
+    }
+
+    // the fields of a Quote
+    pub fn quote(collection_name: &str) -> Self {
+        Self {
+            collection_name: collection_name.to_string(),
+            fields: vec![
+                FieldSchema::new("last", FieldType::Float),
+                FieldSchema::new("bid", FieldType::Float),
+                FieldSchema::new("ask", FieldType::Float),
+                FieldSchema::new("change", FieldType::Float),
+                FieldSchema::new("change_percent", FieldType::Float),
+                FieldSchema::new("volume", FieldType::Float),
+            ],
+        }
+    }
+
+    // the numeric fields of a TradeTick, conditions and exchange are not read back as columns
+    pub fn trade_ticks(collection_name: &str) -> Self {
+        Self {
+            collection_name: collection_name.to_string(),
+            fields: vec![
+                FieldSchema::new("price", FieldType::Float),
+                FieldSchema::new("size", FieldType::Float),
+            ],
+        }
+    }
+
+    // the fields of a QuoteTick
+    pub fn quote_ticks(collection_name: &str) -> Self {
+        Self {
+            collection_name: collection_name.to_string(),
+            fields: vec![
+                FieldSchema::new("bid", FieldType::Float),
+                FieldSchema::new("ask", FieldType::Float),
+                FieldSchema::new("bid_size", FieldType::Float),
+                FieldSchema::new("ask_size", FieldType::Float),
+            ],
+        }
+    }
+
+    // the scalar fields of an OrderBookSnapshot, its levels are read with read_book_at() and read_book_series()
+    pub fn book_snapshots(collection_name: &str) -> Self {
+        Self {
+            collection_name: collection_name.to_string(),
+            fields: vec![FieldSchema::new("sequence", FieldType::Integer)],
+        }
+    }
+
+    // the quote fields of an OptionQuote, the contract of each row is read with read_option_chain()
+    pub fn option_quotes(collection_name: &str) -> Self {
+        Self {
+            collection_name: collection_name.to_string(),
+            fields: ["bid", "ask", "last", "volume", "open_interest", "implied_volatility", "delta", "gamma", "theta", "vega"]
+                .iter()
+                .map(|name| FieldSchema::new(name, FieldType::Float))
+                .collect(),
+        }
+    }
+
+    // the numeric fields of a CurvePoint, read_curve() returns them as a date x tenor matrix instead
+    pub fn curve_points(collection_name: &str) -> Self {
+        Self {
+            collection_name: collection_name.to_string(),
+            fields: vec![
+                FieldSchema::new("tenor_years", FieldType::Float),
+                FieldSchema::new("rate", FieldType::Float),
+            ],
+        }
+    }
+
+    // the numeric fields of a MacroObservation, read_macro_series() gives point in time reads instead
+    pub fn macro_observations(collection_name: &str) -> Self {
+        Self {
+            collection_name: collection_name.to_string(),
+            fields: vec![
+                FieldSchema::new("value", FieldType::Float),
+                FieldSchema::new("revision", FieldType::Integer),
+            ],
+        }
+    }
+
+    // the numeric field of a NewsItem, read_news() gives the headlines and tickers too
+    pub fn news_items(collection_name: &str) -> Self {
+        Self {
+            collection_name: collection_name.to_string(),
+            fields: vec![FieldSchema::new("sentiment", FieldType::Float)],
+        }
+    }
+
+    // mean sentiment of the news about a ticker on each day, with the number of items it is the mean of
+    pub fn daily_sentiment(collection_name: &str) -> Self {
+        Self {
+            collection_name: collection_name.to_string(),
+            fields: vec![
+                FieldSchema::new("sentiment", FieldType::Float),
+                FieldSchema::new("count", FieldType::Integer),
+            ],
+        }
+    }
+
+    pub fn field_names(&self) -> Vec<&str> {
+        self.fields.iter().map(|field| field.name.as_str()).collect()
+    }
+}
+
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collection_type() {
+        let parse = |collection_name: &str| {
+            let collection = CollectionType::parse(collection_name);
+            (collection.kind, collection.granularity)
+        };
+        assert_eq!(parse("equity_spot_1d"), (CollectionKind::Bars, Some(Granularity::Days(1))));
+        assert_eq!(parse("crypto_spot_15m"), (CollectionKind::Bars, Some(Granularity::Minutes(15))));
+        assert_eq!(parse("commodity_futures_1h"), (CollectionKind::Futures, Some(Granularity::Hours(1))));
+        assert_eq!(parse("equity_spot_live"), (CollectionKind::LiveQuotes, None));
+        assert_eq!(parse("equity_spot_trade_ticks"), (CollectionKind::TradeTicks, None));
+        assert_eq!(parse("equity_news_items"), (CollectionKind::NewsItems, None));
+        assert_eq!(parse("equity_sentiment_1d"), (CollectionKind::DailySentiment, Some(Granularity::Days(1))));
+        // names that merely end in the letter of a live collection or an interval are not one
+        assert_eq!(parse("equity_spot_store"), (CollectionKind::Bars, None));
+        assert_eq!(parse("equity_spot_d"), (CollectionKind::Bars, None));
+        assert_eq!(parse("equity_spot_1é"), (CollectionKind::Bars, None));
+        assert_eq!(parse("équity_spot_é"), (CollectionKind::Bars, None));
+        assert_eq!(Granularity::Minutes(5).to_string(), "5m");
+        assert_eq!(Granularity::Hours(2).millis(), 7_200_000);
+    }
+}
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/*
+    Synthetic Data Models, the settings of the synthetic datasource, see data_apis::synthetic. Drifts and
+    volatilities are annual, of log prices, over 252 trading days a year.
+*/
+// a state of a regime switching model
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Regime {
+    pub drift: f64,
+    pub volatility: f64,
+}
+
+// the process daily closes follow
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PriceModel {
+    // geometric brownian motion
+    Gbm { drift: f64, volatility: f64 },
+    // geometric brownian motion with normally distributed jumps in log price arriving jump_intensity times a year
+    JumpDiffusion {
+        drift: f64,
+        volatility: f64,
+        jump_intensity: f64,
+        jump_mean: f64,
+        jump_volatility: f64,
+    },
+    // geometric brownian motion whose drift and volatility move to another regime with switch_probability each day
+    RegimeSwitching { regimes: Vec<Regime>, switch_probability: f64 },
+}
+
+impl Default for PriceModel {
+    fn default() -> Self {
+        PriceModel::Gbm {
+            drift: 0.07,
+            volatility: 0.2,
+        }
+    }
+}
+
+/*
+    Defects injected into the bars the datasource returns, each with a probability per bar. They never change
+    the underlying path, so a series with defects is the same series as one without with bars missing or spoiled.
+*/
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Defects {
+    // an outage of 1 to max_gap_bars bars starts
+    pub gap_probability: f64,
+    pub max_gap_bars: usize,
+    // the shares split, 2:1, 3:1 or a 1:2 reverse split, daily bars only
+    pub split_probability: f64,
+    // the bar is spoiled: its close 10 times or a tenth of what it is, zero, or its high and low swapped
+    pub bad_tick_probability: f64,
+}
+
+impl Default for Defects {
+    fn default() -> Self {
+        Self {
+            gap_probability: 0.0,
+            max_gap_bars: 5,
+            split_probability: 0.0,
+            bad_tick_probability: 0.0,
+        }
+    }
+}
+
+/*
+    Settings of the synthetic datasource. Every ticker gets a path of its own from its ticker, exchange and the
+    seed, so the same settings always give the same bars. Tickers can be given a model of their own in models.
+*/
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct SyntheticConfig {
+    pub seed: u64,
+    pub model: PriceModel,
+    // models of particular tickers by their TICKER.EXCHANGE code, a [synthetic.models] table in the config
+    pub models: HashMap<String, PriceModel>,
+    pub initial_price: f64,
+    // mean volume of a daily bar before any splits
+    pub base_volume: f64,
+    // first day of every path, bars are only generated from here on
+    pub origin: String,
+    // intraday bars are only generated for this many days before the current date, like vendors limit history
+    pub intraday_history_days: i64,
+    pub defects: Defects,
+}
+
+impl Default for SyntheticConfig {
+    fn default() -> Self {
+        Self {
+            seed: 42,
+            model: PriceModel::default(),
+            models: HashMap::new(),
+            initial_price: 100.0,
+            base_volume: 1_000_000.0,
+            origin: "2000-01-03".to_string(),
+            intraday_history_days: 120,
+            defects: Defects::default(),
+        }
+    }
+}
+
+impl SyntheticConfig {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            ..Default::default()
+        }
+    }
+
+    pub fn model(mut self, model: PriceModel) -> Self {
+        self.model = model;
+        self
+    }
+
+    pub fn ticker_model(mut self, ticker: &str, exchange: &str, model: PriceModel) -> Self {
+        self.models.insert(format!("{}.{}", ticker, exchange), model);
+        self
+    }
+
+    pub fn defects(mut self, defects: Defects) -> Self {
+        self.defects = defects;
+        self
+    }
+
+    // the model of a ticker, its own if it has one
+    pub fn model_for(&self, ticker: &str, exchange: &str) -> &PriceModel {
+        self.models.get(&format!("{}.{}", ticker, exchange)).unwrap_or(&self.model)
+    }
+}
@@ -0,0 +1,30 @@
+use bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::eod_models::OhlcvMetaData;
+
+/*
+    Tick Models, stored in collections ending in _ticks, e.g., equity_trade_ticks and equity_quote_ticks
+*/
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TradeTick {
+    pub datetime: DateTime,
+    pub price: f64,
+    pub size: f64,
+    // sale condition codes as reported by the venue
+    pub conditions: Vec<i32>,
+    // venue the trade printed on, when the source reports it
+    pub exchange: Option<String>,
+    pub metadata: OhlcvMetaData,
+}
+
+// top of the book at a point in time
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuoteTick {
+    pub datetime: DateTime,
+    pub bid: f64,
+    pub ask: f64,
+    pub bid_size: f64,
+    pub ask_size: f64,
+    pub metadata: OhlcvMetaData,
+}
@@ -0,0 +1,205 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use mongodb::bson;
+
+// datetimes that carry their own utc offset, rfc3339 is tried before these
+const OFFSET_DATETIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S%.f%:z",
+    "%Y-%m-%d %H:%M:%S%.f%:z",
+    "%Y-%m-%dT%H:%M:%S%.f%z",
+    "%Y-%m-%d %H:%M:%S%.f%z",
+];
+// datetimes without an offset, which are in the timezone of whoever wrote them
+const NAIVE_DATETIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+];
+const DATE_FORMATS: [&str; 2] = ["%Y-%m-%d", "%Y/%m/%d"];
+// compact ISO 8601 dates, only read from exactly 8 digits as they are integers too
+const COMPACT_DATE_FORMAT: &str = "%Y%m%d";
+// integers this large are unix milliseconds, as 1e11 unix seconds is in the year 5138
+const MAX_UNIX_SECONDS: i64 = 100_000_000_000;
+
+fn midnight_utc(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0).expect("midnight is always a valid time").and_utc()
+}
+
+/*
+    Parses the absolute forms a date can take, in the order they are tried:
+    - rfc3339 and other ISO 8601 datetimes with an offset, e.g., 2024-01-02T14:30:00+09:00
+    - datetimes without an offset, which are taken to be in the given timezone
+    - dates, which are stored at midnight UTC of that date whatever the timezone, like daily bars
+    - compact dates like 20240102, before unix timestamps so they are not read as seconds in 1970
+    - unix timestamps in seconds or milliseconds
+    Returns None when the input is none of these.
+*/
+fn parse_absolute(input: &str, timezone: Tz) -> Result<Option<DateTime<Utc>>> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(input) {
+        return Ok(Some(datetime.with_timezone(&Utc)));
+    }
+    for format in OFFSET_DATETIME_FORMATS {
+        if let Ok(datetime) = DateTime::parse_from_str(input, format) {
+            return Ok(Some(datetime.with_timezone(&Utc)));
+        }
+    }
+    for format in NAIVE_DATETIME_FORMATS {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(input, format) {
+            // an hour that happens twice when clocks go back is read as the first of the two
+            let local = timezone
+                .from_local_datetime(&datetime)
+                .earliest()
+                .ok_or_else(|| anyhow!("parse_datetime() {} does not exist in timezone {}", input, timezone))?;
+            return Ok(Some(local.with_timezone(&Utc)));
+        }
+    }
+    for format in DATE_FORMATS {
+        if let Ok(date) = NaiveDate::parse_from_str(input, format) {
+            return Ok(Some(midnight_utc(date)));
+        }
+    }
+    if input.len() == 8 && input.bytes().all(|byte| byte.is_ascii_digit()) {
+        if let Ok(date) = NaiveDate::parse_from_str(input, COMPACT_DATE_FORMAT) {
+            return Ok(Some(midnight_utc(date)));
+        }
+    }
+    if let Ok(timestamp) = input.parse::<i64>() {
+        let datetime = match timestamp.abs() < MAX_UNIX_SECONDS {
+            true => Utc.timestamp_opt(timestamp, 0).single(),
+            false => Utc.timestamp_millis_opt(timestamp).single(),
+        };
+        return datetime
+            .map(Some)
+            .ok_or_else(|| anyhow!("parse_datetime() unix timestamp {} is out of range", input));
+    }
+    Ok(None)
+}
+
+/*
+    Parses the relative forms a user can type, all of them at midnight UTC except "now":
+    - "now", "today", "yesterday"
+    - "ytd", the first day of the current year
+    - a signed amount of days, weeks, months or years from today, e.g., "-5y", "-3m", "+2w", "-10d"
+    Returns None when the input is none of these.
+*/
+fn parse_relative(input: &str, now: DateTime<Utc>) -> Result<Option<DateTime<Utc>>> {
+    let today = now.date_naive();
+    let date = match input.to_lowercase().as_str() {
+        "now" => return Ok(Some(now)),
+        "today" => today,
+        "yesterday" => today - Duration::days(1),
+        "ytd" => NaiveDate::from_ymd_opt(today.year(), 1, 1).expect("january 1st is always a valid date"),
+        relative => {
+            // split by characters, byte indices would panic inside multibyte input such as "-5ä"
+            let (negative, offset) = match (relative.strip_prefix('-'), relative.strip_prefix('+')) {
+                (Some(offset), _) => (true, offset),
+                (_, Some(offset)) => (false, offset),
+                _ => return Ok(None),
+            };
+            let (amount, unit) = match offset.char_indices().last() {
+                Some((index, _)) => offset.split_at(index),
+                None => return Ok(None),
+            };
+            let amount = match amount.parse::<u32>() {
+                Ok(amount) => amount,
+                Err(_) => return Ok(None),
+            };
+            let shifted = match (unit, negative) {
+                ("d", true) => today.checked_sub_signed(Duration::days(amount as i64)),
+                ("d", false) => today.checked_add_signed(Duration::days(amount as i64)),
+                ("w", true) => today.checked_sub_signed(Duration::weeks(amount as i64)),
+                ("w", false) => today.checked_add_signed(Duration::weeks(amount as i64)),
+                ("m", true) => today.checked_sub_months(Months::new(amount)),
+                ("m", false) => today.checked_add_months(Months::new(amount)),
+                ("y", true) => today.checked_sub_months(Months::new(amount.saturating_mul(12))),
+                ("y", false) => today.checked_add_months(Months::new(amount.saturating_mul(12))),
+                _ => return Ok(None),
+            };
+            shifted.ok_or_else(|| anyhow!("parse_datetime() {} is out of range", input))?
+        }
+    };
+    Ok(Some(midnight_utc(date)))
+}
+
+// parses a date typed by a user, which may be relative to the current time
+pub fn parse_datetime(input: &str) -> Result<bson::DateTime> {
+    parse_datetime_relative_to(input, Utc::now())
+}
+
+pub fn parse_datetime_relative_to(input: &str, now: DateTime<Utc>) -> Result<bson::DateTime> {
+    let input = input.trim();
+    let datetime = match parse_relative(input, now)? {
+        Some(datetime) => Some(datetime),
+        None => parse_absolute(input, Tz::UTC)?,
+    };
+    datetime.map(bson::DateTime::from_chrono).ok_or_else(|| {
+        anyhow!(
+            "parse_datetime() could not parse {:?} as a date, datetime, unix timestamp or relative date like -5y",
+            input
+        )
+    })
+}
+
+// parses a date in a datasource response, where naive datetimes are in the source timezone
+pub fn parse_source_datetime(input: &str, source_timezone: Tz) -> Result<bson::DateTime> {
+    let input = input.trim();
+    parse_absolute(input, source_timezone)?
+        .map(bson::DateTime::from_chrono)
+        .ok_or_else(|| anyhow!("parse_source_datetime() could not parse {:?} as a date or datetime", input))
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(datetime: &str) -> bson::DateTime {
+        let datetime = NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M:%S").unwrap();
+        bson::DateTime::from_chrono(datetime.and_utc())
+    }
+
+    #[test]
+    fn test_parse_datetime() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 15, 13, 45, 0).unwrap();
+        let parse = |input: &str| parse_datetime_relative_to(input, now);
+
+        assert_eq!(parse("2024-01-02").unwrap(), utc("2024-01-02 00:00:00"));
+        assert_eq!(parse("2024/01/02").unwrap(), utc("2024-01-02 00:00:00"));
+        assert_eq!(parse("2024-01-02 14:30:00").unwrap(), utc("2024-01-02 14:30:00"));
+        assert_eq!(parse("2024-01-02T14:30").unwrap(), utc("2024-01-02 14:30:00"));
+        assert_eq!(parse("2024-01-02T14:30:00Z").unwrap(), utc("2024-01-02 14:30:00"));
+        assert_eq!(parse("2024-01-02T14:30:00.250+09:00").unwrap().timestamp_millis(), utc("2024-01-02 05:30:00").timestamp_millis() + 250);
+        assert_eq!(parse("2024-01-02 14:30:00-0500").unwrap(), utc("2024-01-02 19:30:00"));
+        assert_eq!(parse("20240102").unwrap(), utc("2024-01-02 00:00:00"));
+        assert_eq!(parse("1704205800").unwrap(), utc("2024-01-02 14:30:00"));
+        // 8 digits that are no date are still unix seconds
+        assert_eq!(parse("86400000").unwrap(), utc("1972-09-27 00:00:00"));
+        assert_eq!(parse("1704205800000").unwrap(), utc("2024-01-02 14:30:00"));
+
+        assert_eq!(parse("now").unwrap(), bson::DateTime::from_chrono(now));
+        assert_eq!(parse(" Today ").unwrap(), utc("2024-05-15 00:00:00"));
+        assert_eq!(parse("yesterday").unwrap(), utc("2024-05-14 00:00:00"));
+        assert_eq!(parse("ytd").unwrap(), utc("2024-01-01 00:00:00"));
+        assert_eq!(parse("-5y").unwrap(), utc("2019-05-15 00:00:00"));
+        assert_eq!(parse("-3m").unwrap(), utc("2024-02-15 00:00:00"));
+        assert_eq!(parse("-2w").unwrap(), utc("2024-05-01 00:00:00"));
+        assert_eq!(parse("+10d").unwrap(), utc("2024-05-25 00:00:00"));
+
+        for input in ["", "2024-13-01", "01/02/2024", "-5x", "tomorrow", "2024-01-02T25:00:00", "é", "-5ä", "+ä5", "2024-01-0é"] {
+            assert!(parse(input).is_err(), "{:?} should not parse", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_source_datetime() {
+        // naive datetimes are in the source timezone, dates stay at midnight UTC like daily bars
+        let tokyo = Tz::Asia__Tokyo;
+        assert_eq!(parse_source_datetime("2024-01-02 09:00:00", tokyo).unwrap(), utc("2024-01-02 00:00:00"));
+        assert_eq!(parse_source_datetime("2024-01-02", tokyo).unwrap(), utc("2024-01-02 00:00:00"));
+        assert_eq!(parse_source_datetime("2024-01-02T09:00:00+00:00", tokyo).unwrap(), utc("2024-01-02 09:00:00"));
+        assert!(parse_source_datetime("2024-03-10 02:30:00", Tz::America__New_York).is_err());
+        assert!(parse_source_datetime("-5y", Tz::UTC).is_err());
+    }
+}
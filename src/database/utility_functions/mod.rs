@@ -1,6 +1,7 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use bson::DateTime as BsonDateTime;
-use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+use chrono_tz::Tz;
 use futures::future;
 use mongodb::bson;
 use polars::prelude::*;
@@ -8,28 +9,12 @@ use reqwest::Client;
 use std::{collections::HashMap, io::Cursor};
 
 use crate::database::models::eod_models::MongoTickerParams;
+use crate::database::models::schema_models::{CollectionType, Granularity};
 
-/*------------------------------ DATE UTILITY FUNCTIONS ------------------------------*/
-pub fn string_to_datetime(date: &str) -> bson::DateTime {
-    match date {
-        _ if date.len() <= 10 => {
-            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
-                .expect("Could not parse date string in %Y-%m-%d to NativeDate object");
-            let datetime = date.and_hms_opt(0, 0, 0)
-                .expect("Could not convert NativeDate to NativeDateTime object for date string in format %Y-%m-%d");
-            let datetime_utc: DateTime<Utc> = Utc.from_utc_datetime(&datetime);
-            bson::DateTime::from_chrono(datetime_utc)
-        }
-        _ => {
-            let datetime = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").expect(
-                "Could not parse date string in %Y-%m-%d %H:%M:%S to NativeDateTime object",
-            );
-            let datetime_utc: DateTime<Utc> = Utc.from_utc_datetime(&datetime);
-            bson::DateTime::from_chrono(datetime_utc)
-        }
-    }
-}
+pub mod date_parsing;
+pub use date_parsing::{parse_datetime, parse_source_datetime};
 
+/*------------------------------ DATE UTILITY FUNCTIONS ------------------------------*/
 pub fn get_current_date_string() -> String {
     let current_date = Utc::now();
     let current_date_string = current_date.format("%Y-%m-%d").to_string();
@@ -41,36 +26,175 @@ pub fn get_current_datetime_bson() -> bson::DateTime {
     bson::DateTime::from_chrono(current_date)
 }
 
+// timezone an eod exchange code trades in, UTC for markets that trade around the clock
+pub fn exchange_timezone(exchange: &str) -> Tz {
+    match exchange {
+        "US" | "NYSE" | "NASDAQ" | "BATS" | "AMEX" | "OTC" => Tz::America__New_York,
+        "TO" | "V" | "NEO" => Tz::America__Toronto,
+        "SA" => Tz::America__Sao_Paulo,
+        "LSE" | "IL" => Tz::Europe__London,
+        "XETRA" | "F" | "BE" | "DU" | "HM" | "HA" | "MU" | "STU" => Tz::Europe__Berlin,
+        "PA" => Tz::Europe__Paris,
+        "AS" => Tz::Europe__Amsterdam,
+        "MC" => Tz::Europe__Madrid,
+        "MI" => Tz::Europe__Rome,
+        "SW" => Tz::Europe__Zurich,
+        "ST" => Tz::Europe__Stockholm,
+        "JSE" => Tz::Africa__Johannesburg,
+        "NSE" | "BSE" => Tz::Asia__Kolkata,
+        "SHG" | "SHE" => Tz::Asia__Shanghai,
+        "HK" => Tz::Asia__Hong_Kong,
+        "KO" | "KQ" => Tz::Asia__Seoul,
+        "TSE" => Tz::Asia__Tokyo,
+        "AU" => Tz::Australia__Sydney,
+        _ => Tz::UTC,
+    }
+}
+
+/*
+    Currency an eod ticker is quoted in, as an ISO 4217 code or the minor unit a market quotes in:
+    - forex pairs are quoted in their second currency, e.g., EURUSD in USD
+    - crypto pairs in the currency after the dash, e.g., BTC-USD in USD
+    - everything else in the currency of its exchange, None where that is not known or where the
+      exchange lists instruments in several currencies, e.g., the IL international order book
+*/
+pub fn instrument_currency(ticker: &str, exchange: &str) -> Option<String> {
+    let currency = match exchange {
+        "FOREX" if ticker.len() == 6 => return Some(ticker[3..].to_uppercase()),
+        "CC" => return ticker.split_once('-').map(|(_, currency)| currency.to_uppercase()),
+        "US" | "NYSE" | "NASDAQ" | "BATS" | "AMEX" | "OTC" => "USD",
+        "TO" | "V" | "NEO" => "CAD",
+        "SA" => "BRL",
+        // the London and Johannesburg exchanges quote in pence and cents
+        "LSE" => "GBX",
+        "JSE" => "ZAC",
+        "XETRA" | "F" | "BE" | "DU" | "HM" | "HA" | "MU" | "STU" | "PA" | "AS" | "MC" | "MI" => "EUR",
+        "SW" => "CHF",
+        "ST" => "SEK",
+        "NSE" | "BSE" => "INR",
+        "SHG" | "SHE" => "CNY",
+        "HK" => "HKD",
+        "KO" | "KQ" => "KRW",
+        "TSE" => "JPY",
+        "AU" => "AUD",
+        _ => return None,
+    };
+    Some(currency.to_string())
+}
+
+// the ISO 4217 currency of a minor unit and the value of one unit in it, e.g., GBX is 0.01 GBP
+pub fn major_currency(currency: &str) -> (String, f64) {
+    match currency {
+        "GBX" | "GBp" => ("GBP".to_string(), 0.01),
+        "ZAC" => ("ZAR".to_string(), 0.01),
+        "ILA" => ("ILS".to_string(), 0.01),
+        _ => (currency.to_uppercase(), 1.0),
+    }
+}
+
+// length of a bar in a collection going by its interval suffix, e.g., 1d or 5m, 0 for ticks and live quotes
+pub fn collection_interval_millis(collection_name: &str) -> i64 {
+    CollectionType::parse(collection_name)
+        .granularity
+        .map_or(0, |granularity| granularity.millis())
+}
+
+pub fn parse_timezone(timezone: &str) -> Result<Tz> {
+    timezone
+        .parse::<Tz>()
+        .map_err(|e| anyhow!("parse_timezone() {} is not an IANA timezone: {}", timezone, e))
+}
+
+// years in a tenor such as 1D, 2W, 3M or 10Y, the form yield curve tenors are stored and requested in
+pub fn parse_tenor(tenor: &str) -> Result<f64> {
+    let normalized = tenor.trim().to_uppercase();
+    // split by characters, tenors are passed in by callers and may end in a multibyte character
+    let (amount, unit) = normalized.split_at(normalized.char_indices().last().map_or(0, |(index, _)| index));
+    let years_per_unit = match unit {
+        "D" => 1.0 / 365.0,
+        "W" => 7.0 / 365.0,
+        "M" => 1.0 / 12.0,
+        "Y" => 1.0,
+        _ => return Err(anyhow!("parse_tenor() {:?} does not end in D, W, M or Y", tenor)),
+    };
+    match amount.parse::<f64>() {
+        Ok(amount) if amount > 0.0 => Ok(amount * years_per_unit),
+        _ => Err(anyhow!("parse_tenor() {:?} does not start with a positive number", tenor)),
+    }
+}
+
+// series are stored as UTC instants, so datetime columns are read back as UTC aware polars datetimes
+pub fn utc_datetime_series(name: &str, millis: Int64Chunked) -> Series {
+    millis
+        .into_datetime(TimeUnit::Milliseconds, Some("UTC".to_string()))
+        .into_series()
+        .with_name(name)
+}
+
+/*
+    Localizes the UTC datetime column of a series to an exchange timezone.
+    - intraday bars are instants, so they keep their instant and are only shown in the new timezone
+    - daily bars are left at midnight UTC of their trading date, the date key every exchange shares, so a
+      Tokyo and a New York bar for the same date still join on the same value
+*/
+pub fn localize_datetimes(df: &DataFrame, timezone: Tz, daily: bool) -> Result<DataFrame> {
+    if daily {
+        return Ok(df.clone());
+    }
+    let localized = df
+        .column("datetime")?
+        .cast(&DataType::Int64)?
+        .i64()?
+        .clone()
+        .into_datetime(TimeUnit::Milliseconds, Some(timezone.name().to_string()))
+        .into_series()
+        .with_name("datetime");
+
+    let mut df = df.clone();
+    df.with_column(localized)?;
+    Ok(df)
+}
+
 fn is_weekday(date: NaiveDate) -> bool {
     let weekday = date.weekday();
     weekday != Weekday::Sat && weekday != Weekday::Sun
 }
 
+// whether a weekday falls after the date of date1, up to and including the date of date2
 pub fn has_business_day_between(date1: BsonDateTime, date2: BsonDateTime) -> bool {
     let date1: DateTime<Utc> = date1.into();
     let date2: DateTime<Utc> = date2.into();
     let mut current_date = date1.date_naive();
     let end_date = date2.date_naive();
-    while current_date <= end_date {
+    while current_date < end_date {
+        current_date = current_date.succ_opt().unwrap();
         if is_weekday(current_date) {
-            return false;
+            return true;
         }
-        current_date = current_date.succ_opt().unwrap();
     }
-    true
+    false
 }
 
+// whether a series stored up to last has a bar after it by now: intraday ones once their interval has passed,
+// any other once a business day has
+pub fn needs_refresh(collection_name: &str, last: BsonDateTime, now: BsonDateTime) -> bool {
+    match collection_interval_millis(collection_name) {
+        interval if interval > 0 && interval < 86_400_000 => now.timestamp_millis() - last.timestamp_millis() >= interval,
+        _ => has_business_day_between(last, now),
+    }
+}
+
+// request windows of unix seconds from from to to, the last one cut short at to
 pub fn get_timestamps_tuple(
     from: BsonDateTime,
     to: BsonDateTime,
-    granularity: &str,
+    granularity: Granularity,
 ) -> Result<Vec<(i64, i64)>> {
-    let mut duration = 0;
-    match granularity.chars().last().unwrap() {
-        'm' => duration = 120,
-        'h' => duration = 350, // CHANGE TO 7200 ONCE ON PAID
-        _ => eprintln!("Invalid granularity"),
-    }
+    let duration = match granularity {
+        Granularity::Minutes(_) => 120,
+        Granularity::Hours(_) => 350, // CHANGE TO 7200 ONCE ON PAID
+        granularity => return Err(anyhow!("get_timestamps_tuple() {} bars are not requested in windows", granularity)),
+    };
 
     let start_date: DateTime<Utc> = from.into();
     let end_date: DateTime<Utc> = to.into();
@@ -80,6 +204,7 @@ pub fn get_timestamps_tuple(
     while current_date < end_date {
         let next_date = current_date + Duration::days(duration);
         if next_date > end_date {
+            date_tuples.push((current_date.timestamp(), end_date.timestamp()));
             break;
         }
         let current_date_timestamp = current_date.timestamp();
@@ -91,17 +216,60 @@ pub fn get_timestamps_tuple(
     Ok(date_tuples)
 }
 
+/*------------------------------ INPUT UTILITY FUNCTIONS ------------------------------*/
+// parses lines of "ticker,exchange,collection,source[,from[,to]]". Blank lines and lines starting
+// with # are skipped. from and to take anything parse_datetime() does, such as 2020-01-01 or -5y,
+// and default to 1970-01-01 and the current date
+pub fn parse_ticker_list(contents: &str) -> Result<Vec<MongoTickerParams>> {
+    let mut tickers = Vec::new();
+    for (line_number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = line.split(',').map(|field| field.trim()).collect::<Vec<&str>>();
+        if fields.len() < 4 || fields.len() > 6 {
+            return Err(anyhow!(
+                "parse_ticker_list() line {} should be ticker,exchange,collection,source[,from[,to]]: {}",
+                line_number + 1,
+                line
+            ));
+        }
+        let parse_field = |index: usize, default: &str| {
+            parse_datetime(fields.get(index).copied().unwrap_or(default))
+                .map_err(|e| anyhow!("parse_ticker_list() line {}: {}", line_number + 1, e))
+        };
+        tickers.push(MongoTickerParams {
+            ticker: fields[0].to_string(),
+            exchange: fields[1].to_string(),
+            series_collection_name: fields[2].to_string(),
+            source: fields[3].to_string(),
+            from: parse_field(4, "1970-01-01")?,
+            to: parse_field(5, "today")?,
+        });
+    }
+    Ok(tickers)
+}
+
 /*------------------------------ NETWORK UTILITY FUNCTIONS ------------------------------*/
+/*
+    Fetches every url and parses its json rows into a df, one result per url so a ticker whose request
+    fails can be told apart from the rest. Network errors, error statuses and responses that are not json
+    rows are errors, an empty "[]" response has no rows and is left out.
+*/
 pub async fn async_http_request(
     client: Client,
     urls: HashMap<String, MongoTickerParams>,
-) -> Result<Vec<(MongoTickerParams, DataFrame)>> {
+) -> Vec<(MongoTickerParams, Result<DataFrame>)> {
     let bodies = future::join_all(urls.into_iter().map(|(url, param)| {
         let client = client.clone(); // reqwest doc states cloning client is cheap so leave this
         async move {
             log::info!("async_http_request() fetching url: {}", url);
-            let resp = client.get(url).send().await.unwrap();
-            let result = resp.bytes().await;
+            let result = async {
+                let resp = client.get(url).send().await?.error_for_status()?;
+                Ok::<_, anyhow::Error>(resp.bytes().await?)
+            }
+            .await;
             (param, result)
         }
     }))
@@ -109,24 +277,109 @@ pub async fn async_http_request(
 
     let mut response_vec = Vec::new();
     for (param, body) in bodies {
-        match body {
-            Ok(body) => {
-                let body_string = String::from_utf8_lossy(&body).into_owned();
-                if body_string != "[]" {
-                    let cursor = Cursor::new(body_string);
-                    let df = JsonReader::new(cursor).finish();
-                    match df {
-                        Ok(df) => response_vec.push((param, df)),
-                        Err(e) => eprintln!(
-                            "async_http_request() Could not parse response to DataFrame: {}",
-                            e
-                        ),
-                    }
-                }
+        let df = body.and_then(|body| {
+            let body_string = String::from_utf8_lossy(&body).into_owned();
+            if body_string.trim() == "[]" {
+                return Ok(None);
+            }
+            JsonReader::new(Cursor::new(body_string))
+                .finish()
+                .map(Some)
+                .map_err(|e| anyhow!("async_http_request() could not parse response to DataFrame: {}", e))
+        });
+        match df {
+            Ok(Some(df)) => response_vec.push((param, Ok(df))),
+            Ok(None) => (),
+            Err(e) => {
+                log::error!("async_http_request() request for {}.{} failed: {:#}", param.ticker, param.exchange, e);
+                response_vec.push((param, Err(e)));
             }
-            Err(e) => eprintln!("Got an error: {}", e),
         }
     }
 
-    Ok(response_vec)
+    response_vec
+}
+
+/*---------------------------------- TESTS ---------------------------------- */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime_df(dates: &[&str]) -> DataFrame {
+        let millis = dates
+            .iter()
+            .map(|date| parse_source_datetime(date, Tz::UTC).unwrap().timestamp_millis())
+            .collect::<Vec<i64>>();
+        DataFrame::new(vec![utc_datetime_series("datetime", Int64Chunked::from_vec("datetime", millis))]).unwrap()
+    }
+
+    fn local_strings(df: &DataFrame, timezone: Tz) -> Vec<String> {
+        df.column("datetime")
+            .unwrap()
+            .cast(&DataType::Int64)
+            .unwrap()
+            .i64()
+            .unwrap()
+            .into_no_null_iter()
+            .map(|millis| {
+                let utc = bson::DateTime::from_millis(millis).to_chrono();
+                utc.with_timezone(&timezone).format("%Y-%m-%d %H:%M").to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_has_business_day_between() {
+        let date = |date: &str| parse_source_datetime(date, Tz::UTC).unwrap();
+        // thursday's bar is stored, friday is a business day after it
+        assert!(has_business_day_between(date("2024-01-04"), date("2024-01-05 22:00:00")));
+        // friday's bar is stored, nothing new until monday
+        assert!(!has_business_day_between(date("2024-01-05"), date("2024-01-05 23:00:00")));
+        assert!(!has_business_day_between(date("2024-01-05"), date("2024-01-07 12:00:00")));
+        assert!(has_business_day_between(date("2024-01-05"), date("2024-01-08")));
+        assert!(!has_business_day_between(date("2024-01-08"), date("2024-01-05")));
+    }
+
+    #[test]
+    fn test_intraday_refresh() {
+        // an hourly series a few hours out of date is refreshed on the same day, in one short window
+        let date = |date: &str| parse_source_datetime(date, Tz::UTC).unwrap();
+        let (last, now) = (date("2024-01-05 09:00:00"), date("2024-01-05 13:30:00"));
+        assert!(needs_refresh("equity_spot_1h", last, now));
+        assert!(!needs_refresh("equity_spot_1h", last, date("2024-01-05 09:45:00")));
+        assert!(!needs_refresh("equity_spot_1d", date("2024-01-05"), now));
+        assert_eq!(
+            get_timestamps_tuple(last, now, Granularity::Hours(1)).unwrap(),
+            vec![(last.timestamp_millis() / 1000, now.timestamp_millis() / 1000)]
+        );
+
+        // whole windows are followed by the partial one up to the end
+        let windows = get_timestamps_tuple(date("2024-01-01"), date("2024-07-01"), Granularity::Minutes(5)).unwrap();
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[1], (date("2024-04-30").timestamp_millis() / 1000, date("2024-07-01").timestamp_millis() / 1000));
+        assert!(get_timestamps_tuple(date("2024-01-01"), date("2024-07-01"), Granularity::Days(1)).is_err());
+    }
+
+    #[test]
+    fn test_localize_datetimes() {
+        // daily bars from Tokyo and New York stay on the same date key, so they join on the same date
+        let daily = datetime_df(&["2024-01-02", "2024-07-01"]);
+        let tokyo = localize_datetimes(&daily, exchange_timezone("TSE"), true).unwrap();
+        let new_york = localize_datetimes(&daily, exchange_timezone("US"), true).unwrap();
+        assert_eq!(tokyo, new_york);
+        assert_eq!(local_strings(&tokyo, Tz::UTC), vec!["2024-01-02 00:00", "2024-07-01 00:00"]);
+
+        // intraday bars are instants, so only the timezone they are shown in changes
+        let intraday = datetime_df(&["2024-01-02 14:30:00", "2024-07-01 13:30:00"]);
+        let new_york = localize_datetimes(&intraday, exchange_timezone("US"), false).unwrap();
+        assert_eq!(
+            new_york.column("datetime").unwrap().dtype(),
+            &DataType::Datetime(TimeUnit::Milliseconds, Some("America/New_York".to_string()))
+        );
+        assert_eq!(local_strings(&new_york, Tz::America__New_York), vec!["2024-01-02 09:30", "2024-07-01 09:30"]);
+        assert_eq!(
+            new_york.column("datetime").unwrap().cast(&DataType::Int64).unwrap(),
+            intraday.column("datetime").unwrap().cast(&DataType::Int64).unwrap()
+        );
+    }
 }
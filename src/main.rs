@@ -1,8 +1,21 @@
-use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveTime};
 use molly_db::alpha::utility_functions::{get_data, Alpha};
+use molly_db::database::database_service::{MongoDbClient, RefreshDaemon};
+
+#[tokio::main]
+async fn run_refresh_daemon() -> anyhow::Result<()> {
+    // daily series refresh at 18:00 local time of their exchange, after it has closed
+    let daily_refresh_time = NaiveTime::from_hms_opt(18, 0, 0).unwrap();
+    let mongo_client = MongoDbClient::new().await;
+    RefreshDaemon::new(mongo_client, daily_refresh_time).run().await
+}
 
 fn main() {
     env_logger::init();
+    if std::env::args().any(|arg| arg == "--daemon") {
+        run_refresh_daemon().unwrap();
+        return;
+    }
     let dfs = get_data().unwrap();
     let start_date = NaiveDate::from_ymd_opt(2010, 1, 1)
         .unwrap()